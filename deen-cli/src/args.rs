@@ -0,0 +1,221 @@
+use std::{convert::TryFrom, io};
+
+use deen::{DecodeLimits, DEFAULT_MAX_DEPTH};
+
+pub const USAGE: &str = "\
+Usage:
+    deen decode <schema> (<file> | --hex <hex>) [options]
+    deen encode <schema> [<json file>] [options]
+    deen import-ksy <ksy file> [--format deen|rust] [--output <file>]
+    deen export <schema> [--format ksy|lua] [--parser <name>] [--arg <name=value>]
+
+<file> and <json file> can be `-` for standard input, which is also the default for encode.
+import-ksy translates a Kaitai Struct description into a schema, or into Rust source with
+`deen!` parsers. export describes a schema as a Kaitai Struct file or a Wireshark dissector.
+
+Options:
+    --parser <name>      Parser to use, the first one of the schema by default
+    --arg <name=value>   Integer argument for a parameter of the parser, can be repeated
+    --offset <bytes>     Skip bytes before decoding
+    --count <n>          Decode the parser n times in a row, as a JSON array
+    --format <format>    Decode output, `json` (default) or `tree`
+                         import-ksy output, `deen` (default) or `rust`
+                         export output, `ksy` (default) or `lua`
+    --trace              Print an annotated hexdump of the decode instead of the value
+    --max-depth <n>      Maximum nesting depth of parsers when decoding, 64 by default
+    --max-alloc <bytes>  Maximum size of a single decoded value, 64 MiB by default
+    --max-elements <n>   Maximum length of a decoded sequence, 1048576 by default
+    --output <file>      Write encoded bytes, the import or the export to a file instead of
+                         standard output
+    --hex                Print encoded bytes as hex
+    --help               Print this message
+";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Decode,
+    Encode,
+    ImportKsy,
+    Export,
+    Help,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Tree,
+    Deen,
+    Rust,
+    Ksy,
+    Lua,
+}
+
+#[derive(Debug)]
+pub struct Args {
+    pub command: Command,
+    pub schema: String,
+    /// Binary input for decode, JSON input for encode.
+    pub input: Option<String>,
+    pub hex: Option<String>,
+    pub parser: Option<String>,
+    pub args: Vec<(String, i128)>,
+    pub offset: usize,
+    pub count: Option<usize>,
+    pub format: Format,
+    pub trace: bool,
+    pub output: Option<String>,
+    pub hex_output: bool,
+    /// Limits for decoding, so that lengths and nesting in the input can not exhaust memory or
+    /// the stack.
+    pub limits: DecodeLimits,
+}
+
+const DEFAULT_MAX_ALLOCATION: usize = 64 << 20;
+const DEFAULT_MAX_ELEMENTS: usize = 1 << 20;
+
+fn usage_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// Integer in decimal or with a `0x` prefix.
+pub fn parse_int(s: &str) -> io::Result<i128> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+    let v = match digits.strip_prefix("0x") {
+        Some(hex) => i128::from_str_radix(hex, 16),
+        None => digits.parse(),
+    };
+    let v = v.map_err(|_| usage_error(format!("invalid number {}", s)))?;
+    Ok(if negative { -v } else { v })
+}
+
+fn parse_usize(arg: &str, s: &str) -> io::Result<usize> {
+    usize::try_from(parse_int(s)?)
+        .map_err(|_| usage_error(format!("{} needs a non-negative number, found {}", arg, s)))
+}
+
+impl Args {
+    pub fn parse(args: &[String]) -> io::Result<Args> {
+        let mut args = args.iter();
+        let command = match args.next().map(String::as_str) {
+            Some("decode") => Command::Decode,
+            Some("encode") => Command::Encode,
+            Some("import-ksy") => Command::ImportKsy,
+            Some("export") => Command::Export,
+            Some("help") | Some("--help") | Some("-h") | None => Command::Help,
+            Some(other) => return Err(usage_error(format!("unknown command {}", other))),
+        };
+        let mut parsed = Args {
+            command,
+            schema: String::new(),
+            input: None,
+            hex: None,
+            parser: None,
+            args: Vec::new(),
+            offset: 0,
+            count: None,
+            format: Format::Json,
+            trace: false,
+            output: None,
+            hex_output: false,
+            limits: DecodeLimits::new()
+                .max_depth(DEFAULT_MAX_DEPTH)
+                .max_allocation(DEFAULT_MAX_ALLOCATION)
+                .max_elements(DEFAULT_MAX_ELEMENTS),
+        };
+        if command == Command::Help {
+            return Ok(parsed);
+        }
+
+        let mut positional = Vec::new();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| usage_error(format!("{} needs a value", arg)))
+            };
+            match arg.as_str() {
+                "--hex" if command == Command::Decode => parsed.hex = Some(value()?.clone()),
+                "--hex" => parsed.hex_output = true,
+                "--parser" => parsed.parser = Some(value()?.clone()),
+                "--arg" => {
+                    let v = value()?;
+                    let (name, v) = v
+                        .split_once('=')
+                        .ok_or_else(|| usage_error(format!("expected name=value, found {}", v)))?;
+                    parsed.args.push((name.to_string(), parse_int(v)?));
+                }
+                "--offset" => parsed.offset = parse_usize(arg, value()?)?,
+                "--count" => parsed.count = Some(parse_usize(arg, value()?)?),
+                "--format" => {
+                    parsed.format = match value()?.as_str() {
+                        "json" => Format::Json,
+                        "tree" => Format::Tree,
+                        "deen" => Format::Deen,
+                        "rust" => Format::Rust,
+                        "ksy" => Format::Ksy,
+                        "lua" => Format::Lua,
+                        other => return Err(usage_error(format!("unknown format {}", other))),
+                    }
+                }
+                "--trace" => parsed.trace = true,
+                "--max-depth" => {
+                    parsed.limits = parsed.limits.max_depth(parse_usize(arg, value()?)?)
+                }
+                "--max-alloc" => {
+                    parsed.limits = parsed.limits.max_allocation(parse_usize(arg, value()?)?)
+                }
+                "--max-elements" => {
+                    parsed.limits = parsed.limits.max_elements(parse_usize(arg, value()?)?)
+                }
+                "--output" => parsed.output = Some(value()?.clone()),
+                "--help" | "-h" => parsed.command = Command::Help,
+                s if s.starts_with("--") => {
+                    return Err(usage_error(format!("unknown option {}", s)))
+                }
+                _ => positional.push(arg.clone()),
+            }
+        }
+        if parsed.command == Command::Help {
+            return Ok(parsed);
+        }
+
+        let import = command == Command::ImportKsy;
+        let mut positional = positional.into_iter();
+        parsed.schema = positional.next().ok_or_else(|| {
+            usage_error(format!(
+                "missing {} file",
+                if import { "ksy" } else { "schema" }
+            ))
+        })?;
+        if !import && command != Command::Export {
+            parsed.input = positional.next();
+        }
+        if let Some(extra) = positional.next() {
+            return Err(usage_error(format!("unexpected argument {}", extra)));
+        }
+        if command == Command::Decode && parsed.input.is_some() == parsed.hex.is_some() {
+            return Err(usage_error(
+                "decode needs either an input file or --hex".to_string(),
+            ));
+        }
+        match (command, parsed.format) {
+            (Command::ImportKsy, Format::Json) => parsed.format = Format::Deen,
+            (Command::ImportKsy, Format::Deen) | (Command::ImportKsy, Format::Rust) => {}
+            (Command::Export, Format::Json) => parsed.format = Format::Ksy,
+            (Command::Export, Format::Ksy) | (Command::Export, Format::Lua) => {}
+            (Command::Decode, Format::Json)
+            | (Command::Decode, Format::Tree)
+            | (Command::Encode, Format::Json)
+            | (Command::Encode, Format::Tree) => {}
+            (_, format) => {
+                return Err(usage_error(format!(
+                    "format {:?} is not supported by this command",
+                    format
+                )))
+            }
+        }
+        Ok(parsed)
+    }
+}
@@ -0,0 +1,145 @@
+use std::io;
+
+use deen::{BinOp, Expr, UnOp};
+
+/// `CamelCase` to `snake_case`.
+pub fn snake(camel: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in camel.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+pub fn unsupported_error(target: &str, problems: &[String]) -> io::Error {
+    let mut message = format!("can not export to {}:", target);
+    for problem in problems {
+        message.push_str("\n    ");
+        message.push_str(problem);
+    }
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Operators of a target language whose expressions, unlike Rust's, keep booleans and
+/// integers apart.
+pub struct Syntax {
+    pub and: &'static str,
+    pub or: &'static str,
+    pub not: &'static str,
+    pub ne: &'static str,
+    pub xor: &'static str,
+    pub div: &'static str,
+    /// Integer 1 or 0 from a boolean, with `{}` for the condition.
+    pub to_int: &'static str,
+}
+
+fn is_bool(e: &Expr) -> bool {
+    match e {
+        Expr::Unary(UnOp::Not, _) => true,
+        Expr::Binary(op, ..) => op.precedence() <= BinOp::Eq.precedence(),
+        _ => false,
+    }
+}
+
+/// `e` in the target syntax. `variant` translates enum variants and fails for unknown ones.
+pub fn translate(
+    e: &Expr,
+    syntax: &Syntax,
+    want_bool: bool,
+    variant: &dyn Fn(&str, &str) -> Option<String>,
+) -> Result<String, String> {
+    let text = translate_prec(e, syntax, 0, variant)?;
+    Ok(match (want_bool, is_bool(e)) {
+        (true, false) => format!("{} {} 0", paren(e, text), syntax.ne),
+        (false, true) => syntax.to_int.replace("{}", &text),
+        _ => text,
+    })
+}
+
+fn paren(e: &Expr, text: String) -> String {
+    match e {
+        Expr::Binary(..) => format!("({})", text),
+        _ => text,
+    }
+}
+
+fn translate_prec(
+    e: &Expr,
+    syntax: &Syntax,
+    prec: u8,
+    variant: &dyn Fn(&str, &str) -> Option<String>,
+) -> Result<String, String> {
+    Ok(match e {
+        Expr::Int(v) => v.to_string(),
+        Expr::Hex(v) if *v < 0 => format!("-{:#x}", v.unsigned_abs()),
+        Expr::Hex(v) => format!("{:#x}", v),
+        Expr::Var(name) => name.clone(),
+        Expr::Variant(e, v) => {
+            variant(e, v).ok_or_else(|| format!("unknown enum variant {}::{}", e, v))?
+        }
+        Expr::Unary(UnOp::Neg, inner) => {
+            format!("-{}", translate_prec(inner, syntax, u8::MAX, variant)?)
+        }
+        Expr::Unary(UnOp::Not, inner) => {
+            let inner_text = translate(inner, syntax, true, variant)?;
+            format!("{} {}", syntax.not, paren(inner, inner_text))
+        }
+        Expr::Binary(op, l, r) => {
+            let p = op.precedence();
+            let (l, r, symbol) = match op {
+                BinOp::And | BinOp::Or => (
+                    paren_below(l, p, translate(l, syntax, true, variant)?),
+                    paren_below(r, p + 1, translate(r, syntax, true, variant)?),
+                    if *op == BinOp::And {
+                        syntax.and
+                    } else {
+                        syntax.or
+                    },
+                ),
+                _ => {
+                    let symbol = match op {
+                        BinOp::Ne => syntax.ne,
+                        BinOp::BitXor => syntax.xor,
+                        BinOp::Div => syntax.div,
+                        op => op.symbol(),
+                    };
+                    (
+                        operand(l, syntax, p, variant)?,
+                        operand(r, syntax, p + 1, variant)?,
+                        symbol,
+                    )
+                }
+            };
+            let text = format!("{} {} {}", l, symbol, r);
+            if p < prec {
+                format!("({})", text)
+            } else {
+                text
+            }
+        }
+    })
+}
+
+// Integer operand of an arithmetic or comparison operator.
+fn operand(
+    e: &Expr,
+    syntax: &Syntax,
+    prec: u8,
+    variant: &dyn Fn(&str, &str) -> Option<String>,
+) -> Result<String, String> {
+    if is_bool(e) {
+        translate(e, syntax, false, variant)
+    } else {
+        translate_prec(e, syntax, prec, variant)
+    }
+}
+
+fn paren_below(e: &Expr, prec: u8, text: String) -> String {
+    match e {
+        Expr::Binary(op, ..) if op.precedence() < prec => format!("({})", text),
+        _ => text,
+    }
+}
@@ -0,0 +1,174 @@
+use std::{convert::TryFrom, io};
+
+use deen::{DynItem, DynSchema, DynType, DynValue};
+use serde_json::{Number, Value};
+
+use crate::args::parse_int;
+
+fn invalid_data_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parses hex digits, ignoring whitespace, `:` separators and a `0x` prefix.
+pub fn from_hex(s: &str) -> io::Result<Vec<u8>> {
+    let s = s.trim();
+    let digits: Vec<u8> = s
+        .strip_prefix("0x")
+        .unwrap_or(s)
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace() && *c != b':')
+        .collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(invalid_data_error("odd number of hex digits".to_string()));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).unwrap_or("");
+            u8::from_str_radix(pair, 16)
+                .map_err(|_| invalid_data_error(format!("invalid hex byte {}", pair)))
+        })
+        .collect()
+}
+
+/// Bytes are hex strings, enum values their variant names and integers that do not fit into
+/// 64 bits decimal strings.
+pub fn to_json(value: &DynValue) -> Value {
+    match value {
+        DynValue::Int(v) => {
+            if let Ok(v) = i64::try_from(*v) {
+                Value::from(v)
+            } else if let Ok(v) = u64::try_from(*v) {
+                Value::from(v)
+            } else {
+                Value::String(v.to_string())
+            }
+        }
+        DynValue::Float(v) => Number::from_f64(*v).map_or(Value::Null, Value::Number),
+        DynValue::Bytes(b) => Value::String(to_hex(b)),
+        DynValue::String(s) => Value::String(s.clone()),
+        DynValue::List(items) => Value::Array(items.iter().map(to_json).collect()),
+        DynValue::Struct(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, v)| (name.clone(), to_json(v)))
+                .collect(),
+        ),
+        DynValue::Enum(name) => Value::String(name.clone()),
+        DynValue::Option(v) => v.as_deref().map_or(Value::Null, to_json),
+    }
+}
+
+/// Converts JSON produced by `to_json` back, using the schema for the types.
+pub fn from_json(schema: &DynSchema, parser: &str, json: &Value) -> io::Result<DynValue> {
+    let s = schema
+        .structs
+        .iter()
+        .find(|s| s.parser == parser)
+        .ok_or_else(|| invalid_data_error(format!("unknown parser {}", parser)))?;
+    let object = match json {
+        Value::Object(object) => object,
+        v => {
+            return Err(invalid_data_error(format!(
+                "expected {} object, found {}",
+                s.name, v
+            )))
+        }
+    };
+    let mut fields = Vec::new();
+    field_types(&s.items, &mut fields);
+
+    let mut values = Vec::new();
+    for (name, ty) in fields {
+        if let Some(v) = object.get(name) {
+            let v = type_from_json(schema, ty, v)
+                .map_err(|e| invalid_data_error(format!("{}.{}: {}", s.name, name, e)))?;
+            values.push((name.to_string(), v));
+        }
+    }
+    if let Some(unknown) = object.keys().find(|k| !values.iter().any(|(n, _)| n == *k)) {
+        return Err(invalid_data_error(format!(
+            "{} has no field {}",
+            s.name, unknown
+        )));
+    }
+    Ok(DynValue::Struct(values))
+}
+
+// Fields in order of first appearance, including all branches of `if` chains.
+fn field_types<'a>(items: &'a [DynItem], fields: &mut Vec<(&'a str, &'a DynType)>) {
+    for item in items {
+        match item {
+            DynItem::Field { name, ty } if !fields.iter().any(|(n, _)| n == name) => {
+                fields.push((name, ty));
+            }
+            DynItem::If { branches, .. } => {
+                for b in branches {
+                    field_types(&b.items, fields);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn type_from_json(schema: &DynSchema, ty: &DynType, json: &Value) -> io::Result<DynValue> {
+    let mismatch =
+        |expected: &str| invalid_data_error(format!("expected {}, found {}", expected, json));
+    Ok(match ty {
+        DynType::Int(_) | DynType::VarU64 | DynType::VarI64 => match json {
+            Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(v), _) => DynValue::Int(v.into()),
+                (_, Some(v)) => DynValue::Int(v.into()),
+                _ => return Err(mismatch("an integer")),
+            },
+            Value::String(s) => DynValue::Int(parse_int(s).map_err(|_| mismatch("an integer"))?),
+            _ => return Err(mismatch("an integer")),
+        },
+        DynType::Fixed(..) | DynType::Scaled { .. } => {
+            DynValue::Float(json.as_f64().ok_or_else(|| mismatch("a number"))?)
+        }
+        DynType::Bytes(_) => match json {
+            Value::String(s) => DynValue::Bytes(from_hex(s)?),
+            _ => return Err(mismatch("a hex string")),
+        },
+        DynType::Utf8(_) => DynValue::String(
+            json.as_str()
+                .ok_or_else(|| mismatch("a string"))?
+                .to_string(),
+        ),
+        DynType::Repeat(_, inner) => match json {
+            Value::Array(items) => DynValue::List(
+                items
+                    .iter()
+                    .map(|v| type_from_json(schema, inner, v))
+                    .collect::<io::Result<_>>()?,
+            ),
+            _ => return Err(mismatch("an array")),
+        },
+        DynType::Take { inner, .. } | DynType::Prefixed { inner, .. } => {
+            type_from_json(schema, inner, json)?
+        }
+        DynType::Enum(_) => match json {
+            Value::String(s) => DynValue::Enum(s.clone()),
+            _ => return Err(mismatch("a variant name")),
+        },
+        DynType::Optional { item, inner, .. } => match json {
+            Value::Null => DynValue::Option(None),
+            v => {
+                let is_enum = schema.enums.iter().any(|e| e.name == *item);
+                let v = if is_enum {
+                    type_from_json(schema, &DynType::Enum(item.clone()), v)?
+                } else {
+                    type_from_json(schema, inner, v)?
+                };
+                DynValue::Option(Some(Box::new(v)))
+            }
+        },
+        DynType::Parser { name, .. } => from_json(schema, name, json)?,
+    })
+}
@@ -0,0 +1,1240 @@
+use std::{convert::TryFrom, error, fmt, io};
+
+use deen::{
+    BinOp, DynItem, DynPresence, DynSchema, DynStruct, DynType, Endian, Expr, IntType, UnOp,
+};
+use serde_yaml::{Mapping, Value};
+
+use crate::{
+    export::{snake, translate, unsupported_error, Syntax},
+    rust::rust_int,
+};
+
+/// Constructs of a `.ksy` file that could not be translated, with their path in the file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KsyError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for KsyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("can not import .ksy:")?;
+        for problem in &self.problems {
+            write!(f, "\n    {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for KsyError {}
+
+impl From<KsyError> for io::Error {
+    fn from(e: KsyError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// Translates a Kaitai Struct description into a dynamic schema, with one parser per type and
+/// the root type first.
+///
+/// Supported are `seq` attributes with integer, `str`, byte array and user types, `size`,
+/// `size-eos`, `contents`, `enum`, `if` and `repeat: expr` or `eos`, as well as `types` with
+/// `params`, `enums` and `meta` endianness. Everything else is reported in the error, all
+/// problems at once.
+pub fn import_ksy(text: &str) -> Result<DynSchema, KsyError> {
+    let root: Value = serde_yaml::from_str(text).map_err(|e| KsyError {
+        problems: vec![format!("invalid YAML: {}", e)],
+    })?;
+    let mut importer = Importer {
+        problems: Vec::new(),
+        types: Vec::new(),
+        enums: Vec::new(),
+    };
+    let source = importer.import(&root);
+    if !importer.problems.is_empty() {
+        return Err(KsyError {
+            problems: importer.problems,
+        });
+    }
+    source.parse().map_err(|e| KsyError {
+        problems: vec![format!("generated schema is invalid: {}\n{}", e, source)],
+    })
+}
+
+struct Type<'a> {
+    id: String,
+    path: String,
+    spec: &'a Mapping,
+    endian: Option<&'static str>,
+}
+
+struct Enum {
+    id: String,
+    path: String,
+    variants: Vec<(String, i128)>,
+    // Integer deener of the first attribute using the enum.
+    int: Option<String>,
+}
+
+struct Importer<'a> {
+    problems: Vec<String>,
+    types: Vec<Type<'a>>,
+    enums: Vec<Enum>,
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where",
+    "while", "async", "await", "dyn",
+];
+
+/// `snake_case` to `CamelCase`.
+fn camel(id: &str) -> String {
+    id.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(c) => c.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn field_name(id: &str) -> String {
+    if KEYWORDS.contains(&id) {
+        format!("{}_", id)
+    } else {
+        id.to_string()
+    }
+}
+
+fn key<'v>(map: &'v Mapping, key: &str) -> Option<&'v Value> {
+    map.get(Value::String(key.to_string()))
+}
+
+fn int_value(v: &Value) -> Option<i128> {
+    match v {
+        Value::Number(n) => n
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| n.as_u64().map(i128::from)),
+        Value::String(s) => {
+            let s = s.replace('_', "");
+            match s.strip_prefix("0x") {
+                Some(hex) => i128::from_str_radix(hex, 16).ok(),
+                None => s.parse().ok(),
+            }
+        }
+        _ => None,
+    }
+}
+
+fn scalar_text(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+// Field of the Rust struct and deener expression of an attribute.
+struct Deener {
+    text: String,
+    rust: String,
+    // Integer deener of an enum, which `Optional` wraps instead.
+    int: Option<String>,
+}
+
+impl<'a> Importer<'a> {
+    fn problem(&mut self, path: &str, message: impl fmt::Display) {
+        self.problems.push(format!("{}: {}", path, message));
+    }
+
+    fn import(&mut self, root: &'a Value) -> String {
+        let root = match root {
+            Value::Mapping(root) => root,
+            _ => {
+                self.problem("/", "expected a mapping");
+                return String::new();
+            }
+        };
+        let meta = key(root, "meta").and_then(Value::as_mapping);
+        let id = meta.and_then(|m| key(m, "id")).and_then(Value::as_str);
+        let id = match id {
+            Some(id) => id.to_string(),
+            None => {
+                self.problem("meta", "`id` is missing");
+                return String::new();
+            }
+        };
+        self.collect_type(id, String::new(), root, None);
+        // Nested types are added before their parent, the root goes first.
+        self.types.rotate_right(1);
+
+        let mut structs = Vec::new();
+        for i in 0..self.types.len() {
+            structs.push(self.dyn_struct(i));
+        }
+        let enums = self
+            .enums
+            .iter()
+            .filter_map(|e| {
+                let int = e.int.as_ref()?;
+                let variants: Vec<_> = e
+                    .variants
+                    .iter()
+                    .map(|(name, v)| format!("    {} = {},\n", camel(name), v))
+                    .collect();
+                Some(format!(
+                    "#[deen({})]\nenum {} {{\n{}}}",
+                    int,
+                    camel(&e.id),
+                    variants.concat()
+                ))
+            })
+            .collect::<Vec<_>>();
+        enums
+            .into_iter()
+            .chain(structs)
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    // Adds the type, its nested types and its enums.
+    fn collect_type(
+        &mut self,
+        id: String,
+        path: String,
+        spec: &'a Mapping,
+        parent_endian: Option<&'static str>,
+    ) {
+        let mut endian = parent_endian;
+        for (k, v) in spec {
+            let k = k.as_str().unwrap_or("");
+            match k {
+                "meta" => {
+                    let meta = v.as_mapping();
+                    match meta.and_then(|m| key(m, "endian")).map(|e| e.as_str()) {
+                        Some(Some("le")) => endian = Some("le"),
+                        Some(Some("be")) => endian = Some("be"),
+                        Some(_) => self.problem(
+                            &format!("{}meta.endian", path),
+                            "only `le` or `be` is supported",
+                        ),
+                        None => {}
+                    }
+                    for (mk, _) in meta.into_iter().flatten() {
+                        let mk = mk.as_str().unwrap_or("");
+                        if ![
+                            "id",
+                            "endian",
+                            "title",
+                            "license",
+                            "file-extension",
+                            "ks-version",
+                            "encoding",
+                            "xref",
+                            "tags",
+                            "application",
+                        ]
+                        .contains(&mk)
+                        {
+                            self.problem(&format!("{}meta.{}", path, mk), "is not supported");
+                        }
+                    }
+                }
+                "seq" | "doc" | "doc-ref" | "params" | "-webide-representation" => {}
+                "types" => {
+                    for (name, t) in v.as_mapping().into_iter().flatten() {
+                        let name = name.as_str().unwrap_or("").to_string();
+                        let type_path = format!("{}types.{}.", path, name);
+                        match t.as_mapping() {
+                            Some(t) => self.collect_type(name, type_path, t, endian),
+                            None => self.problem(&type_path, "expected a mapping"),
+                        }
+                    }
+                }
+                "enums" => {
+                    for (name, e) in v.as_mapping().into_iter().flatten() {
+                        let name = name.as_str().unwrap_or("").to_string();
+                        let enum_path = format!("{}enums.{}", path, name);
+                        self.collect_enum(name, enum_path, e);
+                    }
+                }
+                "instances" => {
+                    self.problem(&format!("{}instances", path), "instances are not supported")
+                }
+                k => self.problem(&format!("{}{}", path, k), "is not supported"),
+            }
+        }
+        self.types.push(Type {
+            id,
+            path,
+            spec,
+            endian,
+        });
+    }
+
+    fn collect_enum(&mut self, id: String, path: String, spec: &Value) {
+        let mut variants = Vec::new();
+        for (v, name) in spec.as_mapping().into_iter().flatten() {
+            let name = match name {
+                Value::Mapping(m) => key(m, "id").and_then(Value::as_str),
+                name => name.as_str(),
+            };
+            match (int_value(v), name) {
+                (Some(v), Some(name)) => variants.push((name.to_string(), v)),
+                _ => self.problem(&path, format!("invalid variant {:?}", v)),
+            }
+        }
+        self.enums.push(Enum {
+            id,
+            path,
+            variants,
+            int: None,
+        });
+    }
+
+    fn dyn_struct(&mut self, i: usize) -> String {
+        let (id, path, spec) = (
+            self.types[i].id.clone(),
+            self.types[i].path.clone(),
+            self.types[i].spec,
+        );
+        let mut names = Vec::new();
+        let mut params = Vec::new();
+        for (n, p) in key(spec, "params")
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .enumerate()
+        {
+            let param_path = format!("{}params[{}]", path, n);
+            let p = p.as_mapping();
+            let name = p.and_then(|p| key(p, "id")).and_then(Value::as_str);
+            let ty = p.and_then(|p| key(p, "type")).and_then(Value::as_str);
+            match (name, ty.and_then(|t| self.int_type(&param_path, t, i))) {
+                (Some(name), Some(int)) => {
+                    names.push(name.to_string());
+                    params.push(format!("{}: {}", field_name(name), int_rust(&int)));
+                }
+                _ => self.problem(&param_path, "only integer parameters are supported"),
+            }
+        }
+
+        let mut items = Vec::new();
+        let seq = key(spec, "seq").and_then(Value::as_sequence);
+        for (n, attr) in seq.into_iter().flatten().enumerate() {
+            let mut attr_path = format!("{}seq[{}]", path, n);
+            let attr = match attr.as_mapping() {
+                Some(attr) => attr,
+                None => {
+                    self.problem(&attr_path, "expected a mapping");
+                    continue;
+                }
+            };
+            if let Some(id) = key(attr, "id").and_then(Value::as_str) {
+                attr_path = format!("{} ({})", attr_path, id);
+            }
+            items.extend(self.attribute(i, &attr_path, attr, &names));
+            if let Some(id) = key(attr, "id").and_then(Value::as_str) {
+                names.push(id.to_string());
+            }
+        }
+
+        let params = if params.is_empty() {
+            String::new()
+        } else {
+            format!("({})", params.join(", "))
+        };
+        let items: Vec<_> = items.iter().map(|i| format!("    {},\n", i)).collect();
+        format!(
+            "struct {}Parser{} for {} {{\n{}}}",
+            camel(&id),
+            params,
+            camel(&id),
+            items.concat()
+        )
+    }
+
+    // Integer deener for a Kaitai integer type like `u2` or `s4le`.
+    fn int_type(&mut self, path: &str, ty: &str, type_index: usize) -> Option<String> {
+        let (signed, rest) = match ty.split_at(1.min(ty.len())) {
+            ("u", rest) => (false, rest),
+            ("s", rest) => (true, rest),
+            _ => return None,
+        };
+        let (size, endian) = match rest.find(|c: char| !c.is_ascii_digit()) {
+            Some(i) => (&rest[..i], Some(&rest[i..])),
+            None => (rest, None),
+        };
+        let bits = match size {
+            "1" => 8,
+            "2" => 16,
+            "4" => 32,
+            "8" => 64,
+            _ => return None,
+        };
+        let endian = match endian {
+            Some("le") => "le",
+            Some("be") => "be",
+            Some(_) => return None,
+            None if bits == 8 => "",
+            None => match self.types[type_index].endian {
+                Some(endian) => endian,
+                None => {
+                    self.problem(path, format!("{} needs `meta.endian`", ty));
+                    "be"
+                }
+            },
+        };
+        let name = format!("{}{}{}", if signed { 'I' } else { 'U' }, bits, endian);
+        IntType::from_name(&name).map(|_| name)
+    }
+
+    fn attribute(
+        &mut self,
+        type_index: usize,
+        path: &str,
+        attr: &Mapping,
+        names: &[String],
+    ) -> Vec<String> {
+        for (k, _) in attr {
+            let k = k.as_str().unwrap_or("");
+            let known = [
+                "id",
+                "type",
+                "size",
+                "size-eos",
+                "if",
+                "repeat",
+                "repeat-expr",
+                "contents",
+                "enum",
+                "encoding",
+                "doc",
+                "doc-ref",
+                "-orig-id",
+            ];
+            if !known.contains(&k) {
+                self.problem(path, format!("`{}` is not supported", k));
+            }
+        }
+
+        if let Some(contents) = key(attr, "contents") {
+            if attr.len() > key(attr, "id").map_or(1, |_| 2) + key(attr, "doc").map_or(0, |_| 1) {
+                self.problem(path, "`contents` can not be combined with other keys");
+            }
+            return self.contents(path, contents);
+        }
+        let id = match key(attr, "id").and_then(Value::as_str) {
+            Some(id) => id,
+            None => {
+                self.problem(path, "`id` is missing");
+                return Vec::new();
+            }
+        };
+
+        let size = match key(attr, "size").map(|s| self.expr(path, s, names)) {
+            Some(Some(size)) => Some(size),
+            Some(None) => return Vec::new(),
+            None => None,
+        };
+        let size_eos = key(attr, "size-eos").and_then(Value::as_bool) == Some(true);
+        let mut deener = match self.base(type_index, path, attr, size, size_eos) {
+            Some(deener) => deener,
+            None => return Vec::new(),
+        };
+
+        match key(attr, "repeat").and_then(Value::as_str) {
+            Some("expr") => {
+                let count = key(attr, "repeat-expr").and_then(|c| self.expr(path, c, names));
+                let count = match count {
+                    Some(count) => count,
+                    None => {
+                        self.problem(path, "`repeat: expr` needs `repeat-expr`");
+                        return Vec::new();
+                    }
+                };
+                deener = Deener {
+                    text: format!("Repeat::new({}, {})", count, deener.text),
+                    rust: format!("Vec<{}>", deener.rust),
+                    int: None,
+                };
+            }
+            Some("eos") => {
+                deener = Deener {
+                    text: format!("Repeat::remaining({})", deener.text),
+                    rust: format!("Vec<{}>", deener.rust),
+                    int: None,
+                };
+            }
+            Some(other) => {
+                self.problem(path, format!("`repeat: {}` is not supported", other));
+                return Vec::new();
+            }
+            None => {}
+        }
+
+        if let Some(condition) = key(attr, "if") {
+            let condition = match self.expr(path, condition, names) {
+                Some(condition) => condition,
+                None => return Vec::new(),
+            };
+            let space = if deener.rust.ends_with('>') { " " } else { "" };
+            deener = Deener {
+                text: format!(
+                    "Optional::<{}{}>::wrap({}).decode_when(|| {})",
+                    deener.rust,
+                    space,
+                    deener.int.unwrap_or(deener.text),
+                    condition
+                ),
+                rust: format!("Option<{}>", deener.rust),
+                int: None,
+            };
+        }
+        vec![format!("{} ~ {}", field_name(id), deener.text)]
+    }
+
+    fn base(
+        &mut self,
+        type_index: usize,
+        path: &str,
+        attr: &Mapping,
+        size: Option<String>,
+        size_eos: bool,
+    ) -> Option<Deener> {
+        let ty = match key(attr, "type") {
+            None => None,
+            Some(Value::String(ty)) => Some(ty.as_str()),
+            Some(_) => {
+                self.problem(path, "switch types are not supported");
+                return None;
+            }
+        };
+        let sized = |name: &str, rust: &str| match (&size, size_eos) {
+            (Some(size), _) => Some(Deener {
+                text: format!("{}::new({})", name, size),
+                rust: rust.to_string(),
+                int: None,
+            }),
+            (None, true) => Some(Deener {
+                text: format!("{}::remaining()", name),
+                rust: rust.to_string(),
+                int: None,
+            }),
+            (None, false) => None,
+        };
+        let deener = match ty {
+            None => sized("Bytes", "Vec<u8>"),
+            Some("str") => {
+                let encoding = key(attr, "encoding")
+                    .or_else(|| {
+                        let meta = key(self.types[0].spec, "meta")?.as_mapping()?;
+                        key(meta, "encoding")
+                    })
+                    .and_then(Value::as_str)
+                    .unwrap_or("UTF-8");
+                if !["utf-8", "utf8", "ascii"].contains(&encoding.to_lowercase().as_str()) {
+                    self.problem(path, format!("encoding {} is not supported", encoding));
+                    return None;
+                }
+                sized("Utf8", "String")
+            }
+            Some(ty) => {
+                if let Some(int) = self.int_type(path, ty, type_index) {
+                    if size.is_some() || size_eos {
+                        self.problem(path, "`size` of an integer is not supported");
+                        return None;
+                    }
+                    return match key(attr, "enum").and_then(Value::as_str) {
+                        Some(e) => self.enum_deener(path, e, int),
+                        None => Some(Deener {
+                            rust: int_rust(&int),
+                            text: int,
+                            int: None,
+                        }),
+                    };
+                }
+                let user = self.user_type(path, ty)?;
+                match size {
+                    Some(size) => Some(Deener {
+                        text: format!("Take::new({}, {})", size, user.text),
+                        rust: user.rust,
+                        int: None,
+                    }),
+                    None => Some(user),
+                }
+            }
+        };
+        if deener.is_none() {
+            self.problem(path, "byte arrays and strings need `size` or `size-eos`");
+        }
+        if key(attr, "enum").is_some() {
+            self.problem(path, "`enum` needs an integer type");
+        }
+        deener
+    }
+
+    fn enum_deener(&mut self, path: &str, name: &str, int: String) -> Option<Deener> {
+        let e = match self.enums.iter_mut().find(|e| e.id == name) {
+            Some(e) => e,
+            None => {
+                self.problem(path, format!("unknown enum {}", name));
+                return None;
+            }
+        };
+        match &e.int {
+            Some(other) if *other != int => {
+                let message = format!(
+                    "{}: used as both {} and {}, which is not supported",
+                    e.path, other, int
+                );
+                self.problems.push(message);
+                None
+            }
+            _ => {
+                e.int = Some(int.clone());
+                Some(Deener {
+                    text: format!("{}::deen()", camel(name)),
+                    rust: camel(name),
+                    int: Some(int),
+                })
+            }
+        }
+    }
+
+    // `name` or `name(arg, ..)`.
+    fn user_type(&mut self, path: &str, ty: &str) -> Option<Deener> {
+        let (name, args) = match ty.find('(') {
+            Some(i) if ty.ends_with(')') => (&ty[..i], Some(&ty[i + 1..ty.len() - 1])),
+            Some(_) => {
+                self.problem(path, format!("invalid type {}", ty));
+                return None;
+            }
+            None => (ty, None),
+        };
+        let index = match self.types.iter().position(|t| t.id == name) {
+            Some(index) => index,
+            None => {
+                let builtin = name == "strz"
+                    || (name.starts_with(['f', 'b'])
+                        && name[1..].starts_with(|c: char| c.is_ascii_digit()));
+                let message = if builtin {
+                    format!("type {} is not supported", name)
+                } else {
+                    format!("unknown type {}", name)
+                };
+                self.problem(path, message);
+                return None;
+            }
+        };
+        let params: Vec<String> = key(self.types[index].spec, "params")
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(|p| key(p.as_mapping()?, "id")?.as_str().map(String::from))
+            .collect();
+        let args: Vec<&str> = match args {
+            Some(args) => split_args(args),
+            None => Vec::new(),
+        };
+        if args.len() != params.len() {
+            self.problem(path, format!("{} takes {} arguments", name, params.len()));
+            return None;
+        }
+        let mut text = format!("{}Parser", camel(name));
+        if !args.is_empty() {
+            let mut fields = Vec::new();
+            for (param, arg) in params.iter().zip(args) {
+                let arg = self.translate(path, arg, &[])?;
+                fields.push(format!("{}: {}", field_name(param), arg));
+            }
+            text = format!("{} {{ {} }}", text, fields.join(", "));
+        }
+        Some(Deener {
+            text,
+            rust: camel(name),
+            int: None,
+        })
+    }
+
+    fn contents(&mut self, path: &str, contents: &Value) -> Vec<String> {
+        let bytes: Option<Vec<u8>> = match contents {
+            Value::String(s) => Some(s.as_bytes().to_vec()),
+            Value::Sequence(items) => items
+                .iter()
+                .map(|i| match i {
+                    Value::String(s) => Some(s.as_bytes().to_vec()),
+                    i => int_value(i)
+                        .and_then(|b| u8::try_from(b).ok())
+                        .map(|b| vec![b]),
+                })
+                .collect::<Option<Vec<_>>>()
+                .map(|chunks| chunks.concat()),
+            i => int_value(i)
+                .and_then(|b| u8::try_from(b).ok())
+                .map(|b| vec![b]),
+        };
+        let bytes = match bytes {
+            Some(bytes) if !bytes.is_empty() => bytes,
+            _ => {
+                self.problem(path, "`contents` must be bytes or a string");
+                return Vec::new();
+            }
+        };
+        // Big endian integers of the largest fitting width.
+        let mut tags = Vec::new();
+        let mut rest = &bytes[..];
+        while !rest.is_empty() {
+            let n = [16, 8, 6, 4, 3, 2, 1]
+                .iter()
+                .copied()
+                .find(|n| *n <= rest.len())
+                .unwrap();
+            let value = rest[..n].iter().fold(0u128, |v, b| v << 8 | u128::from(*b));
+            let int = if n == 1 {
+                "U8".to_string()
+            } else {
+                format!("U{}be", n * 8)
+            };
+            tags.push(format!("Tag::new({}, {:#x})", int, value));
+            rest = &rest[n..];
+        }
+        tags
+    }
+
+    fn expr(&mut self, path: &str, v: &Value, names: &[String]) -> Option<String> {
+        match scalar_text(v) {
+            Some(text) => self.translate(path, &text, names),
+            None => {
+                self.problem(path, format!("invalid expression {:?}", v));
+                None
+            }
+        }
+    }
+
+    // Kaitai expression to Rust syntax. Names are checked against `names` unless it is empty.
+    fn translate(&mut self, path: &str, expr: &str, names: &[String]) -> Option<String> {
+        let chars: Vec<char> = expr.chars().collect();
+        let mut out = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if c.is_whitespace() {
+                i += 1;
+                continue;
+            }
+            if c.is_alphabetic() || c == '_' {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|c| c.is_alphanumeric() || **c == '_')
+                    .count();
+                let word: String = chars[i..i + len].iter().collect();
+                i += len;
+                let next_is_path = chars[i..].starts_with(&[':', ':']);
+                let token = match word.as_str() {
+                    "and" => "&&".to_string(),
+                    "or" => "||".to_string(),
+                    "not" => "!".to_string(),
+                    "true" | "false" => word,
+                    w if w.starts_with('_') => {
+                        self.problem(path, format!("`{}` in expressions is not supported", w));
+                        return None;
+                    }
+                    _ if next_is_path => {
+                        let len = chars[i + 2..]
+                            .iter()
+                            .take_while(|c| c.is_alphanumeric() || **c == '_')
+                            .count();
+                        let variant: String = chars[i + 2..i + 2 + len].iter().collect();
+                        i += 2 + len;
+                        format!("{}::{}", camel(&word), camel(&variant))
+                    }
+                    _ => {
+                        if !names.is_empty() && !names.contains(&word) {
+                            self.problem(path, format!("unknown name {} in `{}`", word, expr));
+                            return None;
+                        }
+                        field_name(&word)
+                    }
+                };
+                push_token(&mut out, &token);
+                continue;
+            }
+            if c.is_ascii_digit() {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|c| c.is_alphanumeric() || **c == '_')
+                    .count();
+                let literal: String = chars[i..i + len].iter().collect();
+                i += len;
+                if chars.get(i) == Some(&'.') && chars.get(i + 1).is_some_and(char::is_ascii_digit)
+                {
+                    self.problem(path, format!("floats in `{}` are not supported", expr));
+                    return None;
+                }
+                push_token(&mut out, &literal);
+                continue;
+            }
+            let two: String = chars[i..].iter().take(2).collect();
+            let op = if ["==", "!=", "<=", ">=", "<<", ">>"].contains(&two.as_str()) {
+                two
+            } else if "+-*/%&|^<>()".contains(c) {
+                c.to_string()
+            } else {
+                let what = match c {
+                    '.' => "member access".to_string(),
+                    '?' => "the ternary operator".to_string(),
+                    '"' | '\'' => "strings".to_string(),
+                    c => format!("`{}`", c),
+                };
+                self.problem(path, format!("{} in `{}` is not supported", what, expr));
+                return None;
+            };
+            i += op.len();
+            push_token(&mut out, &op);
+        }
+        Some(out)
+    }
+}
+
+// Joins tokens with spaces, except inside parentheses and after unary operators.
+fn push_token(out: &mut String, token: &str) {
+    let glue = out.is_empty()
+        || out.ends_with('(')
+        || out.ends_with('!')
+        || token == ")"
+        || (out.ends_with('-') && {
+            let before = out[..out.len() - 1].trim_end();
+            before.is_empty() || before.ends_with(|c: char| "(+-*/%&|^<>=!".contains(c))
+        });
+    if !glue {
+        out.push(' ');
+    }
+    out.push_str(token);
+}
+
+fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !args[start..].trim().is_empty() {
+        parts.push(args[start..].trim());
+    }
+    parts
+}
+
+fn int_rust(int: &str) -> String {
+    rust_int(IntType::from_name(int).expect("valid integer deener"))
+}
+
+const KSY: Syntax = Syntax {
+    and: "and",
+    or: "or",
+    not: "not",
+    ne: "!=",
+    xor: "^",
+    div: "/",
+    to_int: "({} ? 1 : 0)",
+};
+
+/// Describes a schema as a Kaitai Struct file, with the first parser as the top-level type and
+/// the other parsers in `types`. Deeners Kaitai has no counterpart for, like varints and named
+/// `if` chains, are reported in the error, all at once.
+pub fn export_ksy(schema: &DynSchema) -> io::Result<String> {
+    let root = schema
+        .structs
+        .first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "schema has no parsers"))?;
+    let mut export = Exporter {
+        schema,
+        problems: Vec::new(),
+    };
+
+    let mut ksy = Mapping::new();
+    let mut meta = Mapping::new();
+    meta.insert("id".into(), snake(&root.name).into());
+    ksy.insert("meta".into(), Value::Mapping(meta));
+    ksy.extend(export.type_spec(root));
+    let mut types = Mapping::new();
+    for s in &schema.structs[1..] {
+        types.insert(snake(&s.name).into(), Value::Mapping(export.type_spec(s)));
+    }
+    if !types.is_empty() {
+        ksy.insert("types".into(), Value::Mapping(types));
+    }
+    let mut enums = Mapping::new();
+    for e in &schema.enums {
+        let variants = e
+            .variants
+            .iter()
+            .map(|(name, v)| (Value::from(*v as i64), Value::from(snake(name))))
+            .collect();
+        enums.insert(snake(&e.name).into(), Value::Mapping(variants));
+    }
+    if !enums.is_empty() {
+        ksy.insert("enums".into(), Value::Mapping(enums));
+    }
+
+    if !export.problems.is_empty() {
+        return Err(unsupported_error(".ksy", &export.problems));
+    }
+    serde_yaml::to_string(&ksy).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+struct Exporter<'a> {
+    schema: &'a DynSchema,
+    problems: Vec<String>,
+}
+
+fn and(cond: Option<Expr>, e: Expr) -> Expr {
+    match cond {
+        Some(cond) => Expr::binary(BinOp::And, cond, e),
+        None => e,
+    }
+}
+
+// Kaitai type of a parameter of a parser.
+fn param_type(ty: &str) -> Option<&'static str> {
+    Some(match ty {
+        "u8" => "u1",
+        "u16" => "u2",
+        "u32" => "u4",
+        "u64" | "usize" => "u8",
+        "i8" => "s1",
+        "i16" => "s2",
+        "i32" => "s4",
+        "i64" | "isize" => "s8",
+        "bool" => "bool",
+        _ => return None,
+    })
+}
+
+impl Exporter<'_> {
+    fn problem(&mut self, path: &str, message: impl fmt::Display) {
+        self.problems.push(format!("{}: {}", path, message));
+    }
+
+    fn expr(&mut self, path: &str, e: &Expr, want_bool: bool) -> String {
+        let schema = self.schema;
+        let variant = |e: &str, v: &str| {
+            let known = schema
+                .enums
+                .iter()
+                .any(|d| d.name == e && d.variants.iter().any(|(n, _)| n == v));
+            Some(format!("{}::{}", snake(e), snake(v))).filter(|_| known)
+        };
+        translate(e, &KSY, want_bool, &variant).unwrap_or_else(|message| {
+            self.problem(path, message);
+            String::new()
+        })
+    }
+
+    fn int(&mut self, path: &str, int: IntType) -> String {
+        let size = match int.bits {
+            8 | 16 | 32 | 64 => int.bits / 8,
+            bits => {
+                self.problem(path, format!("{}-bit integers are not supported", bits));
+                int.bits / 8
+            }
+        };
+        let endian = match (size, int.endian) {
+            (1, _) => "",
+            (_, Endian::Big) => "be",
+            (_, Endian::Little) => "le",
+        };
+        format!("{}{}{}", if int.signed { 's' } else { 'u' }, size, endian)
+    }
+
+    fn type_spec(&mut self, s: &DynStruct) -> Mapping {
+        let mut spec = Mapping::new();
+        let mut params = Vec::new();
+        for (name, ty) in &s.params {
+            let mut param = Mapping::new();
+            param.insert("id".into(), name.as_str().into());
+            match param_type(ty) {
+                Some(ty) => {
+                    param.insert("type".into(), ty.into());
+                }
+                None => self.problem(
+                    &format!("{}({})", s.parser, name),
+                    format!("parameters of type {} are not supported", ty),
+                ),
+            }
+            params.push(Value::Mapping(param));
+        }
+        if !params.is_empty() {
+            spec.insert("params".into(), Value::Sequence(params));
+        }
+        let mut seq = Vec::new();
+        self.items(&s.parser, &s.items, None, &mut seq);
+        spec.insert("seq".into(), Value::Sequence(seq));
+        spec
+    }
+
+    fn items(&mut self, path: &str, items: &[DynItem], cond: Option<Expr>, seq: &mut Vec<Value>) {
+        for item in items {
+            match item {
+                DynItem::Field { name, ty } => {
+                    let path = format!("{}.{}", path, name);
+                    self.attr(&path, Some(name), ty, cond.clone(), seq);
+                }
+                DynItem::Tag { ty, value } => {
+                    let path = format!("{}.{}", path, item);
+                    self.tag(&path, ty, value, cond.clone(), seq);
+                }
+                DynItem::Any(ty) => {
+                    self.attr(&format!("{}.{}", path, item), None, ty, cond.clone(), seq)
+                }
+                DynItem::Padding(len) => {
+                    let mut m = Mapping::new();
+                    let path = format!("{}.{}", path, item);
+                    m.insert("size".into(), self.expr(&path, len, false).into());
+                    self.push(&path, m, cond.clone(), seq);
+                }
+                DynItem::If {
+                    name: Some(name),
+                    branches,
+                } if branches.len() > 1 => {
+                    self.problem(
+                        &format!("{}.{}", path, name),
+                        "`if` chains with a name are not supported, use `Optional` or a parser per branch",
+                    );
+                }
+                DynItem::If { branches, .. } => {
+                    // Each branch applies if its condition holds and none of the previous did.
+                    let mut previous: Option<Expr> = None;
+                    for b in branches {
+                        let mut branch_cond = cond.clone();
+                        if let Some(previous) = &previous {
+                            branch_cond = Some(and(
+                                branch_cond,
+                                Expr::Unary(UnOp::Not, Box::new(previous.clone())),
+                            ));
+                        }
+                        if let Some(c) = &b.condition {
+                            branch_cond = Some(and(branch_cond, c.clone()));
+                            previous = Some(match previous {
+                                Some(p) => Expr::binary(BinOp::Or, p, c.clone()),
+                                None => c.clone(),
+                            });
+                        }
+                        self.items(path, &b.items, branch_cond, seq);
+                    }
+                }
+            }
+        }
+    }
+
+    // Adds `if` for the condition and appends the attribute.
+    fn push(&mut self, path: &str, mut m: Mapping, cond: Option<Expr>, seq: &mut Vec<Value>) {
+        if let Some(cond) = cond {
+            m.insert("if".into(), self.expr(path, &cond, true).into());
+        }
+        seq.push(Value::Mapping(m));
+    }
+
+    fn tag(
+        &mut self,
+        path: &str,
+        ty: &DynType,
+        value: &Expr,
+        cond: Option<Expr>,
+        seq: &mut Vec<Value>,
+    ) {
+        let mut m = Mapping::new();
+        match (ty, value.literal()) {
+            (DynType::Int(int), Some(v)) if int.bits % 8 == 0 => {
+                let n = (int.bits / 8) as usize;
+                let be = (v as u128).to_be_bytes();
+                let mut bytes = be[16 - n..].to_vec();
+                if int.endian == Endian::Little {
+                    bytes.reverse();
+                }
+                let bytes = bytes
+                    .into_iter()
+                    .map(|b| Value::from(u64::from(b)))
+                    .collect();
+                m.insert("contents".into(), Value::Sequence(bytes));
+            }
+            (DynType::Int(int), _) => {
+                m.insert("type".into(), self.int(path, *int).into());
+                m.insert("valid".into(), self.expr(path, value, false).into());
+            }
+            _ => {
+                self.problem(path, "tags must be integers");
+                return;
+            }
+        }
+        self.push(path, m, cond, seq);
+    }
+
+    fn attr(
+        &mut self,
+        path: &str,
+        id: Option<&str>,
+        mut ty: &DynType,
+        mut cond: Option<Expr>,
+        seq: &mut Vec<Value>,
+    ) {
+        let base_id = id.unwrap_or("value").to_string();
+        let mut m = Mapping::new();
+        if let Some(id) = id {
+            m.insert("id".into(), id.into());
+        }
+        let mut enum_item = None;
+        if let DynType::Optional {
+            item,
+            inner,
+            presence,
+        } = ty
+        {
+            match presence {
+                DynPresence::When(e) => cond = Some(and(cond, e.clone())),
+                DynPresence::RemainingInput => {
+                    let eof = Expr::Unary(UnOp::Not, Box::new(Expr::var("_io.eof")));
+                    cond = Some(and(cond, eof));
+                }
+                DynPresence::Byte => {
+                    let flag = format!("has_{}", base_id);
+                    let mut f = Mapping::new();
+                    f.insert("id".into(), flag.as_str().into());
+                    f.insert("type".into(), "u1".into());
+                    self.push(path, f, cond.clone(), seq);
+                    let present = Expr::binary(BinOp::Ne, Expr::var(&flag), Expr::Int(0));
+                    cond = Some(and(cond, present));
+                }
+            }
+            enum_item = self.schema.enums.iter().find(|e| e.name == *item);
+            ty = inner;
+        }
+
+        let mut repeat = None;
+        if let DynType::Repeat(count, inner) = ty {
+            repeat = Some(count);
+            ty = inner;
+        }
+        let mut size = None;
+        match ty {
+            DynType::Take { len, inner, .. } => {
+                size = Some(self.expr(path, len, false));
+                ty = inner;
+            }
+            DynType::Prefixed { len, inner, .. } => {
+                let len_id = format!("{}_len", base_id);
+                let mut l = Mapping::new();
+                l.insert("id".into(), len_id.as_str().into());
+                l.insert("type".into(), self.int(path, *len).into());
+                self.push(path, l, cond.clone(), seq);
+                size = Some(len_id);
+                ty = inner;
+            }
+            _ => {}
+        }
+
+        match (ty, enum_item) {
+            (DynType::Int(int), Some(e)) => {
+                m.insert("type".into(), self.int(path, *int).into());
+                m.insert("enum".into(), snake(&e.name).into());
+            }
+            (DynType::Int(int), None) => {
+                m.insert("type".into(), self.int(path, *int).into());
+            }
+            (DynType::Fixed(int, _), _) | (DynType::Scaled { int, .. }, _) => {
+                m.insert("type".into(), self.int(path, *int).into());
+                m.insert("doc".into(), format!("Raw value of {}", ty).into());
+            }
+            (DynType::Enum(name), _) => match self.schema.enums.iter().find(|e| e.name == *name) {
+                Some(e) => {
+                    m.insert("type".into(), self.int(path, e.int).into());
+                    m.insert("enum".into(), snake(name).into());
+                }
+                None => self.problem(path, format!("unknown enum {}", name)),
+            },
+            (DynType::Bytes(len), _) | (DynType::Utf8(len), _) => {
+                if let DynType::Utf8(_) = ty {
+                    m.insert("type".into(), "str".into());
+                    m.insert("encoding".into(), "UTF-8".into());
+                }
+                match (len, size.take()) {
+                    (Some(len), None) => {
+                        m.insert("size".into(), self.expr(path, len, false).into());
+                    }
+                    (None, Some(size)) => {
+                        m.insert("size".into(), size.into());
+                    }
+                    (None, None) => {
+                        m.insert("size-eos".into(), true.into());
+                    }
+                    (Some(_), Some(_)) => self.problem(
+                        path,
+                        format!("{} inside a length limit is not supported", ty),
+                    ),
+                }
+            }
+            (DynType::Parser { name, args }, _) => {
+                match self.schema.structs.iter().find(|s| s.parser == *name) {
+                    Some(s) => {
+                        let mut type_name = snake(&s.name);
+                        if !s.params.is_empty() {
+                            let args: Vec<String> = s
+                                .params
+                                .iter()
+                                .map(|(param, _)| match args.iter().find(|(n, _)| n == param) {
+                                    Some((_, arg)) => self.expr(path, arg, false),
+                                    None => {
+                                        self.problem(path, format!("missing argument {}", param));
+                                        String::new()
+                                    }
+                                })
+                                .collect();
+                            type_name = format!("{}({})", type_name, args.join(", "));
+                        }
+                        m.insert("type".into(), type_name.into());
+                    }
+                    None => self.problem(path, format!("unknown parser {}", name)),
+                }
+            }
+            (ty, _) => {
+                self.problem(path, format!("{} is not supported", ty));
+                return;
+            }
+        }
+        if let Some(size) = size {
+            if let DynType::Parser { .. } = ty {
+                m.insert("size".into(), size.into());
+            } else {
+                self.problem(
+                    path,
+                    format!("{} inside a length limit is not supported", ty),
+                );
+            }
+        }
+        match repeat {
+            Some(Some(count)) => {
+                m.insert("repeat".into(), "expr".into());
+                m.insert("repeat-expr".into(), self.expr(path, count, false).into());
+            }
+            Some(None) => {
+                m.insert("repeat".into(), "eos".into());
+            }
+            None => {}
+        }
+        self.push(path, m, cond, seq);
+    }
+}
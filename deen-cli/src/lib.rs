@@ -0,0 +1,281 @@
+//! The `deen` command: decodes binary data described by a text schema to JSON or a tree and
+//! encodes JSON back, see `args::USAGE`. Also imports Kaitai Struct descriptions and exports
+//! schemas as Kaitai Struct descriptions or Wireshark dissectors.
+
+mod args;
+mod export;
+mod json;
+mod ksy;
+mod lua;
+mod rust;
+
+pub use args::{Args, Command, Format, USAGE};
+pub use json::{from_hex, from_json, to_hex, to_json};
+pub use ksy::{export_ksy, import_ksy, KsyError};
+pub use lua::wireshark_dissector;
+pub use rust::{rust_int, rust_source};
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+};
+
+use deen::{DecodeLimits, Deen, DynParser, DynSchema, DynValue, Trace};
+
+/// Runs the command line `args`, without the program name.
+pub fn run(
+    args: &[String],
+    stdin: &mut dyn Read,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> io::Result<()> {
+    let args = Args::parse(args)?;
+    match args.command {
+        Command::Help => stdout.write_all(USAGE.as_bytes()),
+        Command::Decode => {
+            let schema = load_schema(&args.schema)?;
+            let parser = parser(&schema, &args)?;
+            let input = match (&args.hex, &args.input) {
+                (Some(hex), _) => from_hex(hex)?,
+                (None, Some(path)) => read_input(path, stdin)?,
+                (None, None) => unreachable!("checked by Args::parse"),
+            };
+            decode(&parser, &input, &args, stdout, stderr)
+        }
+        Command::Encode => {
+            let schema = load_schema(&args.schema)?;
+            let parser = parser(&schema, &args)?;
+            let input = read_input(args.input.as_deref().unwrap_or("-"), stdin)?;
+            let bytes = encode(&schema, &parser, &input, &args)?;
+            if args.hex_output {
+                writeln!(stdout, "{}", to_hex(&bytes))
+            } else if let Some(path) = &args.output {
+                fs::write(path, bytes)
+            } else {
+                stdout.write_all(&bytes)
+            }
+        }
+        Command::ImportKsy => {
+            let text = fs::read_to_string(&args.schema)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", args.schema, e)))?;
+            let schema = import_ksy(&text).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", args.schema, e),
+                )
+            })?;
+            let source = match args.format {
+                Format::Rust => rust_source(&schema),
+                _ => format!("{}\n", schema),
+            };
+            write_output(&args, &source, stdout)
+        }
+        Command::Export => {
+            let mut schema = load_schema(&args.schema)?;
+            // The exports describe their first parser.
+            if let Some(name) = &args.parser {
+                let i = schema
+                    .structs
+                    .iter()
+                    .position(|s| s.parser == *name)
+                    .ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::InvalidInput, format!("no parser {}", name))
+                    })?;
+                let root = schema.structs.remove(i);
+                schema.structs.insert(0, root);
+            }
+            let root = schema.structs.first().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "schema has no parsers")
+            })?;
+            let source = match args.format {
+                Format::Lua => {
+                    let args: Vec<_> = args.args.iter().map(|(n, v)| (n.as_str(), *v)).collect();
+                    wireshark_dissector(&schema, &export::snake(&root.name), &args)?
+                }
+                _ => export_ksy(&schema)?,
+            };
+            write_output(&args, &source, stdout)
+        }
+    }
+}
+
+fn write_output(args: &Args, source: &str, stdout: &mut dyn Write) -> io::Result<()> {
+    match &args.output {
+        Some(path) => fs::write(path, source),
+        None => stdout.write_all(source.as_bytes()),
+    }
+}
+
+fn read_input(path: &str, stdin: &mut dyn Read) -> io::Result<Vec<u8>> {
+    if path == "-" {
+        let mut input = Vec::new();
+        stdin.read_to_end(&mut input)?;
+        Ok(input)
+    } else {
+        fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))
+    }
+}
+
+fn load_schema(path: &str) -> io::Result<DynSchema> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+    text.parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("{}:{}", path, e)))
+}
+
+fn parser<'a>(schema: &'a DynSchema, args: &Args) -> io::Result<DynParser<'a>> {
+    let args_ref: Vec<_> = args.args.iter().map(|(n, v)| (n.as_str(), *v)).collect();
+    match &args.parser {
+        Some(name) => schema.parser(name, &args_ref),
+        None => {
+            let root = schema.structs.first().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "schema has no parsers")
+            })?;
+            schema.parser(&root.parser, &args_ref)
+        }
+    }
+}
+
+// Decodes a parser within the limits of the command line.
+struct Limited<'a, 'b> {
+    limits: DecodeLimits,
+    parser: &'a DynParser<'b>,
+}
+
+impl Deen for Limited<'_, '_> {
+    type Item = DynValue;
+
+    fn encode(&self, value: &DynValue, buf: impl Write) -> io::Result<()> {
+        self.parser.encode(value, buf)
+    }
+
+    fn decode(&self, buf: impl Read) -> io::Result<DynValue> {
+        self.limits.decode(self.parser, buf)
+    }
+}
+
+fn decode(
+    parser: &DynParser,
+    input: &[u8],
+    args: &Args,
+    stdout: &mut dyn Write,
+    stderr: &mut dyn Write,
+) -> io::Result<()> {
+    let parser = &Limited {
+        limits: args.limits,
+        parser,
+    };
+    let mut pos = args.offset.min(input.len());
+    let mut values = Vec::new();
+    for n in 0..args.count.unwrap_or(1) {
+        let rest = &input[pos..];
+        let mut unread = rest;
+        let result = parser.decode(&mut unread);
+        let decoded = rest.len() - unread.len();
+        if args.trace {
+            if args.count.is_some() {
+                writeln!(stdout, "# record {} at {:#x}", n, pos)?;
+            }
+            // Trace only the bytes of this record, unless there is an error to show in context.
+            let traced = match &result {
+                Ok(_) => &rest[..decoded],
+                Err(_) => rest,
+            };
+            write!(stdout, "{}", Trace::decode(parser, traced).1)?;
+        }
+        let value = result
+            .map_err(|e| io::Error::new(e.kind(), format!("at {:#x}: {}", pos + decoded, e)))?;
+        pos += decoded;
+        values.push(value);
+    }
+    if pos < input.len() {
+        writeln!(
+            stderr,
+            "warning: {} bytes after {:#x} were not decoded",
+            input.len() - pos,
+            pos
+        )?;
+    }
+    if args.trace {
+        return Ok(());
+    }
+
+    match args.format {
+        Format::Json => {
+            let json = match args.count {
+                Some(_) => serde_json::Value::Array(values.iter().map(to_json).collect()),
+                None => to_json(&values[0]),
+            };
+            serde_json::to_writer_pretty(&mut *stdout, &json)?;
+            writeln!(stdout)
+        }
+        Format::Tree => {
+            for (n, value) in values.iter().enumerate() {
+                if args.count.is_some() {
+                    writeln!(stdout, "[{}]", n)?;
+                }
+                write_tree(value, 0, stdout)?;
+            }
+            Ok(())
+        }
+        Format::Deen | Format::Rust | Format::Ksy | Format::Lua => {
+            unreachable!("checked by Args::parse")
+        }
+    }
+}
+
+// One `name: value` line per field, nested structs and lists indented below their name.
+fn write_tree(value: &DynValue, indent: usize, out: &mut dyn Write) -> io::Result<()> {
+    let entries: Vec<(String, &DynValue)> = match value {
+        DynValue::Struct(fields) => fields.iter().map(|(n, v)| (n.clone(), v)).collect(),
+        DynValue::List(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (format!("[{}]", i), v))
+            .collect(),
+        v => return writeln!(out, "{:1$}{2}", "", indent, v),
+    };
+    for (name, v) in entries {
+        let v = match v {
+            DynValue::Option(Some(v)) => &**v,
+            v => v,
+        };
+        match v {
+            DynValue::Struct(_) | DynValue::List(_) => {
+                writeln!(out, "{:1$}{2}:", "", indent, name)?;
+                write_tree(v, indent + 2, out)?;
+            }
+            v => writeln!(out, "{:1$}{2}: {3}", "", indent, name, v)?,
+        }
+    }
+    Ok(())
+}
+
+fn encode(
+    schema: &DynSchema,
+    parser: &DynParser,
+    input: &[u8],
+    args: &Args,
+) -> io::Result<Vec<u8>> {
+    let json: serde_json::Value = serde_json::from_slice(input)?;
+    let name = match &args.parser {
+        Some(name) => name.as_str(),
+        None => &schema.structs[0].parser,
+    };
+    let records = match (args.count, json) {
+        (None, json) => vec![json],
+        (Some(_), serde_json::Value::Array(records)) => records,
+        (Some(_), _) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "--count needs a JSON array",
+            ))
+        }
+    };
+    let mut bytes = Vec::new();
+    for json in &records {
+        let value = from_json(schema, name, json)?;
+        parser.encode(&value, &mut bytes)?;
+    }
+    Ok(bytes)
+}
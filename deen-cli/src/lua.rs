@@ -0,0 +1,528 @@
+use std::{fmt, io};
+
+use deen::{DynItem, DynPresence, DynSchema, DynStruct, DynType, Endian, Expr, IntType};
+
+use crate::export::{snake, translate, unsupported_error, Syntax};
+
+const LUA: Syntax = Syntax {
+    and: "and",
+    or: "or",
+    not: "not",
+    ne: "~=",
+    xor: "~",
+    div: "//",
+    to_int: "({} and 1 or 0)",
+};
+
+const VARINT: &str = "\
+-- LEB128 varint at offset, returns its value and length.
+local function varint(buf, offset)
+    local value, shift, len = 0, 0, 0
+    repeat
+        local byte = buf(offset + len, 1):uint()
+        value = value | ((byte & 0x7f) << shift)
+        shift, len = shift + 7, len + 1
+    until byte < 0x80
+    return value, len
+end
+";
+
+/// Wireshark dissector in Lua for the first parser of the schema, with `args` for its
+/// parameters. Every parser becomes a function, fields are named `protocol.field` in the root
+/// and `protocol.struct.field` elsewhere. Integer operators need Lua 5.3, i.e. Wireshark 4.4.
+pub fn wireshark_dissector(
+    schema: &DynSchema,
+    protocol: &str,
+    args: &[(&str, i128)],
+) -> io::Result<String> {
+    let root = schema
+        .structs
+        .first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "schema has no parsers"))?;
+    let mut d = Dissector {
+        schema,
+        protocol: protocol.to_string(),
+        fields: Vec::new(),
+        problems: Vec::new(),
+        lines: Vec::new(),
+        indent: 0,
+        varint: false,
+    };
+    for s in &schema.structs {
+        d.dissect_struct(s, s.parser == root.parser);
+    }
+    let mut root_args = Vec::new();
+    for (param, _) in &root.params {
+        match args.iter().find(|(n, _)| n == param) {
+            Some((_, v)) => root_args.push(v.to_string()),
+            None => d.problem(&root.parser, format!("missing argument {}", param)),
+        }
+    }
+    if !d.problems.is_empty() {
+        return Err(unsupported_error("Lua", &d.problems));
+    }
+
+    let mut out = format!(
+        "-- Wireshark dissector for {}, generated by deen. Register it for a port with e.g.\n\
+         -- DissectorTable.get(\"udp.port\"):add(5000, proto)\n\n\
+         local proto = Proto(\"{}\", \"{}\")\n",
+        root.name, protocol, root.name
+    );
+    for e in &schema.enums {
+        let variants: Vec<String> = e
+            .variants
+            .iter()
+            .map(|(name, v)| format!("[{}] = \"{}\"", v, name))
+            .collect();
+        out.push_str(&format!(
+            "local {}_values = {{ {} }}\n",
+            snake(&e.name),
+            variants.join(", ")
+        ));
+    }
+    out.push_str("\nlocal fields = {\n");
+    for (abbrev, decl) in &d.fields {
+        out.push_str(&format!("    [\"{}\"] = {},\n", abbrev, decl));
+    }
+    out.push_str("}\nproto.fields = fields\n\n");
+    if d.varint {
+        out.push_str(VARINT);
+        out.push('\n');
+    }
+    out.push_str("local dissect = {}\n");
+    for line in &d.lines {
+        out.push_str(line);
+        out.push('\n');
+    }
+    let args: String = root_args.iter().map(|a| format!(", {}", a)).collect();
+    out.push_str(&format!(
+        "\nfunction proto.dissector(buf, pinfo, tree)\n    \
+         pinfo.cols.protocol = proto.name\n    \
+         local root = tree:add(proto, buf())\n    \
+         dissect.{}(buf, root, 0, buf:len(){})\n\
+         end\n",
+        root.parser, args
+    ));
+    Ok(out)
+}
+
+struct Dissector<'a> {
+    schema: &'a DynSchema,
+    protocol: String,
+    // Abbreviation without the protocol and `ProtoField` constructor.
+    fields: Vec<(String, String)>,
+    problems: Vec<String>,
+    lines: Vec<String>,
+    indent: usize,
+    varint: bool,
+}
+
+// Schema names become locals with a prefix, so they can not clash with Lua keywords or the
+// names the dissector uses.
+fn local(name: &str) -> String {
+    format!("v_{}", name)
+}
+
+fn rename(e: &Expr) -> Expr {
+    match e {
+        Expr::Var(name) => Expr::Var(local(name)),
+        Expr::Unary(op, e) => Expr::Unary(*op, Box::new(rename(e))),
+        Expr::Binary(op, l, r) => Expr::binary(*op, rename(l), rename(r)),
+        e => e.clone(),
+    }
+}
+
+// `ProtoField` constructor and the method reading the value from a `TvbRange`.
+fn int_field(int: IntType) -> (String, String) {
+    let bits = if int.bits == 48 { 64 } else { int.bits };
+    let ctor = format!("{}int{}", if int.signed { "" } else { "u" }, bits);
+    let le = if int.endian == Endian::Little && int.bits > 8 {
+        "le_"
+    } else {
+        ""
+    };
+    let sign = if int.signed { "" } else { "u" };
+    let read = if int.bits > 32 {
+        format!("{}{}int64():tonumber()", le, sign)
+    } else {
+        format!("{}{}int()", le, sign)
+    };
+    (ctor, read)
+}
+
+fn add(int: IntType) -> &'static str {
+    if int.endian == Endian::Little && int.bits > 8 {
+        "add_le"
+    } else {
+        "add"
+    }
+}
+
+impl Dissector<'_> {
+    fn problem(&mut self, path: &str, message: impl fmt::Display) {
+        self.problems.push(format!("{}: {}", path, message));
+    }
+
+    fn line(&mut self, line: impl AsRef<str>) {
+        let line = format!("{:1$}{2}", "", self.indent * 4, line.as_ref());
+        self.lines.push(line);
+    }
+
+    fn expr(&mut self, path: &str, e: &Expr, want_bool: bool) -> String {
+        let schema = self.schema;
+        let variant = |e: &str, v: &str| {
+            let e = schema.enums.iter().find(|d| d.name == e)?;
+            let (_, value) = e.variants.iter().find(|(n, _)| n == v)?;
+            Some(value.to_string())
+        };
+        translate(&rename(e), &LUA, want_bool, &variant).unwrap_or_else(|message| {
+            self.problem(path, message);
+            "nil".to_string()
+        })
+    }
+
+    // Registers a `ProtoField` and returns the expression to use it. A field whose name is
+    // taken by a different declaration gets a numbered name.
+    fn field(&mut self, abbrev: &str, label: &str, ctor: &str, extra: &str) -> String {
+        let decl = |abbrev: &str| {
+            format!(
+                "ProtoField.{}(\"{}.{}\", \"{}\"{})",
+                ctor, self.protocol, abbrev, label, extra
+            )
+        };
+        let mut name = abbrev.to_string();
+        for n in 2.. {
+            match self.fields.iter().find(|(a, _)| *a == name) {
+                Some((_, d)) if *d == decl(&name) => break,
+                Some(_) => name = format!("{}_{}", abbrev, n),
+                None => {
+                    let d = decl(&name);
+                    self.fields.push((name.clone(), d));
+                    break;
+                }
+            }
+        }
+        format!("fields[\"{}\"]", name)
+    }
+
+    fn dissect_struct(&mut self, s: &DynStruct, root: bool) {
+        let params: String = s
+            .params
+            .iter()
+            .map(|(n, _)| format!(", {}", local(n)))
+            .collect();
+        self.line(format!(
+            "\nfunction dissect.{}(buf, tree, offset, limit{})",
+            s.parser, params
+        ));
+        self.indent += 1;
+        let mut names = Vec::new();
+        collect_fields(&s.items, &mut names);
+        if !names.is_empty() {
+            let names: Vec<String> = names.iter().map(|n| local(n)).collect();
+            self.line(format!("local {}", names.join(", ")));
+        }
+        let prefix = if root {
+            String::new()
+        } else {
+            format!("{}.", snake(&s.name))
+        };
+        self.items(&s.parser, &prefix, &s.items);
+        self.line("return offset");
+        self.indent -= 1;
+        self.line("end");
+    }
+
+    fn items(&mut self, path: &str, prefix: &str, items: &[DynItem]) {
+        for item in items {
+            match item {
+                DynItem::Field { name, ty } => {
+                    let path = format!("{}.{}", path, name);
+                    let abbrev = format!("{}{}", prefix, name);
+                    self.dissect(&path, ty, &abbrev, name, Some(&local(name)), None, "limit");
+                }
+                DynItem::Tag { ty, value } => {
+                    let path = format!("{}.{}", path, item);
+                    if !matches!(ty, DynType::Int(_)) {
+                        self.problem(&path, "tags must be integers");
+                        continue;
+                    }
+                    let value = self.expr(&path, value, false);
+                    let abbrev = format!("{}tag", prefix);
+                    self.line("do");
+                    self.indent += 1;
+                    self.line("local tag");
+                    self.dissect(&path, ty, &abbrev, "tag", Some("tag"), None, "limit");
+                    self.line(format!("if tag ~= {} then", value));
+                    self.line(format!(
+                        "    tree:add_expert_info(PI_MALFORMED, PI_ERROR, \
+                         string.format(\"expected tag %s\", {}))",
+                        value
+                    ));
+                    self.line("end");
+                    self.indent -= 1;
+                    self.line("end");
+                }
+                DynItem::Any(ty) => {
+                    let path = format!("{}.{}", path, item);
+                    let abbrev = format!("{}any", prefix);
+                    self.dissect(&path, ty, &abbrev, "any", None, None, "limit");
+                }
+                DynItem::Padding(len) => {
+                    let len = self.expr(&format!("{}.{}", path, item), len, false);
+                    self.line(format!(
+                        "tree:add(proto, buf(offset, {}), \"padding\")",
+                        len
+                    ));
+                    self.line(format!("offset = offset + {}", len));
+                }
+                DynItem::If { branches, .. } => {
+                    for (i, b) in branches.iter().enumerate() {
+                        match (&b.condition, i) {
+                            (Some(c), 0) => {
+                                let c = self.expr(path, c, true);
+                                self.line(format!("if {} then", c));
+                            }
+                            (Some(c), _) => {
+                                let c = self.expr(path, c, true);
+                                self.line(format!("elseif {} then", c));
+                            }
+                            (None, _) => self.line("else"),
+                        }
+                        self.indent += 1;
+                        self.items(path, prefix, &b.items);
+                        self.indent -= 1;
+                    }
+                    self.line("end");
+                }
+            }
+        }
+    }
+
+    // Adds the value of `ty` at `offset` to `tree` and moves `offset` past it. `target` is
+    // the local that keeps integer values for expressions, `enum_name` the enum the integer
+    // is shown as.
+    #[allow(clippy::too_many_arguments)]
+    fn dissect(
+        &mut self,
+        path: &str,
+        ty: &DynType,
+        abbrev: &str,
+        label: &str,
+        target: Option<&str>,
+        enum_name: Option<&str>,
+        limit: &str,
+    ) {
+        match ty {
+            DynType::Int(int) | DynType::Fixed(int, _) | DynType::Scaled { int, .. } => {
+                // Neither `ProtoField` nor `TvbRange` have anything wider than 64 bits.
+                if int.bits > 64 {
+                    let message = format!("{}-bit integers are not supported", int.bits);
+                    self.problem(path, message);
+                    return;
+                }
+                let (ctor, read) = int_field(*int);
+                let extra = match enum_name {
+                    Some(e) => format!(", base.DEC, {}_values", snake(e)),
+                    None => String::new(),
+                };
+                let field = self.field(abbrev, label, &ctor, &extra);
+                let size = int.bits / 8;
+                self.line("do");
+                self.indent += 1;
+                self.line(format!("local range = buf(offset, {})", size));
+                self.line(format!("tree:{}({}, range)", add(*int), field));
+                if let Some(target) = target {
+                    self.line(format!("{} = range:{}", target, read));
+                }
+                self.line(format!("offset = offset + {}", size));
+                self.indent -= 1;
+                self.line("end");
+            }
+            DynType::VarU64 | DynType::VarI64 => {
+                self.varint = true;
+                let signed = *ty == DynType::VarI64;
+                let ctor = if signed { "int64" } else { "uint64" };
+                let field = self.field(abbrev, label, ctor, "");
+                self.line("do");
+                self.indent += 1;
+                self.line("local value, len = varint(buf, offset)");
+                if signed {
+                    self.line("value = (value >> 1) ~ -(value & 1)");
+                }
+                self.line(format!("tree:add({}, buf(offset, len), value)", field));
+                if let Some(target) = target {
+                    self.line(format!("{} = value", target));
+                }
+                self.line("offset = offset + len");
+                self.indent -= 1;
+                self.line("end");
+            }
+            DynType::Bytes(len) | DynType::Utf8(len) => {
+                let len = match len {
+                    Some(len) => self.expr(path, len, false),
+                    None => format!("{} - offset", limit),
+                };
+                self.line("do");
+                self.indent += 1;
+                self.line(format!("local range = buf(offset, {})", len));
+                if let DynType::Utf8(_) = ty {
+                    let field = self.field(abbrev, label, "string", "");
+                    self.line(format!(
+                        "tree:add({}, range, range:string(ENC_UTF_8))",
+                        field
+                    ));
+                } else {
+                    let field = self.field(abbrev, label, "bytes", "");
+                    self.line(format!("tree:add({}, range)", field));
+                }
+                self.line("offset = offset + range:len()");
+                self.indent -= 1;
+                self.line("end");
+            }
+            DynType::Repeat(count, inner) => {
+                // The list replaces `tree` for its items.
+                self.line("do");
+                self.indent += 1;
+                self.line(format!(
+                    "local start, tree = offset, tree:add(proto, buf(offset, 0), \"{}\")",
+                    label
+                ));
+                match count {
+                    Some(count) => {
+                        let count = self.expr(path, count, false);
+                        self.line(format!("for _ = 1, {} do", count));
+                    }
+                    None => self.line(format!("while offset < {} do", limit)),
+                }
+                self.indent += 1;
+                self.dissect(path, inner, abbrev, label, None, enum_name, limit);
+                self.indent -= 1;
+                self.line("end");
+                self.line("tree:set_len(offset - start)");
+                self.indent -= 1;
+                self.line("end");
+            }
+            DynType::Take { len, inner, .. } => {
+                let len = self.expr(path, len, false);
+                self.line("do");
+                self.indent += 1;
+                self.line(format!("local take_limit = offset + {}", len));
+                self.dissect(path, inner, abbrev, label, target, enum_name, "take_limit");
+                self.line("offset = take_limit");
+                self.indent -= 1;
+                self.line("end");
+            }
+            DynType::Prefixed { len, inner, .. } => {
+                self.line("do");
+                self.indent += 1;
+                self.line("local len");
+                let len_abbrev = format!("{}_len", abbrev);
+                let len_label = format!("{} length", label);
+                let len_ty = DynType::Int(*len);
+                self.dissect(
+                    path,
+                    &len_ty,
+                    &len_abbrev,
+                    &len_label,
+                    Some("len"),
+                    None,
+                    limit,
+                );
+                self.line("local take_limit = offset + len");
+                self.dissect(path, inner, abbrev, label, target, enum_name, "take_limit");
+                self.line("offset = take_limit");
+                self.indent -= 1;
+                self.line("end");
+            }
+            DynType::Enum(name) => match self.schema.enums.iter().find(|e| e.name == *name) {
+                Some(e) => {
+                    let int = DynType::Int(e.int);
+                    self.dissect(path, &int, abbrev, label, target, Some(name), limit);
+                }
+                None => self.problem(path, format!("unknown enum {}", name)),
+            },
+            DynType::Optional {
+                item,
+                inner,
+                presence,
+            } => {
+                let item_enum = self.schema.enums.iter().find(|e| e.name == *item);
+                let item_enum = item_enum.map(|e| e.name.clone());
+                let enum_name = item_enum.as_deref().or(enum_name);
+                let close = match presence {
+                    DynPresence::When(c) => {
+                        let c = self.expr(path, c, true);
+                        self.line(format!("if {} then", c));
+                        1
+                    }
+                    DynPresence::RemainingInput => {
+                        self.line(format!("if offset < {} then", limit));
+                        1
+                    }
+                    DynPresence::Byte => {
+                        self.line("do");
+                        self.indent += 1;
+                        self.line("local present = buf(offset, 1):uint()");
+                        self.line("offset = offset + 1");
+                        self.line("if present ~= 0 then");
+                        2
+                    }
+                };
+                self.indent += 1;
+                self.dissect(path, inner, abbrev, label, target, enum_name, limit);
+                for _ in 0..close {
+                    self.indent -= 1;
+                    self.line("end");
+                }
+            }
+            DynType::Parser { name, args } => {
+                let s = match self.schema.structs.iter().find(|s| s.parser == *name) {
+                    Some(s) => s,
+                    None => {
+                        self.problem(path, format!("unknown parser {}", name));
+                        return;
+                    }
+                };
+                let mut call_args = String::new();
+                for (param, _) in &s.params {
+                    match args.iter().find(|(n, _)| n == param) {
+                        Some((_, arg)) => {
+                            let arg = self.expr(path, arg, false);
+                            call_args.push_str(&format!(", {}", arg));
+                        }
+                        None => self.problem(path, format!("missing argument {}", param)),
+                    }
+                }
+                self.line("do");
+                self.indent += 1;
+                self.line(format!(
+                    "local start, sub = offset, tree:add(proto, buf(offset, 0), \"{}: {}\")",
+                    label, s.name
+                ));
+                self.line(format!(
+                    "offset = dissect.{}(buf, sub, offset, {}{})",
+                    name, limit, call_args
+                ));
+                self.line("sub:set_len(offset - start)");
+                self.indent -= 1;
+                self.line("end");
+            }
+        }
+    }
+}
+
+// Names of the fields of a parser, including the fields in `if` chains.
+fn collect_fields(items: &[DynItem], names: &mut Vec<String>) {
+    for item in items {
+        match item {
+            DynItem::Field { name, .. } if !names.contains(name) => names.push(name.clone()),
+            DynItem::If { branches, .. } => {
+                for b in branches {
+                    collect_fields(&b.items, names);
+                }
+            }
+            _ => {}
+        }
+    }
+}
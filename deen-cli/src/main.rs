@@ -0,0 +1,21 @@
+use std::{env, io, process};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let result = deen_cli::run(
+        &args,
+        &mut stdin.lock(),
+        &mut stdout.lock(),
+        &mut io::stderr(),
+    );
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(if e.kind() == io::ErrorKind::InvalidInput {
+            2
+        } else {
+            1
+        });
+    }
+}
@@ -0,0 +1,111 @@
+use deen::{DynItem, DynSchema, DynType, IntType};
+
+use crate::export::snake;
+
+/// Rust type of the values of an integer deener.
+pub fn rust_int(int: IntType) -> String {
+    let bits = match int.bits {
+        24 => 32,
+        48 => 64,
+        bits => bits,
+    };
+    format!("{}{}", if int.signed { 'i' } else { 'u' }, bits)
+}
+
+fn rust_type(schema: &DynSchema, ty: &DynType) -> String {
+    match ty {
+        DynType::Int(int) => rust_int(*int),
+        DynType::VarU64 => "u64".to_string(),
+        DynType::VarI64 => "i64".to_string(),
+        DynType::Fixed(..) | DynType::Scaled { .. } => "f64".to_string(),
+        DynType::Bytes(_) => "Vec<u8>".to_string(),
+        DynType::Utf8(_) => "String".to_string(),
+        DynType::Repeat(_, inner) => format!("Vec<{}>", rust_type(schema, inner)),
+        DynType::Take { inner, .. } | DynType::Prefixed { inner, .. } => rust_type(schema, inner),
+        DynType::Enum(name) => name.clone(),
+        DynType::Optional { item, inner, .. } if item == "_" => {
+            format!("Option<{}>", rust_type(schema, inner))
+        }
+        DynType::Optional { item, .. } => format!("Option<{}>", item),
+        DynType::Parser { name, .. } => schema
+            .structs
+            .iter()
+            .find(|s| s.parser == *name)
+            .map_or_else(|| name.clone(), |s| s.name.clone()),
+    }
+}
+
+// Struct fields in order of first appearance.
+fn fields(schema: &DynSchema, items: &[DynItem], fields: &mut Vec<(String, String)>) {
+    for item in items {
+        let (name, ty) = match item {
+            DynItem::Field { name, ty } => (name, ty),
+            DynItem::If {
+                name: Some(name),
+                branches,
+            } => match branches[0].items.last() {
+                Some(DynItem::Any(ty)) | Some(DynItem::Field { ty, .. }) => (name, ty),
+                _ => continue,
+            },
+            DynItem::If {
+                name: None,
+                branches,
+            } => {
+                for b in branches {
+                    self::fields(schema, &b.items, fields);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        if !fields.iter().any(|(n, _)| n == name) {
+            fields.push((name.clone(), rust_type(schema, ty)));
+        }
+    }
+}
+
+/// Rust module source for a schema: the enums, a struct per parser and the `deen!` parsers,
+/// each in its own module because `deen!` can only be used once per module.
+pub fn rust_source(schema: &DynSchema) -> String {
+    let mut out = String::from(
+        "use deen::*;\nuse deen_proc::deen;\nuse try_from_primitive::{DeenEnum, TryFromPrimitive};\n",
+    );
+    for e in &schema.enums {
+        out.push_str(&format!(
+            "\n#[repr({})]\n\
+             #[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive, DeenEnum)]\n\
+             #[deen({})]\npub enum {} {{\n",
+            rust_int(e.int),
+            e.int,
+            e.name
+        ));
+        for (name, v) in &e.variants {
+            out.push_str(&format!("    {} = {},\n", name, v));
+        }
+        out.push_str("}\n");
+    }
+    for s in &schema.structs {
+        let mut struct_fields = Vec::new();
+        fields(schema, &s.items, &mut struct_fields);
+        out.push_str(&format!(
+            "\n#[derive(Debug, PartialEq, Clone)]\npub struct {} {{\n",
+            s.name
+        ));
+        for (name, ty) in struct_fields {
+            out.push_str(&format!("    pub {}: {},\n", name, ty));
+        }
+        out.push_str("}\n");
+    }
+    for s in &schema.structs {
+        let module = snake(&s.parser);
+        out.push_str(&format!(
+            "\nmod {} {{\n    use super::*;\n\n    deen! {{\n",
+            module
+        ));
+        for line in format!("pub {}", s).lines() {
+            out.push_str(&format!("        {}\n", line));
+        }
+        out.push_str(&format!("    }}\n}}\npub use {}::{};\n", module, s.parser));
+    }
+    out
+}
@@ -1,12 +1,13 @@
 mod condition;
 
-use quote::quote;
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
 use syn::{
     parse::{self, Parse, ParseStream},
     Expr, Ident, Token,
 };
 
-use condition::{ExprIf, encode_if, decode_if};
+use condition::{ExprIf, encode_if, decode_if, schema_if};
 
 pub enum Item {
     Value(Expr),
@@ -50,10 +51,13 @@ pub fn encode_item(item: &Item) -> proc_macro2::TokenStream {
         Item::Field(f) => {
             let init = &f.init;
             let name = &f.name;
-            let tf = try_from(name);
+            let label = name.to_string();
             quote! {
-                let #name = value.#name;
-                #init.encode(&#tf, &mut buf)?;
+                let #name = &value.#name;
+                {
+                    use deen::{EncodeConverted as _, EncodeSame as _};
+                    (&deen::EncodeField(&#init, #name)).encode_field(#label, &mut buf)?;
+                }
             }
         }
         Item::Value(c) => {
@@ -71,20 +75,157 @@ pub fn decode_item(item: &Item) -> proc_macro2::TokenStream {
             let init = &f.init;
             let name = &f.name;
             let tf = try_from(name);
+            let label = name.to_string();
             quote! {
+                let trace = deen::Trace::enter(#label);
                 let #name = #init.decode(&mut buf)?;
+                trace.done(|| {
+                    use deen::{TraceDebug as _, TraceOpaque as _};
+                    (&deen::TraceValue(&#name)).trace_value()
+                });
                 let #name = #tf;
             }
         }
         Item::Value(c) => {
+            let label = source(c);
             quote! {
+                let trace = deen::Trace::enter(#label);
                 #c.compare(&mut buf)?;
+                trace.done(|| None);
             }
         }
         Item::If(i) => decode_if(i),
     }
 }
 
+/// Schema of `item`. Deeners that refer to decoded `fields` can only be built while decoding, so
+/// they are described by their source text.
+pub fn schema_item(item: &Item, fields: &[Ident]) -> proc_macro2::TokenStream {
+    let schema = |tokens: proc_macro2::TokenStream, src: &str| {
+        let mut idents = Vec::new();
+        collect_idents(tokens.clone(), &mut idents);
+        if idents.iter().any(|i| fields.contains(i)) {
+            quote! { deen::Schema::Opaque(#src.to_string()) }
+        } else {
+            tokens
+        }
+    };
+    match item {
+        Item::Field(f) => {
+            let init = &f.init;
+            let name = f.name.to_string();
+            let src = source(init);
+            let schema = schema(quote! { deen::Deen::schema(&(#init)) }, &src);
+            quote! {
+                deen::Field {
+                    name: Some(#name.to_string()),
+                    source: #src.to_string(),
+                    schema: #schema,
+                }
+            }
+        }
+        Item::Value(c) => {
+            let src = source(c);
+            let schema = schema(quote! { deen::Value::schema(&(#c)) }, &src);
+            quote! {
+                deen::Field {
+                    name: None,
+                    source: #src.to_string(),
+                    schema: #schema,
+                }
+            }
+        }
+        Item::If(i) => schema_if(i, fields),
+    }
+}
+
+/// Identifiers in the parts of `item` that are evaluated to build its schema, i.e. everything
+/// but the conditions.
+pub fn schema_idents(item: &Item, idents: &mut Vec<Ident>) {
+    match item {
+        Item::Field(f) => collect_idents(f.init.clone().into_token_stream(), idents),
+        Item::Value(c) => collect_idents(c.clone().into_token_stream(), idents),
+        Item::If(i) => condition::schema_idents(i, idents),
+    }
+}
+
+/// Identifiers `item` may use as variables, in its conditions as well as its deeners.
+pub fn expr_idents(item: &Item, idents: &mut Vec<Ident>) {
+    match item {
+        Item::Field(f) => collect_idents(f.init.clone().into_token_stream(), idents),
+        Item::Value(c) => collect_idents(c.clone().into_token_stream(), idents),
+        Item::If(i) => condition::expr_idents(i, idents),
+    }
+}
+
+// Only identifiers that can be variables: not paths, method or function calls and field names
+// of struct expressions.
+pub(crate) fn collect_idents(tokens: TokenStream, idents: &mut Vec<Ident>) {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    for (n, tt) in tokens.iter().enumerate() {
+        match tt {
+            TokenTree::Ident(i) => {
+                let prev = n.checked_sub(1).and_then(|p| punct(&tokens[p]));
+                let next = tokens.get(n + 1);
+                let is_path_or_member = prev == Some('.') || prev == Some(':');
+                let is_followed = match next {
+                    Some(TokenTree::Punct(p)) => p.as_char() == ':' || p.as_char() == '!',
+                    Some(TokenTree::Group(g)) => g.delimiter() == Delimiter::Parenthesis,
+                    _ => false,
+                };
+                if !is_path_or_member && !is_followed {
+                    idents.push(i.clone());
+                }
+            }
+            TokenTree::Group(g) => collect_idents(g.stream(), idents),
+            _ => {}
+        }
+    }
+}
+
+fn punct(tt: &TokenTree) -> Option<char> {
+    match tt {
+        TokenTree::Punct(p) => Some(p.as_char()),
+        _ => None,
+    }
+}
+
+/// Source text of `tokens`, with the spacing of `to_string` tidied up for common expressions.
+pub fn source(tokens: &impl ToTokens) -> String {
+    let s = tokens.into_token_stream().to_string();
+    let mut s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+    for (from, to) in &[
+        ("| |", "||"),
+        (" :: ", "::"),
+        (":: ", "::"),
+        (" ::", "::"),
+        (" . ", "."),
+        (" ,", ","),
+        (" ;", ";"),
+        ("( ", "("),
+        (" )", ")"),
+        ("[ ", "["),
+        (" ]", "]"),
+        ("::< ", "::<"),
+        (" >::", ">::"),
+        (" ! ", "!"),
+    ] {
+        s = s.replace(from, to);
+    }
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        // Calls and generic arguments: `new (` becomes `new(`.
+        if c == '(' && out.ends_with(' ') {
+            let prev = out.trim_end().chars().last();
+            if prev.is_some_and(|p| p.is_alphanumeric() || p == '_' || p == '>' || p == '!') {
+                out.pop();
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
 fn try_from(item: &Ident) -> proc_macro2::TokenStream {
     let name = format!("{}", item);
     quote! {
@@ -1,12 +1,12 @@
 use std::iter;
 
-use quote::quote;
+use quote::{quote, ToTokens};
 use syn::{
     parse::{self, Parse, ParseStream},
     Expr, Block, Stmt, Ident, Token,
 };
 
-use super::{decode_item, encode_item, Field, Item};
+use super::{collect_idents, decode_item, encode_item, schema_item, source, Field, Item};
 
 pub struct ExprIf {
     pub name: Option<Ident>,
@@ -158,3 +158,89 @@ fn decode_else(e: &Expr, name: &Option<Ident>) -> proc_macro2::TokenStream {
         else #branch
     }
 }
+
+pub fn schema_if(i: &ExprIf, fields: &[Ident]) -> proc_macro2::TokenStream {
+    let name = match &i.name {
+        Some(name) => {
+            let name = name.to_string();
+            quote! { Some(#name.to_string()) }
+        }
+        None => quote! { None },
+    };
+    let src = source(&i.expr);
+    let mut branches = Vec::new();
+    let mut expr = Some(&i.expr);
+    while let Some(e) = expr.take() {
+        let cond = source(&e.cond);
+        let items = schema_block(&e.then_branch, &i.name, fields);
+        branches.push(quote! {
+            deen::Branch {
+                condition: Some(#cond.to_string()),
+                fields: vec![#(#items),*],
+            }
+        });
+        match e.else_branch.as_ref().map(|(_, e)| &**e) {
+            Some(Expr::If(e)) => expr = Some(e),
+            Some(Expr::Block(b)) => {
+                let items = schema_block(&b.block, &i.name, fields);
+                branches.push(quote! {
+                    deen::Branch {
+                        condition: None,
+                        fields: vec![#(#items),*],
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+    quote! {
+        deen::Field {
+            name: #name,
+            source: #src.to_string(),
+            schema: deen::Schema::Conditional(vec![#(#branches),*]),
+        }
+    }
+}
+
+fn schema_block(
+    block: &Block,
+    name: &Option<Ident>,
+    fields: &[Ident],
+) -> Vec<proc_macro2::TokenStream> {
+    let exprs: Vec<&Expr> = block
+        .stmts
+        .iter()
+        .map(|s| match s {
+            Stmt::Expr(e) => e,
+            Stmt::Semi(e, _) => e,
+            _ => panic!("not supported"),
+        })
+        .collect();
+    exprs
+        .iter()
+        .enumerate()
+        .map(|(n, e)| match name {
+            Some(name) if n + 1 == exprs.len() => {
+                let field = Field { name: name.clone(), init: (*e).clone() };
+                schema_item(&Item::Field(field), fields)
+            }
+            _ => schema_item(&Item::Value((*e).clone()), fields),
+        })
+        .collect()
+}
+
+pub fn schema_idents(i: &ExprIf, idents: &mut Vec<Ident>) {
+    let mut expr = Some(&i.expr);
+    while let Some(e) = expr.take() {
+        collect_idents(e.then_branch.clone().into_token_stream(), idents);
+        match e.else_branch.as_ref().map(|(_, e)| &**e) {
+            Some(Expr::If(e)) => expr = Some(e),
+            Some(Expr::Block(b)) => collect_idents(b.block.clone().into_token_stream(), idents),
+            _ => {}
+        }
+    }
+}
+
+pub fn expr_idents(i: &ExprIf, idents: &mut Vec<Ident>) {
+    collect_idents(i.expr.clone().into_token_stream(), idents);
+}
@@ -11,10 +11,10 @@ use syn::{
     parse::{self, Parse, ParseStream},
     parse_macro_input,
     punctuated::Punctuated,
-    token, Attribute, Ident, Token, Visibility,
+    token, Attribute, Ident, Token, Type, Visibility,
 };
 
-use items::{Item, encode_item, decode_item};
+use items::{Item, encode_item, decode_item, expr_idents, schema_idents, schema_item, source};
 
 struct Deen {
     attrs: Vec<Attribute>,
@@ -58,7 +58,7 @@ impl Parse for Deen {
 
 struct Param {
     name: Ident,
-    ty: Ident,
+    ty: Type,
 }
 
 impl Parse for Param {
@@ -76,11 +76,13 @@ fn parser_declaration(named: &Deen) -> proc_macro2::TokenStream {
     let name = &named.parser_name;
     match &named.params {
         Some(params) => {
+            // Fields share the visibility of the parser, so that other parsers can construct it
+            // inline with their own fields as arguments.
             let params = params.iter().map(|p| {
                 let name = &p.name;
                 let ty = &p.ty;
                 quote! {
-                    #name: #ty,
+                    #visibility #name: #ty,
                 }
             });
             quote! {
@@ -99,8 +101,25 @@ fn parser_declaration(named: &Deen) -> proc_macro2::TokenStream {
 }
 
 fn encode_impl(named: &Deen) -> proc_macro2::TokenStream {
-    let params = params_declaration(named);
-    let write_to = named.items.iter().map(encode_item);
+    let idents = used_idents(named, expr_idents);
+    let params = params_declaration(named, &idents);
+    // Fields are encoded by reference, and copied for the deeners of later items that use them
+    // as they are after decoding.
+    let write_to = named.items.iter().map(|item| {
+        let encode = encode_item(item);
+        let name = match item {
+            Item::Field(f) => Some(&f.name),
+            Item::If(f) => f.name.as_ref(),
+            _ => None,
+        };
+        match name {
+            Some(name) if idents.contains(name) => quote! {
+                #encode
+                let #name = value.#name;
+            },
+            _ => encode,
+        }
+    });
     quote! {
         fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
             #(#params)*
@@ -111,7 +130,7 @@ fn encode_impl(named: &Deen) -> proc_macro2::TokenStream {
 }
 
 fn decode_impl(named: &Deen) -> proc_macro2::TokenStream {
-    let params = params_declaration(named);
+    let params = params_declaration(named, &used_idents(named, expr_idents));
     let read_from = named.items.iter().map(decode_item);
     let names = named
         .items
@@ -125,6 +144,7 @@ fn decode_impl(named: &Deen) -> proc_macro2::TokenStream {
 
     quote! {
         fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+            let _depth = deen::DecodeLimits::enter()?;
             #(#params)*
             #(#read_from)*
             Ok(Self::Item {
@@ -134,10 +154,55 @@ fn decode_impl(named: &Deen) -> proc_macro2::TokenStream {
     }
 }
 
-fn params_declaration(named: &Deen) -> Vec<proc_macro2::TokenStream> {
+fn schema_impl(named: &Deen) -> proc_macro2::TokenStream {
+    let name = named.struct_name.to_string();
+    // Deeners that also refer to fields are not built, leaving their params unused.
+    let params = params_declaration(named, &used_idents(named, schema_idents))
+        .into_iter()
+        .map(|p| quote! { #[allow(unused_variables)] #p });
+    let fields: Vec<Ident> = named
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Field(f) => Some(f.name.clone()),
+            Item::If(f) => f.name.clone(),
+            _ => None,
+        })
+        .collect();
+    let items = named.items.iter().map(|item| schema_item(item, &fields));
+    let parser = named.parser_name.to_string();
+    let param_types = named.params.iter().flatten().map(|p| {
+        let name = p.name.to_string();
+        let ty = source(&p.ty);
+        quote! { (#name.to_string(), #ty.to_string()) }
+    });
+
+    quote! {
+        fn schema(&self) -> deen::Schema {
+            #(#params)*
+            deen::Schema::Struct {
+                name: #name.to_string(),
+                parser: #parser.to_string(),
+                params: vec![#(#param_types),*],
+                fields: vec![#(#items),*],
+            }
+        }
+    }
+}
+
+fn used_idents(named: &Deen, collect: fn(&Item, &mut Vec<Ident>)) -> Vec<Ident> {
+    let mut idents = Vec::new();
+    for item in &named.items {
+        collect(item, &mut idents);
+    }
+    idents
+}
+
+fn params_declaration(named: &Deen, idents: &[Ident]) -> Vec<proc_macro2::TokenStream> {
     match &named.params {
         Some(params) => params
             .iter()
+            .filter(|p| idents.contains(&p.name))
             .map(|p| {
                 let name = &p.name;
                 quote! {
@@ -158,6 +223,7 @@ pub fn deen(input: TokenStream) -> TokenStream {
     let parser_decl = parser_declaration(&named);
     let encoder = encode_impl(&named);
     let decoder = decode_impl(&named);
+    let schema = schema_impl(&named);
     let expanded = quote! {
         use std::io;
 
@@ -170,6 +236,7 @@ pub fn deen(input: TokenStream) -> TokenStream {
 
             #encoder
             #decoder
+            #schema
         }
     };
 
@@ -0,0 +1,193 @@
+use std::{
+    convert::TryFrom,
+    io::{self, Read},
+};
+
+use crate::{invalid_data_error, read_byte, DecodeLimits, Deen, Schema};
+
+/// Raw bytes, either exactly `len` of them or all remaining input.
+#[derive(Clone, Copy, Debug)]
+pub struct Bytes {
+    len: Option<u64>,
+}
+
+impl Bytes {
+    pub fn new(len: impl Into<u64>) -> Bytes {
+        Self {
+            len: Some(len.into()),
+        }
+    }
+
+    /// Reads until the end of input, usually of a `Take`.
+    pub fn remaining() -> Bytes {
+        Self { len: None }
+    }
+}
+
+fn check_len(len: Option<u64>, actual: usize) -> io::Result<()> {
+    match len {
+        Some(len) if len != actual as u64 => Err(invalid_data_error(format!(
+            "value has {} bytes, but the length is {}",
+            actual, len
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn to_usize(n: u64) -> io::Result<usize> {
+    usize::try_from(n).map_err(|_| invalid_data_error(format!("length {} is too large", n)))
+}
+
+fn read_bytes(len: Option<u64>, mut buf: impl io::Read) -> io::Result<Vec<u8>> {
+    match len {
+        Some(len) => {
+            DecodeLimits::check_allocation(to_usize(len)?)?;
+            // Grows with the input read rather than trusting the decoded length up front.
+            let mut bytes = Vec::new();
+            buf.by_ref().take(len).read_to_end(&mut bytes)?;
+            if (bytes.len() as u64) < len {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "failed to fill whole buffer",
+                ));
+            }
+            Ok(bytes)
+        }
+        None => {
+            let mut bytes = Vec::new();
+            let mut chunk = [0; 4096];
+            loop {
+                let n = match buf.read(&mut chunk) {
+                    Ok(0) => return Ok(bytes),
+                    Ok(n) => n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+                DecodeLimits::check_allocation(bytes.len() + n)?;
+                bytes.extend_from_slice(&chunk[..n]);
+            }
+        }
+    }
+}
+
+impl Deen for Bytes {
+    type Item = Vec<u8>;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        check_len(self.len, value.len())?;
+        buf.write_all(value)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        read_bytes(self.len, buf)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::leaf("Bytes", self.len, None)
+    }
+}
+
+/// UTF-8 string of `len` bytes or of all remaining input.
+#[derive(Clone, Copy, Debug)]
+pub struct Utf8 {
+    len: Option<u64>,
+}
+
+impl Utf8 {
+    pub fn new(len: impl Into<u64>) -> Utf8 {
+        Self {
+            len: Some(len.into()),
+        }
+    }
+
+    pub fn remaining() -> Utf8 {
+        Self { len: None }
+    }
+}
+
+impl Deen for Utf8 {
+    type Item = String;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        check_len(self.len, value.len())?;
+        buf.write_all(value.as_bytes())
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        String::from_utf8(read_bytes(self.len, buf)?).map_err(invalid_data_error)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::leaf("Utf8", self.len, None)
+    }
+}
+
+/// Sequence of `count` values, or of values until the end of input.
+pub struct Repeat<T> {
+    count: Option<u64>,
+    deener: T,
+}
+
+impl<T: Deen> Repeat<T> {
+    pub fn new(count: impl Into<u64>, deener: T) -> Repeat<T> {
+        Self {
+            count: Some(count.into()),
+            deener,
+        }
+    }
+
+    pub fn remaining(deener: T) -> Repeat<T> {
+        Self {
+            count: None,
+            deener,
+        }
+    }
+}
+
+impl<T: Deen> Deen for Repeat<T> {
+    type Item = Vec<T::Item>;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        match self.count {
+            Some(count) if count != value.len() as u64 => Err(invalid_data_error(format!(
+                "sequence has {} elements, but the count is {}",
+                value.len(),
+                count
+            ))),
+            _ => value.iter().try_for_each(|v| self.deener.encode(v, &mut buf)),
+        }
+    }
+
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        let mut items = Vec::new();
+        match self.count {
+            Some(count) => {
+                DecodeLimits::check_elements(to_usize(count)?)?;
+                for _ in 0..count {
+                    items.push(self.deener.decode(&mut buf)?);
+                }
+            }
+            None => {
+                let mut first = [0];
+                while read_byte(&mut buf, &mut first)? {
+                    DecodeLimits::check_elements(items.len() + 1)?;
+                    let mut peeked = &first[..];
+                    items.push(self.deener.decode((&mut peeked).chain(&mut buf))?);
+                    // Otherwise the element would repeat until the input ends, and the byte
+                    // read to check for it would be lost.
+                    if !peeked.is_empty() {
+                        return Err(invalid_data_error("sequence element consumed no input"));
+                    }
+                }
+            }
+        }
+        Ok(items)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::List {
+            count: self.count,
+            inner: Box::new(self.deener.schema()),
+        }
+    }
+}
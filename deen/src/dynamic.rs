@@ -0,0 +1,1513 @@
+mod parse;
+
+use std::{convert::TryFrom, fmt, io};
+
+use crate::{
+    invalid_data_error, is_canonical, Bytes, DecodeLimits, Deen, Endian, Field, Fixed, I128be,
+    I128le, I16be, I16le, I24be, I24le, I32be, I32le, I48be, I48le, I64be, I64le, Integer,
+    Optional, Padding, PresenceRule, Repeat, Scaled, Schema, Take, Trace, U128be, U128le, U16be,
+    U16le, U24be, U24le, U32be, U32le, U48be, U48le, U64be, U64le, Utf8, Value, VarI64, VarU64, I8,
+    U8,
+};
+
+pub use parse::ParseError;
+
+/// Value decoded by a `DynSchema`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DynValue {
+    Int(i128),
+    Float(f64),
+    Bytes(Vec<u8>),
+    String(String),
+    List(Vec<DynValue>),
+    /// Fields in schema order.
+    Struct(Vec<(String, DynValue)>),
+    /// Variant name of an enum.
+    Enum(String),
+    Option(Option<Box<DynValue>>),
+}
+
+impl DynValue {
+    pub fn as_int(&self) -> Option<i128> {
+        match self {
+            DynValue::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<&DynValue> {
+        match self {
+            DynValue::Struct(fields) => fields.iter().find(|(n, _)| n == name).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+/// Compact single-line form: `{ version: 3, name: "abc", data: 01 02 }`.
+impl fmt::Display for DynValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DynValue::Int(v) => write!(f, "{}", v),
+            DynValue::Float(v) => write!(f, "{:?}", v),
+            DynValue::Bytes(b) => {
+                let hex: Vec<_> = b.iter().map(|b| format!("{:02x}", b)).collect();
+                write!(f, "[{}]", hex.join(" "))
+            }
+            DynValue::String(s) => write!(f, "{:?}", s),
+            DynValue::List(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            DynValue::Struct(fields) => {
+                f.write_str("{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    let sep = if i > 0 { "," } else { "" };
+                    write!(f, "{} {}: {}", sep, name, value)?;
+                }
+                f.write_str(" }")
+            }
+            DynValue::Enum(name) => f.write_str(name),
+            DynValue::Option(None) => f.write_str("None"),
+            DynValue::Option(Some(v)) => write!(f, "Some({})", v),
+        }
+    }
+}
+
+/// One of the built-in integer deeners, like `U16be`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IntType {
+    pub signed: bool,
+    pub bits: u32,
+    pub endian: Endian,
+}
+
+// Runs `$body` with `$d` bound to the deener of `$int`.
+macro_rules! with_int {
+    ($int:expr, $d:ident => $body:expr) => {
+        with_int!(@match $int, $d, $body,
+            (false, 8, _) U8, (true, 8, _) I8,
+            (false, 16, Endian::Big) U16be, (false, 16, Endian::Little) U16le,
+            (false, 24, Endian::Big) U24be, (false, 24, Endian::Little) U24le,
+            (false, 32, Endian::Big) U32be, (false, 32, Endian::Little) U32le,
+            (false, 48, Endian::Big) U48be, (false, 48, Endian::Little) U48le,
+            (false, 64, Endian::Big) U64be, (false, 64, Endian::Little) U64le,
+            (false, 128, Endian::Big) U128be, (false, 128, Endian::Little) U128le,
+            (true, 16, Endian::Big) I16be, (true, 16, Endian::Little) I16le,
+            (true, 24, Endian::Big) I24be, (true, 24, Endian::Little) I24le,
+            (true, 32, Endian::Big) I32be, (true, 32, Endian::Little) I32le,
+            (true, 48, Endian::Big) I48be, (true, 48, Endian::Little) I48le,
+            (true, 64, Endian::Big) I64be, (true, 64, Endian::Little) I64le,
+            (true, 128, Endian::Big) I128be, (true, 128, Endian::Little) I128le)
+    };
+    (@match $int:expr, $d:ident, $body:expr, $(($s:pat, $b:pat, $e:pat) $t:ident),*) => {
+        match ($int.signed, $int.bits, $int.endian) {
+            $(($s, $b, $e) => {
+                let $d = $t;
+                $body
+            })*
+            _ => unreachable!("IntType is only built from valid names"),
+        }
+    };
+}
+
+impl IntType {
+    /// Parses the name of an integer deener, like `U16be` or `I8`.
+    pub fn from_name(name: &str) -> Option<IntType> {
+        let signed = match name.chars().next()? {
+            'U' => false,
+            'I' => true,
+            _ => return None,
+        };
+        let (bits, endian) = match name[1..].find(|c: char| !c.is_ascii_digit()) {
+            Some(i) => {
+                let endian = match &name[1 + i..] {
+                    "be" => Endian::Big,
+                    "le" => Endian::Little,
+                    _ => return None,
+                };
+                (&name[1..1 + i], endian)
+            }
+            None => (&name[1..], Endian::Big),
+        };
+        let bits = bits.parse().ok()?;
+        let valid = match bits {
+            8 => name.len() == 2,
+            16 | 24 | 32 | 48 | 64 | 128 => name.len() > 3,
+            _ => false,
+        };
+        if valid {
+            Some(IntType {
+                signed,
+                bits,
+                endian,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn decode(&self, buf: &mut dyn io::Read) -> io::Result<i128> {
+        with_int!(self, d => {
+            let v = d.decode(buf)?;
+            v.to_i128()
+                .ok_or_else(|| invalid_data_error(format!("{} does not fit into i128", self)))
+        })
+    }
+
+    fn encode(&self, value: i128, buf: &mut dyn io::Write) -> io::Result<()> {
+        with_int!(self, d => {
+            let v = Integer::from_i128(value).ok_or_else(|| {
+                invalid_data_error(format!("{} is out of range of {}", value, self))
+            })?;
+            d.encode(&v, buf)
+        })
+    }
+
+    fn schema(&self) -> Schema {
+        with_int!(self, d => d.schema())
+    }
+}
+
+impl fmt::Display for IntType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", if self.signed { 'I' } else { 'U' }, self.bits)?;
+        match (self.bits, self.endian) {
+            (8, _) => Ok(()),
+            (_, Endian::Big) => f.write_str("be"),
+            (_, Endian::Little) => f.write_str("le"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnOp {
+    Neg,
+    Not,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    BitAnd,
+    BitXor,
+    BitOr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl BinOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
+            BinOp::BitAnd => "&",
+            BinOp::BitXor => "^",
+            BinOp::BitOr => "|",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+        }
+    }
+
+    /// Binding strength as in Rust, higher binds tighter.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinOp::Mul | BinOp::Div | BinOp::Rem => 10,
+            BinOp::Add | BinOp::Sub => 9,
+            BinOp::Shl | BinOp::Shr => 8,
+            BinOp::BitAnd => 7,
+            BinOp::BitXor => 6,
+            BinOp::BitOr => 5,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => 4,
+            BinOp::And => 3,
+            BinOp::Or => 2,
+        }
+    }
+}
+
+/// Integer expression over parameters and previously decoded fields, used for conditions,
+/// lengths and tag values. Booleans are `0` and `1`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Int(i128),
+    /// Integer literal that is displayed in hex, like tag values usually are.
+    Hex(i128),
+    Var(String),
+    /// Enum variant, like `Kind::Dir`.
+    Variant(String, String),
+    Unary(UnOp, Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn var(name: &str) -> Expr {
+        Expr::Var(name.to_string())
+    }
+
+    pub fn binary(op: BinOp, lhs: Expr, rhs: Expr) -> Expr {
+        Expr::Binary(op, Box::new(lhs), Box::new(rhs))
+    }
+
+    /// Value of an integer literal.
+    pub fn literal(&self) -> Option<i128> {
+        match self {
+            Expr::Int(v) | Expr::Hex(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn eval(&self, ctx: &Context) -> io::Result<i128> {
+        let overflow = || invalid_data_error(format!("overflow in {}", self));
+        match self {
+            Expr::Int(v) | Expr::Hex(v) => Ok(*v),
+            Expr::Var(name) => match ctx.lookup(name) {
+                Some(DynValue::Int(v)) => Ok(*v),
+                Some(DynValue::Enum(variant)) => {
+                    ctx.schema.variant_value(ctx.enum_of(name)?, variant)
+                }
+                Some(v) => Err(invalid_data_error(format!(
+                    "{} is not an integer: {}",
+                    name, v
+                ))),
+                None => Err(invalid_data_error(format!("unknown name {}", name))),
+            },
+            Expr::Variant(e, variant) => ctx.schema.variant_value(e, variant),
+            Expr::Unary(op, e) => {
+                let v = e.eval(ctx)?;
+                match op {
+                    UnOp::Neg => v.checked_neg().ok_or_else(overflow),
+                    UnOp::Not => Ok((v == 0) as i128),
+                }
+            }
+            Expr::Binary(BinOp::And, l, r) => Ok((l.eval(ctx)? != 0 && r.eval(ctx)? != 0) as i128),
+            Expr::Binary(BinOp::Or, l, r) => Ok((l.eval(ctx)? != 0 || r.eval(ctx)? != 0) as i128),
+            Expr::Binary(op, l, r) => {
+                let (l, r) = (l.eval(ctx)?, r.eval(ctx)?);
+                let shift = || u32::try_from(r).map_err(|_| overflow());
+                let v = match op {
+                    BinOp::Mul => l.checked_mul(r),
+                    BinOp::Div => l.checked_div(r),
+                    BinOp::Rem => l.checked_rem(r),
+                    BinOp::Add => l.checked_add(r),
+                    BinOp::Sub => l.checked_sub(r),
+                    BinOp::Shl => l.checked_shl(shift()?),
+                    BinOp::Shr => l.checked_shr(shift()?),
+                    BinOp::BitAnd => Some(l & r),
+                    BinOp::BitXor => Some(l ^ r),
+                    BinOp::BitOr => Some(l | r),
+                    BinOp::Eq => Some((l == r) as i128),
+                    BinOp::Ne => Some((l != r) as i128),
+                    BinOp::Lt => Some((l < r) as i128),
+                    BinOp::Le => Some((l <= r) as i128),
+                    BinOp::Gt => Some((l > r) as i128),
+                    BinOp::Ge => Some((l >= r) as i128),
+                    BinOp::And | BinOp::Or => unreachable!(),
+                };
+                v.ok_or_else(overflow)
+            }
+        }
+    }
+
+    fn eval_len(&self, ctx: &Context) -> io::Result<u64> {
+        let v = self.eval(ctx)?;
+        u64::try_from(v)
+            .map_err(|_| invalid_data_error(format!("invalid length {} of {}", v, self)))
+    }
+
+    fn fmt_prec(&self, f: &mut fmt::Formatter, prec: u8) -> fmt::Result {
+        match self {
+            Expr::Int(v) => write!(f, "{}", v),
+            Expr::Hex(v) if *v < 0 => write!(f, "-{:#x}", v.unsigned_abs()),
+            Expr::Hex(v) => write!(f, "{:#x}", v),
+            Expr::Var(name) => f.write_str(name),
+            Expr::Variant(e, variant) => write!(f, "{}::{}", e, variant),
+            Expr::Unary(op, e) => {
+                f.write_str(match op {
+                    UnOp::Neg => "-",
+                    UnOp::Not => "!",
+                })?;
+                e.fmt_prec(f, u8::MAX)
+            }
+            Expr::Binary(op, l, r) => {
+                let p = op.precedence();
+                if p < prec {
+                    f.write_str("(")?;
+                }
+                l.fmt_prec(f, p)?;
+                write!(f, " {} ", op.symbol())?;
+                r.fmt_prec(f, p + 1)?;
+                if p < prec {
+                    f.write_str(")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Rust syntax, with parentheses only where precedence needs them.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_prec(f, 0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DynPresence {
+    When(Expr),
+    RemainingInput,
+    Byte,
+}
+
+/// Runtime counterpart of the deener expressions in a `deen!` block.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DynType {
+    Int(IntType),
+    VarU64,
+    VarI64,
+    Fixed(IntType, u32),
+    Scaled {
+        int: IntType,
+        scale: f64,
+        offset: f64,
+    },
+    /// `Bytes::new(len)`, or `Bytes::remaining()` without a length.
+    Bytes(Option<Expr>),
+    Utf8(Option<Expr>),
+    Repeat(Option<Expr>, Box<DynType>),
+    Take {
+        len: Expr,
+        inner: Box<DynType>,
+        skip_remaining: bool,
+    },
+    Prefixed {
+        len: IntType,
+        inner: Box<DynType>,
+        skip_remaining: bool,
+    },
+    /// `Kind::deen()` for an enum declared in the schema.
+    Enum(String),
+    /// `Optional::<item>::wrap(inner)`. If `item` is an enum of the schema, the inner integer
+    /// is decoded as one of its variants.
+    Optional {
+        item: String,
+        inner: Box<DynType>,
+        presence: DynPresence,
+    },
+    /// Another parser of the schema with arguments for its parameters, like
+    /// `BodyParser { version, length }`.
+    Parser {
+        name: String,
+        args: Vec<(String, Expr)>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DynItem {
+    Field {
+        name: String,
+        ty: DynType,
+    },
+    Tag {
+        ty: DynType,
+        value: Expr,
+    },
+    Any(DynType),
+    Padding(Expr),
+    /// `if` chain. For a named chain the last item of every branch is the field.
+    If {
+        name: Option<String>,
+        branches: Vec<DynBranch>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynBranch {
+    /// `None` for the final `else`.
+    pub condition: Option<Expr>,
+    pub items: Vec<DynItem>,
+}
+
+/// Runtime counterpart of a `deen!` parser: `struct parser(params) for name { items }`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynStruct {
+    pub parser: String,
+    pub name: String,
+    /// Names and types of the parameters.
+    pub params: Vec<(String, String)>,
+    pub items: Vec<DynItem>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynEnum {
+    pub name: String,
+    pub int: IntType,
+    pub variants: Vec<(String, i128)>,
+}
+
+/// Set of parsers and enums interpreted at runtime. The first parser is the root.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DynSchema {
+    pub structs: Vec<DynStruct>,
+    pub enums: Vec<DynEnum>,
+}
+
+impl DynSchema {
+    pub fn new() -> DynSchema {
+        Self::default()
+    }
+
+    pub fn with_struct(mut self, s: DynStruct) -> DynSchema {
+        self.structs.push(s);
+        self
+    }
+
+    pub fn with_enum(mut self, e: DynEnum) -> DynSchema {
+        self.enums.push(e);
+        self
+    }
+
+    /// Deener for the parser called `name`, with integer arguments for its parameters.
+    pub fn parser(&self, name: &str, args: &[(&str, i128)]) -> io::Result<DynParser<'_>> {
+        let _ = self.find_struct(name)?;
+        Ok(DynParser {
+            schema: self,
+            name: name.to_string(),
+            args: args
+                .iter()
+                .map(|(n, v)| (n.to_string(), DynValue::Int(*v)))
+                .collect(),
+        })
+    }
+
+    /// Rebuilds the schema of a `deen!` parser from its reflection by parsing the source of
+    /// every field, with the reflected parser first. Fails for deeners the text syntax does
+    /// not support, like closures over the value and custom deeners. Enums are only known
+    /// from `DeenEnum` fields, `Optional::<E>` of other enums decodes integers.
+    pub fn from_schema(schema: &Schema) -> io::Result<DynSchema> {
+        let mut dyn_schema = DynSchema::new();
+        dyn_schema.add_reflected(schema)?;
+        // Custom deeners parse like references to parsers or enums that do not exist.
+        for s in &dyn_schema.structs {
+            for item in &s.items {
+                if let Some(unknown) = item_unknown_ref(&dyn_schema, item) {
+                    let field = match item {
+                        DynItem::Field { name, .. }
+                        | DynItem::If {
+                            name: Some(name), ..
+                        } => name.clone(),
+                        item => item.to_string(),
+                    };
+                    return Err(invalid_data_error(format!(
+                        "{}.{}: unknown deener {}",
+                        s.parser, field, unknown
+                    )));
+                }
+            }
+        }
+        Ok(dyn_schema)
+    }
+
+    // Adds the parsers and enums found in `schema`.
+    fn add_reflected(&mut self, schema: &Schema) -> io::Result<()> {
+        match schema {
+            Schema::Struct {
+                name,
+                parser,
+                params,
+                fields,
+            } => {
+                if self.structs.iter().any(|s| s.parser == *parser) {
+                    return Ok(());
+                }
+                // Added before the fields so that the root stays first.
+                let index = self.structs.len();
+                self.structs.push(DynStruct {
+                    parser: parser.clone(),
+                    name: name.clone(),
+                    params: params.clone(),
+                    items: Vec::new(),
+                });
+                let mut items = Vec::new();
+                for field in fields {
+                    self.add_reflected(&field.schema)?;
+                    items.push(reflected_item(field).map_err(|e| {
+                        let field = field.name.as_deref().unwrap_or(&field.source);
+                        invalid_data_error(format!("{}.{}: {}", parser, field, e.message))
+                    })?);
+                }
+                self.structs[index].items = items;
+                Ok(())
+            }
+            Schema::Enum {
+                name,
+                inner,
+                variants,
+            } => {
+                let int = match **inner {
+                    Schema::Int {
+                        signed,
+                        bits,
+                        endian,
+                    } => IntType {
+                        signed,
+                        bits,
+                        endian: endian.unwrap_or(Endian::Big),
+                    },
+                    _ => return Err(invalid_data_error(format!("enum {} is not an integer", name))),
+                };
+                if self.find_enum(name).is_none() {
+                    self.enums.push(DynEnum {
+                        name: name.clone(),
+                        int,
+                        variants: variants.clone(),
+                    });
+                }
+                Ok(())
+            }
+            Schema::Conditional(branches) => branches
+                .iter()
+                .flat_map(|b| &b.fields)
+                .try_for_each(|f| self.add_reflected(&f.schema)),
+            Schema::Wrap { inner, .. }
+            | Schema::Tag { inner, .. }
+            | Schema::Any(inner)
+            | Schema::Optional { inner, .. }
+            | Schema::List { inner, .. } => self.add_reflected(inner),
+            Schema::Take { prefix, inner } => {
+                if let Some(prefix) = prefix {
+                    self.add_reflected(prefix)?;
+                }
+                self.add_reflected(inner)
+            }
+            Schema::Int { .. } | Schema::Leaf { .. } | Schema::Padding(_) | Schema::Opaque(_) => {
+                Ok(())
+            }
+        }
+    }
+
+    /// Deener for the first parser.
+    pub fn root(&self) -> io::Result<DynParser<'_>> {
+        let root = self
+            .structs
+            .first()
+            .ok_or_else(|| invalid_data_error("schema has no parsers"))?;
+        self.parser(&root.parser, &[])
+    }
+
+    fn find_struct(&self, name: &str) -> io::Result<&DynStruct> {
+        self.structs
+            .iter()
+            .find(|s| s.parser == name)
+            .ok_or_else(|| invalid_data_error(format!("unknown parser {}", name)))
+    }
+
+    fn find_enum(&self, name: &str) -> Option<&DynEnum> {
+        self.enums.iter().find(|e| e.name == name)
+    }
+
+    fn variant_value(&self, name: &str, variant: &str) -> io::Result<i128> {
+        self.find_enum(name)
+            .and_then(|e| e.variants.iter().find(|(n, _)| n == variant))
+            .map(|(_, v)| *v)
+            .ok_or_else(|| {
+                invalid_data_error(format!("unknown enum variant {}::{}", name, variant))
+            })
+    }
+}
+
+/// Deener of a parser of a `DynSchema`, see `DynSchema::parser`.
+pub struct DynParser<'a> {
+    schema: &'a DynSchema,
+    name: String,
+    args: Vec<(String, DynValue)>,
+}
+
+impl Deen for DynParser<'_> {
+    type Item = DynValue;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        encode_struct(self.schema, &self.name, &self.args, value, &mut buf)
+    }
+
+    /// A parser may refer to itself before reading any input, so unless the caller limits the
+    /// nesting depth, it is limited to `DEFAULT_MAX_DEPTH`.
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        match DecodeLimits::current() {
+            Some(limits) if limits.limits_depth() => Unbounded(self).decode(buf),
+            _ => DecodeLimits::new()
+                .max_depth(DEFAULT_MAX_DEPTH)
+                .decode(&Unbounded(self), buf),
+        }
+    }
+
+    fn schema(&self) -> Schema {
+        match self.schema.find_struct(&self.name) {
+            Ok(s) => struct_schema(self.schema, s, &self.args),
+            Err(_) => Schema::Opaque(self.name.clone()),
+        }
+    }
+}
+
+/// Nesting depth of parsers that `DynParser` decodes with if no limit is set.
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+// Decodes a `DynParser` without setting up the default depth limit.
+struct Unbounded<'a, 'b>(&'a DynParser<'b>);
+
+impl Deen for Unbounded<'_, '_> {
+    type Item = DynValue;
+
+    fn encode(&self, value: &DynValue, buf: impl io::Write) -> io::Result<()> {
+        self.0.encode(value, buf)
+    }
+
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<DynValue> {
+        let p = self.0;
+        decode_struct(p.schema, &p.name, &p.args, &mut buf)
+    }
+}
+
+// Names visible to expressions: the parameters, then the fields decoded so far.
+struct Context<'a> {
+    schema: &'a DynSchema,
+    params: &'a [(String, DynValue)],
+    fields: Vec<(String, DynValue)>,
+    // Declared enum of each field, to evaluate comparisons with variants.
+    types: Vec<(String, String)>,
+}
+
+impl<'a> Context<'a> {
+    fn new(
+        schema: &'a DynSchema,
+        s: &DynStruct,
+        params: &'a [(String, DynValue)],
+        fields: Vec<(String, DynValue)>,
+    ) -> Context<'a> {
+        let types = s
+            .params
+            .iter()
+            .filter(|(_, ty)| schema.find_enum(ty).is_some())
+            .cloned()
+            .collect();
+        Context {
+            schema,
+            params,
+            fields,
+            types,
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&DynValue> {
+        self.fields
+            .iter()
+            .rev()
+            .chain(self.params.iter().rev())
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v)
+    }
+
+    fn enum_of(&self, name: &str) -> io::Result<&str> {
+        self.types
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, e)| e.as_str())
+            .ok_or_else(|| invalid_data_error(format!("{} is not an enum", name)))
+    }
+
+    fn record_type(&mut self, name: &str, ty: &DynType) {
+        let e = match ty {
+            DynType::Enum(e) => e,
+            DynType::Optional { item, .. } if self.schema.find_enum(item).is_some() => item,
+            _ => return,
+        };
+        self.types.push((name.to_string(), e.clone()));
+    }
+}
+
+fn decode_struct(
+    schema: &DynSchema,
+    name: &str,
+    args: &[(String, DynValue)],
+    buf: &mut dyn io::Read,
+) -> io::Result<DynValue> {
+    let _depth = DecodeLimits::enter()?;
+    let s = schema.find_struct(name)?;
+    let mut ctx = Context::new(schema, s, args, Vec::new());
+    decode_items(&s.items, &mut ctx, buf)?;
+    Ok(DynValue::Struct(ctx.fields))
+}
+
+fn decode_items(items: &[DynItem], ctx: &mut Context, buf: &mut dyn io::Read) -> io::Result<()> {
+    for item in items {
+        match item {
+            DynItem::Field { name, ty } => {
+                let trace = Trace::enter(name);
+                let value = decode_type(ty, ctx, buf)?;
+                trace.done(|| Some(value.to_string()));
+                ctx.record_type(name, ty);
+                ctx.fields.push((name.clone(), value));
+            }
+            DynItem::Tag { ty, value } => {
+                let trace = Trace::enter(&item.to_string());
+                let expected = tag_value(ty, value.eval(ctx)?, ctx)?;
+                let found = decode_type(ty, ctx, buf)?;
+                if found != expected {
+                    return Err(invalid_data_error(format!(
+                        "unexpected tag - expected: {}, found: {}",
+                        expected, found
+                    )));
+                }
+                trace.done(|| None);
+            }
+            DynItem::Any(ty) => {
+                let trace = Trace::enter(&item.to_string());
+                let found = decode_type(ty, ctx, buf)?;
+                if is_canonical() && Some(&found) != default_value(ty).as_ref() {
+                    return Err(invalid_data_error(format!(
+                        "non-canonical value of ignored field: {}",
+                        found
+                    )));
+                }
+                trace.done(|| None);
+            }
+            DynItem::Padding(len) => {
+                let trace = Trace::enter(&item.to_string());
+                Padding::new(len.eval_len(ctx)? as usize).compare(&mut *buf)?;
+                trace.done(|| None);
+            }
+            DynItem::If { branches, .. } => {
+                if let Some(b) = choose_branch(branches, ctx)? {
+                    decode_items(&b.items, ctx, buf)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn choose_branch<'a>(
+    branches: &'a [DynBranch],
+    ctx: &Context,
+) -> io::Result<Option<&'a DynBranch>> {
+    for b in branches {
+        match &b.condition {
+            Some(c) if c.eval(ctx)? == 0 => {}
+            _ => return Ok(Some(b)),
+        }
+    }
+    Ok(None)
+}
+
+// Value of a tag of type `ty`, which is an integer or an enum.
+fn tag_value(ty: &DynType, v: i128, ctx: &Context) -> io::Result<DynValue> {
+    match ty {
+        DynType::Enum(name) => variant(find_enum(ctx, name)?, v),
+        _ => Ok(DynValue::Int(v)),
+    }
+}
+
+fn default_value(ty: &DynType) -> Option<DynValue> {
+    Some(match ty {
+        DynType::Int(_) | DynType::VarU64 | DynType::VarI64 => DynValue::Int(0),
+        DynType::Fixed(..) | DynType::Scaled { .. } => DynValue::Float(0.0),
+        DynType::Bytes(_) => DynValue::Bytes(Vec::new()),
+        DynType::Utf8(_) => DynValue::String(String::new()),
+        DynType::Repeat(..) => DynValue::List(Vec::new()),
+        DynType::Take { inner, .. } | DynType::Prefixed { inner, .. } => default_value(inner)?,
+        DynType::Optional { .. } => DynValue::Option(None),
+        DynType::Enum(_) | DynType::Parser { .. } => return None,
+    })
+}
+
+// Deener for a `DynType` in the context of the parser it appears in, so that the generic
+// combinators like `Take` and `Repeat` can wrap it.
+struct Dyn<'a, 'c> {
+    ty: &'a DynType,
+    ctx: &'a Context<'c>,
+}
+
+impl Deen for Dyn<'_, '_> {
+    type Item = DynValue;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        encode_type(self.ty, self.ctx, value, &mut buf)
+    }
+
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        decode_type(self.ty, self.ctx, &mut buf)
+    }
+}
+
+fn inner<'a, 'c>(ty: &'a DynType, ctx: &'a Context<'c>) -> Dyn<'a, 'c> {
+    Dyn { ty, ctx }
+}
+
+fn decode_type(ty: &DynType, ctx: &Context, buf: &mut dyn io::Read) -> io::Result<DynValue> {
+    Ok(match ty {
+        DynType::Int(int) => DynValue::Int(int.decode(buf)?),
+        DynType::VarU64 => DynValue::Int(i128::from(VarU64.decode(buf)?)),
+        DynType::VarI64 => DynValue::Int(i128::from(VarI64.decode(buf)?)),
+        DynType::Fixed(int, frac_bits) => {
+            DynValue::Float(with_int!(int, d => Fixed::new(d, *frac_bits).decode(buf))?)
+        }
+        DynType::Scaled { int, scale, offset } => DynValue::Float(with_int!(int, d => {
+            Scaled::new(d, *scale).with_offset(*offset).decode(buf)
+        })?),
+        DynType::Bytes(len) => DynValue::Bytes(bytes(len, ctx)?.decode(buf)?),
+        DynType::Utf8(len) => DynValue::String(utf8(len, ctx)?.decode(buf)?),
+        DynType::Repeat(count, ty) => DynValue::List(repeat(count, ty, ctx)?.decode(buf)?),
+        DynType::Take {
+            len,
+            inner: ty,
+            skip_remaining,
+        } => take(len, ty, *skip_remaining, ctx)?.decode(buf)?,
+        DynType::Prefixed {
+            len,
+            inner: ty,
+            skip_remaining,
+        } => with_int!(len, d => prefixed(d, ty, *skip_remaining, ctx).decode(buf))?,
+        DynType::Enum(name) => {
+            let e = find_enum(ctx, name)?;
+            variant(e, e.int.decode(buf)?)?
+        }
+        DynType::Optional {
+            item,
+            inner: ty,
+            presence,
+        } => {
+            let value = match presence {
+                DynPresence::When(cond) => {
+                    let present = cond.eval(ctx)? != 0;
+                    Optional::<DynValue>::wrap(inner(ty, ctx))
+                        .decode_when(move || present)
+                        .decode(buf)?
+                }
+                DynPresence::RemainingInput => Optional::<DynValue>::wrap(inner(ty, ctx))
+                    .if_remaining()
+                    .decode(buf)?,
+                DynPresence::Byte => Optional::<DynValue>::wrap(inner(ty, ctx))
+                    .with_presence_byte()
+                    .decode(buf)?,
+            };
+            let value = match (value, ctx.schema.find_enum(item)) {
+                (Some(DynValue::Int(v)), Some(e)) => Some(variant(e, v)?),
+                (value, _) => value,
+            };
+            DynValue::Option(value.map(Box::new))
+        }
+        DynType::Parser { name, args } => {
+            let args = eval_args(args, ctx)?;
+            decode_struct(ctx.schema, name, &args, buf)?
+        }
+    })
+}
+
+fn bytes(len: &Option<Expr>, ctx: &Context) -> io::Result<Bytes> {
+    Ok(match len {
+        Some(len) => Bytes::new(len.eval_len(ctx)?),
+        None => Bytes::remaining(),
+    })
+}
+
+fn utf8(len: &Option<Expr>, ctx: &Context) -> io::Result<Utf8> {
+    Ok(match len {
+        Some(len) => Utf8::new(len.eval_len(ctx)?),
+        None => Utf8::remaining(),
+    })
+}
+
+fn repeat<'a, 'c>(
+    count: &Option<Expr>,
+    ty: &'a DynType,
+    ctx: &'a Context<'c>,
+) -> io::Result<Repeat<Dyn<'a, 'c>>> {
+    Ok(match count {
+        Some(count) => Repeat::new(count.eval_len(ctx)?, inner(ty, ctx)),
+        None => Repeat::remaining(inner(ty, ctx)),
+    })
+}
+
+fn take<'a, 'c>(
+    len: &Expr,
+    ty: &'a DynType,
+    skip_remaining: bool,
+    ctx: &'a Context<'c>,
+) -> io::Result<Take<Dyn<'a, 'c>>> {
+    let take = Take::new(len.eval_len(ctx)?, inner(ty, ctx));
+    Ok(if skip_remaining {
+        take.skip_remaining()
+    } else {
+        take
+    })
+}
+
+fn prefixed<'a, 'c, L>(
+    len: L,
+    ty: &'a DynType,
+    skip_remaining: bool,
+    ctx: &'a Context<'c>,
+) -> crate::Prefixed<L, Dyn<'a, 'c>>
+where
+    L: Deen,
+    <L as Deen>::Item: Integer,
+{
+    let prefixed = Take::prefixed(len, inner(ty, ctx));
+    if skip_remaining {
+        prefixed.skip_remaining()
+    } else {
+        prefixed
+    }
+}
+
+fn find_enum<'a>(ctx: &Context<'a>, name: &str) -> io::Result<&'a DynEnum> {
+    ctx.schema
+        .find_enum(name)
+        .ok_or_else(|| invalid_data_error(format!("unknown enum {}", name)))
+}
+
+fn variant(e: &DynEnum, v: i128) -> io::Result<DynValue> {
+    e.variants
+        .iter()
+        .find(|(_, value)| *value == v)
+        .map(|(n, _)| DynValue::Enum(n.clone()))
+        .ok_or_else(|| invalid_data_error(format!("{} is not a valid {} value", v, e.name)))
+}
+
+fn variant_value(e: &DynEnum, value: &DynValue) -> io::Result<i128> {
+    match value {
+        DynValue::Enum(n) => e
+            .variants
+            .iter()
+            .find(|(name, _)| name == n)
+            .map(|(_, v)| *v)
+            .ok_or_else(|| invalid_data_error(format!("{} is not a variant of {}", n, e.name))),
+        v => Err(type_error("an enum variant", v)),
+    }
+}
+
+fn eval_args(args: &[(String, Expr)], ctx: &Context) -> io::Result<Vec<(String, DynValue)>> {
+    args.iter()
+        .map(|(name, e)| {
+            let v = match e {
+                Expr::Var(v) => ctx.lookup(v).cloned(),
+                _ => None,
+            };
+            Ok((
+                name.clone(),
+                v.map_or_else(|| e.eval(ctx).map(DynValue::Int), Ok)?,
+            ))
+        })
+        .collect()
+}
+
+fn type_error(expected: &str, found: &DynValue) -> io::Error {
+    invalid_data_error(format!("expected {}, found {}", expected, found))
+}
+
+fn encode_struct(
+    schema: &DynSchema,
+    name: &str,
+    args: &[(String, DynValue)],
+    value: &DynValue,
+    buf: &mut dyn io::Write,
+) -> io::Result<()> {
+    let s = schema.find_struct(name)?;
+    let fields = match value {
+        DynValue::Struct(fields) => fields,
+        v => return Err(type_error(&format!("a {} struct", s.name), v)),
+    };
+    let mut ctx = Context::new(schema, s, args, fields.clone());
+    record_types(&s.items, &mut ctx);
+    encode_items(&s.items, &ctx, value, buf)
+}
+
+fn record_types(items: &[DynItem], ctx: &mut Context) {
+    for item in items {
+        match item {
+            DynItem::Field { name, ty } => ctx.record_type(name, ty),
+            DynItem::If { branches, .. } => {
+                for b in branches {
+                    record_types(&b.items, ctx);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn encode_items(
+    items: &[DynItem],
+    ctx: &Context,
+    value: &DynValue,
+    buf: &mut dyn io::Write,
+) -> io::Result<()> {
+    for item in items {
+        match item {
+            DynItem::Field { name, ty } => {
+                let v = value
+                    .field(name)
+                    .ok_or_else(|| invalid_data_error(format!("missing field {}", name)))?;
+                encode_type(ty, ctx, v, buf)?;
+            }
+            DynItem::Tag { ty, value } => {
+                encode_type(ty, ctx, &tag_value(ty, value.eval(ctx)?, ctx)?, buf)?;
+            }
+            DynItem::Any(ty) => {
+                let v = default_value(ty)
+                    .ok_or_else(|| invalid_data_error(format!("{} has no default value", ty)))?;
+                encode_type(ty, ctx, &v, buf)?;
+            }
+            DynItem::Padding(len) => {
+                Padding::new(len.eval_len(ctx)? as usize).encode_value(&mut *buf)?;
+            }
+            DynItem::If { branches, .. } => {
+                if let Some(b) = choose_branch(branches, ctx)? {
+                    encode_items(&b.items, ctx, value, buf)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn int_of(value: &DynValue) -> io::Result<i128> {
+    value
+        .as_int()
+        .ok_or_else(|| type_error("an integer", value))
+}
+
+fn encode_type(
+    ty: &DynType,
+    ctx: &Context,
+    value: &DynValue,
+    buf: &mut dyn io::Write,
+) -> io::Result<()> {
+    let float = |v: &DynValue| match v {
+        DynValue::Float(f) => Ok(*f),
+        DynValue::Int(i) => Ok(*i as f64),
+        v => Err(type_error("a number", v)),
+    };
+    match (ty, value) {
+        (DynType::Int(int), v) => int.encode(int_of(v)?, buf),
+        (DynType::VarU64, v) => {
+            let v = u64::try_from(int_of(v)?).map_err(invalid_data_error)?;
+            VarU64.encode(&v, buf)
+        }
+        (DynType::VarI64, v) => {
+            let v = i64::try_from(int_of(v)?).map_err(invalid_data_error)?;
+            VarI64.encode(&v, buf)
+        }
+        (DynType::Fixed(int, frac_bits), v) => {
+            let v = float(v)?;
+            with_int!(int, d => Fixed::new(d, *frac_bits).encode(&v, buf))
+        }
+        (DynType::Scaled { int, scale, offset }, v) => {
+            let v = float(v)?;
+            with_int!(int, d => Scaled::new(d, *scale).with_offset(*offset).encode(&v, buf))
+        }
+        (DynType::Bytes(len), DynValue::Bytes(b)) => bytes(len, ctx)?.encode(b, buf),
+        (DynType::Utf8(len), DynValue::String(s)) => utf8(len, ctx)?.encode(s, buf),
+        (DynType::Repeat(count, ty), DynValue::List(items)) => {
+            repeat(count, ty, ctx)?.encode(items, buf)
+        }
+        (
+            DynType::Take {
+                len,
+                inner: ty,
+                skip_remaining,
+            },
+            v,
+        ) => take(len, ty, *skip_remaining, ctx)?.encode(v, buf),
+        (
+            DynType::Prefixed {
+                len,
+                inner: ty,
+                skip_remaining,
+            },
+            v,
+        ) => with_int!(len, d => prefixed(d, ty, *skip_remaining, ctx).encode(v, buf)),
+        (DynType::Enum(name), v) => {
+            let e = find_enum(ctx, name)?;
+            e.int.encode(variant_value(e, v)?, buf)
+        }
+        (
+            DynType::Optional {
+                item,
+                inner: ty,
+                presence,
+            },
+            DynValue::Option(v),
+        ) => {
+            let v = match (v, ctx.schema.find_enum(item)) {
+                (Some(v), Some(e)) => Some(DynValue::Int(variant_value(e, v)?)),
+                (v, _) => v.as_deref().cloned(),
+            };
+            let opt = Optional::<DynValue>::wrap(inner(ty, ctx));
+            match presence {
+                DynPresence::When(cond) => {
+                    let present = cond.eval(ctx)? != 0;
+                    opt.decode_when(move || present).encode(&v, buf)
+                }
+                DynPresence::RemainingInput => opt.if_remaining().encode(&v, buf),
+                DynPresence::Byte => opt.with_presence_byte().encode(&v, buf),
+            }
+        }
+        (DynType::Parser { name, args }, v) => {
+            let args = eval_args(args, ctx)?;
+            encode_struct(ctx.schema, name, &args, v, buf)
+        }
+        (ty, v) => Err(invalid_data_error(format!("{} can not encode {}", ty, v))),
+    }
+}
+
+fn reflected_item(field: &Field) -> Result<DynItem, ParseError> {
+    match (&field.name, &field.schema) {
+        (name, Schema::Conditional(branches)) => Ok(DynItem::If {
+            name: name.clone(),
+            branches: branches
+                .iter()
+                .map(|b| {
+                    Ok(DynBranch {
+                        condition: b.condition.as_deref().map(parse::expr).transpose()?,
+                        items: b.fields.iter().map(reflected_item).collect::<Result<_, _>>()?,
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+        }),
+        (Some(name), _) => Ok(DynItem::Field {
+            name: name.clone(),
+            ty: parse::dyn_type(&field.source)?,
+        }),
+        (None, _) => parse::value(&field.source),
+    }
+}
+
+fn item_unknown_ref<'a>(schema: &DynSchema, item: &'a DynItem) -> Option<&'a str> {
+    match item {
+        DynItem::Field { ty, .. } | DynItem::Tag { ty, .. } | DynItem::Any(ty) => {
+            unknown_ref(schema, ty)
+        }
+        DynItem::Padding(_) => None,
+        DynItem::If { branches, .. } => branches
+            .iter()
+            .flat_map(|b| &b.items)
+            .find_map(|i| item_unknown_ref(schema, i)),
+    }
+}
+
+fn unknown_ref<'a>(schema: &DynSchema, ty: &'a DynType) -> Option<&'a str> {
+    match ty {
+        DynType::Parser { name, .. } if !schema.structs.iter().any(|s| s.parser == *name) => {
+            Some(name)
+        }
+        DynType::Enum(name) if schema.find_enum(name).is_none() => Some(name),
+        DynType::Repeat(_, inner)
+        | DynType::Take { inner, .. }
+        | DynType::Prefixed { inner, .. }
+        | DynType::Optional { inner, .. } => unknown_ref(schema, inner),
+        _ => None,
+    }
+}
+
+fn struct_schema(schema: &DynSchema, s: &DynStruct, args: &[(String, DynValue)]) -> Schema {
+    // Parameters are known, fields are not, so only tags that depend on parameters alone
+    // get their value.
+    let ctx = Context::new(schema, s, args, Vec::new());
+    Schema::Struct {
+        name: s.name.clone(),
+        parser: s.parser.clone(),
+        params: s.params.clone(),
+        fields: s.items.iter().map(|i| item_schema(&ctx, i)).collect(),
+    }
+}
+
+fn item_schema(ctx: &Context, item: &DynItem) -> Field {
+    let (name, schema) = match item {
+        DynItem::Field { name, ty } => (Some(name.clone()), type_schema(ctx.schema, ty)),
+        DynItem::Tag { ty, value } => {
+            let value = match value.eval(ctx).and_then(|v| tag_value(ty, v, ctx)) {
+                Ok(v) => v.to_string(),
+                Err(_) => value.to_string(),
+            };
+            let inner = Box::new(type_schema(ctx.schema, ty));
+            (None, Schema::Tag { inner, value })
+        }
+        DynItem::Any(ty) => (None, Schema::Any(Box::new(type_schema(ctx.schema, ty)))),
+        DynItem::Padding(len) => match len.literal() {
+            Some(len) => (None, Schema::Padding(len as u64)),
+            None => (None, Schema::Opaque(item.to_string())),
+        },
+        DynItem::If { name, branches } => {
+            let branches = branches
+                .iter()
+                .map(|b| crate::Branch {
+                    condition: b.condition.as_ref().map(Expr::to_string),
+                    fields: b.items.iter().map(|i| item_schema(ctx, i)).collect(),
+                })
+                .collect();
+            (name.clone(), Schema::Conditional(branches))
+        }
+    };
+    let source = match item {
+        DynItem::Field { ty, .. } => ty.to_string(),
+        _ => {
+            let mut source = String::new();
+            let _ = item.fmt_indented(&mut source, 0, false);
+            source
+        }
+    };
+    Field {
+        name,
+        source,
+        schema,
+    }
+}
+
+fn type_schema(schema: &DynSchema, ty: &DynType) -> Schema {
+    let wrap = |name, inner: &DynType| Schema::wrap(name, type_schema(schema, inner));
+    let len = |len: &Option<Expr>| match len {
+        Some(len) => len.literal().map(|len| len as u64),
+        None => None,
+    };
+    match ty {
+        DynType::Int(int) => int.schema(),
+        DynType::VarU64 => VarU64.schema(),
+        DynType::VarI64 => VarI64.schema(),
+        DynType::Fixed(int, _) => wrap("Fixed", &DynType::Int(*int)),
+        DynType::Scaled { int, .. } => wrap("Scaled", &DynType::Int(*int)),
+        DynType::Bytes(l) => Schema::leaf("Bytes", len(l), None),
+        DynType::Utf8(l) => Schema::leaf("Utf8", len(l), None),
+        DynType::Repeat(count, inner) => Schema::List {
+            count: len(count),
+            inner: Box::new(type_schema(schema, inner)),
+        },
+        DynType::Take { inner, .. } => Schema::Take {
+            prefix: None,
+            inner: Box::new(type_schema(schema, inner)),
+        },
+        DynType::Prefixed { len, inner, .. } => Schema::Take {
+            prefix: Some(Box::new(len.schema())),
+            inner: Box::new(type_schema(schema, inner)),
+        },
+        DynType::Enum(name) => match schema.find_enum(name) {
+            Some(e) => Schema::Enum {
+                name: e.name.clone(),
+                inner: Box::new(e.int.schema()),
+                variants: e.variants.clone(),
+            },
+            None => Schema::Opaque(name.clone()),
+        },
+        DynType::Optional {
+            inner, presence, ..
+        } => Schema::Optional {
+            inner: Box::new(type_schema(schema, inner)),
+            presence: match presence {
+                DynPresence::When(_) => PresenceRule::Condition,
+                DynPresence::RemainingInput => PresenceRule::RemainingInput,
+                DynPresence::Byte => PresenceRule::Byte,
+            },
+        },
+        DynType::Parser { name, .. } => match schema.find_struct(name) {
+            Ok(s) => struct_schema(schema, s, &[]),
+            Err(_) => Schema::Opaque(name.clone()),
+        },
+    }
+}
+
+fn fmt_len(
+    f: &mut fmt::Formatter,
+    name: &str,
+    len: &Option<Expr>,
+    inner: Option<&DynType>,
+) -> fmt::Result {
+    match (len, inner) {
+        (Some(len), Some(inner)) => write!(f, "{}::new({}, {})", name, len, inner),
+        (Some(len), None) => write!(f, "{}::new({})", name, len),
+        (None, Some(inner)) => write!(f, "{}::remaining({})", name, inner),
+        (None, None) => write!(f, "{}::remaining()", name),
+    }
+}
+
+/// The deener expression as written in a `deen!` block.
+impl fmt::Display for DynType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let skip = |s: bool| if s { ".skip_remaining()" } else { "" };
+        match self {
+            DynType::Int(int) => write!(f, "{}", int),
+            DynType::VarU64 => f.write_str("VarU64"),
+            DynType::VarI64 => f.write_str("VarI64"),
+            DynType::Fixed(int, frac_bits) => write!(f, "Fixed::new({}, {})", int, frac_bits),
+            DynType::Scaled { int, scale, offset } => {
+                write!(f, "Scaled::new({}, {:?})", int, scale)?;
+                if *offset != 0.0 {
+                    write!(f, ".with_offset({:?})", offset)?;
+                }
+                Ok(())
+            }
+            DynType::Bytes(len) => fmt_len(f, "Bytes", len, None),
+            DynType::Utf8(len) => fmt_len(f, "Utf8", len, None),
+            DynType::Repeat(count, inner) => fmt_len(f, "Repeat", count, Some(inner)),
+            DynType::Take {
+                len,
+                inner,
+                skip_remaining,
+            } => write!(f, "Take::new({}, {}){}", len, inner, skip(*skip_remaining)),
+            DynType::Prefixed {
+                len,
+                inner,
+                skip_remaining,
+            } => write!(
+                f,
+                "Take::prefixed({}, {}){}",
+                len,
+                inner,
+                skip(*skip_remaining)
+            ),
+            DynType::Enum(name) => write!(f, "{}::deen()", name),
+            DynType::Optional {
+                item,
+                inner,
+                presence,
+            } => {
+                // A space keeps `Vec<u8> >` from lexing as a shift.
+                let space = if item.ends_with('>') { " " } else { "" };
+                write!(f, "Optional::<{}{}>::wrap({})", item, space, inner)?;
+                match presence {
+                    DynPresence::When(cond) => write!(f, ".decode_when(|| {})", cond),
+                    DynPresence::RemainingInput => f.write_str(".if_remaining()"),
+                    DynPresence::Byte => f.write_str(".with_presence_byte()"),
+                }
+            }
+            DynType::Parser { name, args } => {
+                f.write_str(name)?;
+                if !args.is_empty() {
+                    f.write_str(" {")?;
+                    for (i, (param, e)) in args.iter().enumerate() {
+                        f.write_str(if i > 0 { ", " } else { " " })?;
+                        match e {
+                            Expr::Var(v) if v == param => f.write_str(param)?,
+                            e => write!(f, "{}: {}", param, e)?,
+                        }
+                    }
+                    f.write_str(" }")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl DynItem {
+    // `named` includes the `name ~` of fields.
+    fn fmt_indented(&self, f: &mut dyn fmt::Write, indent: usize, named: bool) -> fmt::Result {
+        match self {
+            DynItem::Field { name, ty } if named => write!(f, "{} ~ {}", name, ty),
+            DynItem::Field { ty, .. } => write!(f, "{}", ty),
+            DynItem::Tag { ty, value } => write!(f, "Tag::new({}, {})", ty, value),
+            DynItem::Any(ty) => write!(f, "Any::new({})", ty),
+            DynItem::Padding(len) => write!(f, "Padding::new({})", len),
+            DynItem::If { name, branches } => {
+                if let (Some(name), true) = (name, named) {
+                    write!(f, "{} ~ ", name)?;
+                }
+                for (i, b) in branches.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" else ")?;
+                    }
+                    if let Some(c) = &b.condition {
+                        write!(f, "if {} ", c)?;
+                    }
+                    f.write_str("{\n")?;
+                    for (n, item) in b.items.iter().enumerate() {
+                        write!(f, "{:1$}", "", (indent + 1) * 4)?;
+                        let last = n + 1 == b.items.len();
+                        item.fmt_indented(f, indent + 1, name.is_none() || !last)?;
+                        f.write_str(if last { "\n" } else { ";\n" })?;
+                    }
+                    write!(f, "{:1$}}}", "", indent * 4)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for DynItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_indented(f, 0, true)
+    }
+}
+
+/// A complete `deen!` block.
+impl fmt::Display for DynStruct {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "struct {}", self.parser)?;
+        if !self.params.is_empty() {
+            let params: Vec<_> = self
+                .params
+                .iter()
+                .map(|(n, t)| format!("{}: {}", n, t))
+                .collect();
+            write!(f, "({})", params.join(", "))?;
+        }
+        writeln!(f, " for {} {{", self.name)?;
+        for item in &self.items {
+            f.write_str("    ")?;
+            item.fmt_indented(f, 1, true)?;
+            f.write_str(",\n")?;
+        }
+        f.write_str("}")
+    }
+}
+
+/// Declaration as for `DeenEnum`.
+impl fmt::Display for DynEnum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "#[deen({})]", self.int)?;
+        writeln!(f, "enum {} {{", self.name)?;
+        for (name, v) in &self.variants {
+            writeln!(f, "    {} = {},", name, v)?;
+        }
+        f.write_str("}")
+    }
+}
+
+/// Enums and parsers, separated by blank lines, in the syntax `DynSchema::from_str` parses.
+impl fmt::Display for DynSchema {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let enums = self.enums.iter().map(|e| e.to_string());
+        let structs = self.structs.iter().map(|s| s.to_string());
+        let blocks: Vec<_> = enums.chain(structs).collect();
+        f.write_str(&blocks.join("\n\n"))
+    }
+}
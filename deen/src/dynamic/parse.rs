@@ -0,0 +1,913 @@
+use std::{error, fmt, io, str::FromStr};
+
+use super::{
+    BinOp, DynBranch, DynEnum, DynItem, DynPresence, DynSchema, DynStruct, DynType, Expr, IntType,
+    UnOp,
+};
+use crate::invalid_data_error;
+
+/// Error in a text schema, with the 1-based position it was found at.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl error::Error for ParseError {}
+
+impl From<ParseError> for io::Error {
+    fn from(e: ParseError) -> io::Error {
+        invalid_data_error(e)
+    }
+}
+
+/// Parses a text schema. Parsers are written as the body of a `deen!` block, enums as the
+/// Rust declaration with the `#[deen(..)]` attribute of `DeenEnum`:
+///
+/// ```text
+/// #[deen(U8)]
+/// enum Kind {
+///     File = 1,
+///     Dir = 2,
+/// }
+///
+/// struct EntryParser for Entry {
+///     Tag::new(U32be, 0xcafebabe),
+///     kind ~ Kind::deen(),
+///     len ~ U16be,
+///     name ~ Utf8::new(len),
+/// }
+/// ```
+///
+/// Attributes other than `#[deen(..)]`, visibility and `//` comments are ignored, so a file can
+/// also be Rust source.
+impl FromStr for DynSchema {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<DynSchema, ParseError> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            pos: 0,
+            refs: Vec::new(),
+        };
+        let schema = parser.schema()?;
+        parser.check_refs(&schema)?;
+        Ok(schema)
+    }
+}
+
+// Parses all of `text` with `f`, for the sources of reflected fields.
+fn fragment<T>(
+    text: &str,
+    f: impl FnOnce(&mut Parser) -> Result<T, ParseError>,
+) -> Result<T, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        pos: 0,
+        refs: Vec::new(),
+    };
+    let v = f(&mut parser)?;
+    match parser.peek() {
+        Tok::Eof => Ok(v),
+        _ => parser.error("end of input"),
+    }
+}
+
+pub(super) fn dyn_type(text: &str) -> Result<DynType, ParseError> {
+    fragment(text, Parser::dyn_type)
+}
+
+pub(super) fn value(text: &str) -> Result<DynItem, ParseError> {
+    fragment(text, Parser::value)
+}
+
+pub(super) fn expr(text: &str) -> Result<Expr, ParseError> {
+    fragment(text, Parser::expr)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tok {
+    Ident(String),
+    Int(i128, bool),
+    Float(f64),
+    Punct(&'static str),
+    Eof,
+}
+
+impl fmt::Display for Tok {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tok::Ident(i) => write!(f, "`{}`", i),
+            Tok::Int(..) | Tok::Float(_) => f.write_str("number"),
+            Tok::Punct(p) => write!(f, "`{}`", p),
+            Tok::Eof => f.write_str("end of input"),
+        }
+    }
+}
+
+struct Token {
+    tok: Tok,
+    line: usize,
+    column: usize,
+}
+
+const PUNCT: &[&str] = &[
+    "::", "==", "!=", "<=", ">=", "&&", "||", "<<", ">>", "~", ",", ";", ":", "(", ")", "{", "}",
+    "[", "]", "<", ">", "=", "+", "-", "*", "/", "%", "&", "^", "|", "!", ".", "#",
+];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ParseError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let (mut i, mut line, mut line_start) = (0, 1, 0);
+    loop {
+        while i < chars.len() {
+            if chars[i] == '\n' {
+                line += 1;
+                line_start = i + 1;
+                i += 1;
+            } else if chars[i].is_whitespace() {
+                i += 1;
+            } else if chars[i..].starts_with(&['/', '/']) {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            } else if chars[i..].starts_with(&['/', '*']) {
+                i += 2;
+                while i < chars.len() && !chars[i..].starts_with(&['*', '/']) {
+                    if chars[i] == '\n' {
+                        line += 1;
+                        line_start = i + 1;
+                    }
+                    i += 1;
+                }
+                i += 2;
+            } else {
+                break;
+            }
+        }
+        let column = i - line_start + 1;
+        let error = |message: String| ParseError {
+            line,
+            column,
+            message,
+        };
+        let tok = match chars.get(i) {
+            None => Tok::Eof,
+            Some(c) if c.is_alphabetic() || *c == '_' => {
+                let len = word_len(&chars[i..]);
+                let ident = chars[i..i + len].iter().collect();
+                i += len;
+                Tok::Ident(ident)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let mut len = word_len(&chars[i..]);
+                let is_float = chars.get(i + len) == Some(&'.')
+                    && chars.get(i + len + 1).is_some_and(char::is_ascii_digit);
+                if is_float {
+                    len += 1 + word_len(&chars[i + len + 1..]);
+                }
+                let literal: String = chars[i..i + len].iter().collect();
+                i += len;
+                number(&literal).ok_or_else(|| error(format!("invalid number `{}`", literal)))?
+            }
+            Some(c) => {
+                let p = PUNCT
+                    .iter()
+                    .find(|p| {
+                        p.chars().zip(&chars[i..]).filter(|(a, b)| a == *b).count() == p.len()
+                    })
+                    .ok_or_else(|| error(format!("unexpected character `{}`", c)))?;
+                i += p.len();
+                Tok::Punct(p)
+            }
+        };
+        let eof = tok == Tok::Eof;
+        tokens.push(Token { tok, line, column });
+        if eof {
+            return Ok(tokens);
+        }
+    }
+}
+
+fn word_len(chars: &[char]) -> usize {
+    chars
+        .iter()
+        .take_while(|c| c.is_alphanumeric() || **c == '_')
+        .count()
+}
+
+// Integer literals keep whether they were hex, suffixes like `u8` are dropped.
+fn number(literal: &str) -> Option<Tok> {
+    let literal = literal.replace('_', "");
+    let (radix, digits) = match literal.get(..2) {
+        Some("0x") => (16, &literal[2..]),
+        Some("0o") => (8, &literal[2..]),
+        Some("0b") => (2, &literal[2..]),
+        _ => (10, &literal[..]),
+    };
+    let suffix = ["f32", "f64"];
+    if radix == 10 && (digits.contains('.') || suffix.iter().any(|s| digits.ends_with(s))) {
+        let digits = suffix.iter().fold(digits, |d, s| d.trim_end_matches(s));
+        return digits.parse().ok().map(Tok::Float);
+    }
+    let end = digits.find(['i', 'u']).unwrap_or(digits.len());
+    let suffix = &digits[end..];
+    let valid_suffix =
+        suffix.is_empty() || ["8", "16", "32", "64", "128", "size"].contains(&&suffix[1..]);
+    if !valid_suffix {
+        return None;
+    }
+    let v = i128::from_str_radix(&digits[..end], radix).ok()?;
+    Some(Tok::Int(v, radix == 16))
+}
+
+// Names that have to be declared somewhere in the schema.
+enum Ref {
+    Parser(String, Vec<String>),
+    Enum(String),
+    Variant(String, String),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    refs: Vec<(Ref, usize)>,
+}
+
+impl Parser {
+    fn peek(&self) -> &Tok {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, n: usize) -> &Tok {
+        let i = (self.pos + n).min(self.tokens.len() - 1);
+        &self.tokens[i].tok
+    }
+
+    fn next(&mut self) -> Tok {
+        let tok = self.peek().clone();
+        if tok != Tok::Eof {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn error_at<T>(&self, pos: usize, message: String) -> Result<T, ParseError> {
+        let token = &self.tokens[pos.min(self.tokens.len() - 1)];
+        Err(ParseError {
+            line: token.line,
+            column: token.column,
+            message,
+        })
+    }
+
+    fn error<T>(&self, expected: &str) -> Result<T, ParseError> {
+        self.error_at(
+            self.pos,
+            format!("expected {}, found {}", expected, self.peek()),
+        )
+    }
+
+    fn is(&self, p: &str) -> bool {
+        matches!(self.peek(), Tok::Punct(q) if *q == p)
+    }
+
+    fn is_ident(&self, name: &str) -> bool {
+        matches!(self.peek(), Tok::Ident(i) if i == name)
+    }
+
+    fn eat(&mut self, p: &str) -> bool {
+        let found = self.is(p);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, p: &str) -> Result<(), ParseError> {
+        if self.eat(p) {
+            Ok(())
+        } else {
+            self.error(&format!("`{}`", p))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        match self.peek().clone() {
+            Tok::Ident(i) => {
+                self.pos += 1;
+                Ok(i)
+            }
+            _ => self.error("identifier"),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.is_ident(keyword) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            self.error(&format!("`{}`", keyword))
+        }
+    }
+
+    // Comma separated list up to `close`, with optional trailing comma.
+    fn list<T>(
+        &mut self,
+        close: &str,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = Vec::new();
+        while !self.eat(close) {
+            items.push(item(self)?);
+            if !self.eat(",") {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    fn schema(&mut self) -> Result<DynSchema, ParseError> {
+        let mut schema = DynSchema::new();
+        while *self.peek() != Tok::Eof {
+            let start = self.pos;
+            let deen = self.attributes()?;
+            self.visibility()?;
+            if self.is_ident("struct") {
+                let s = self.dyn_struct()?;
+                if schema.structs.iter().any(|o| o.parser == s.parser) {
+                    return self.error_at(start, format!("parser {} is declared twice", s.parser));
+                }
+                schema.structs.push(s);
+            } else if self.is_ident("enum") {
+                let e = self.dyn_enum(deen, start)?;
+                if schema.enums.iter().any(|o| o.name == e.name) {
+                    return self.error_at(start, format!("enum {} is declared twice", e.name));
+                }
+                schema.enums.push(e);
+            } else {
+                return self.error("`struct` or `enum`");
+            }
+        }
+        Ok(schema)
+    }
+
+    // Skips attributes and returns the integer of `#[deen(..)]`.
+    fn attributes(&mut self) -> Result<Option<IntType>, ParseError> {
+        let mut deen = None;
+        while self.eat("#") {
+            self.expect("[")?;
+            if self.is_ident("deen") && *self.peek_at(1) == Tok::Punct("(") {
+                self.pos += 2;
+                deen = Some(self.int_type()?);
+                self.expect(")")?;
+                self.expect("]")?;
+            } else {
+                self.skip_group("]")?;
+            }
+        }
+        Ok(deen)
+    }
+
+    fn skip_group(&mut self, close: &str) -> Result<(), ParseError> {
+        let mut depth = 0;
+        loop {
+            match self.next() {
+                Tok::Punct("(") | Tok::Punct("[") | Tok::Punct("{") => depth += 1,
+                Tok::Punct(p) if p == close && depth == 0 => return Ok(()),
+                Tok::Punct(")") | Tok::Punct("]") | Tok::Punct("}") => depth -= 1,
+                Tok::Eof => return self.error(&format!("`{}`", close)),
+                _ => {}
+            }
+        }
+    }
+
+    fn visibility(&mut self) -> Result<(), ParseError> {
+        if self.is_ident("pub") {
+            self.pos += 1;
+            if self.eat("(") {
+                self.skip_group(")")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn dyn_struct(&mut self) -> Result<DynStruct, ParseError> {
+        self.keyword("struct")?;
+        let parser = self.ident()?;
+        let params = if self.eat("(") {
+            self.list(")", |p| {
+                let name = p.ident()?;
+                p.expect(":")?;
+                Ok((name, p.type_text()?))
+            })?
+        } else {
+            Vec::new()
+        };
+        self.keyword("for")?;
+        let name = self.ident()?;
+        self.expect("{")?;
+        let items = self.list("}", Self::item)?;
+        Ok(DynStruct {
+            parser,
+            name,
+            params,
+            items,
+        })
+    }
+
+    // Rust type, kept as text.
+    fn type_text(&mut self) -> Result<String, ParseError> {
+        let mut text = String::new();
+        let mut depth = 0;
+        loop {
+            match self.peek() {
+                Tok::Punct("<") => depth += 1,
+                Tok::Punct(">") if depth > 0 => depth -= 1,
+                Tok::Punct(">>") if depth > 1 => depth -= 2,
+                Tok::Punct(",") | Tok::Punct(")") | Tok::Punct(">") | Tok::Punct(">>")
+                    if depth == 0 =>
+                {
+                    break
+                }
+                Tok::Ident(_) | Tok::Int(..) | Tok::Punct(_) => {}
+                _ => return self.error("type"),
+            }
+            match self.next() {
+                Tok::Ident(i) => text.push_str(&i),
+                Tok::Int(v, _) => text.push_str(&v.to_string()),
+                Tok::Punct(p) => text.push_str(p),
+                _ => unreachable!(),
+            }
+        }
+        if text.is_empty() {
+            return self.error("type");
+        }
+        Ok(text)
+    }
+
+    fn dyn_enum(&mut self, int: Option<IntType>, start: usize) -> Result<DynEnum, ParseError> {
+        self.keyword("enum")?;
+        let name = self.ident()?;
+        let int = match int {
+            Some(int) => int,
+            None => {
+                return self.error_at(
+                    start,
+                    format!("enum {} needs a `#[deen(..)]` attribute", name),
+                )
+            }
+        };
+        self.expect("{")?;
+        let variants = self.list("}", |p| {
+            let name = p.ident()?;
+            p.expect("=")?;
+            let negative = p.eat("-");
+            match p.next() {
+                Tok::Int(v, _) => Ok((name, if negative { -v } else { v })),
+                _ => {
+                    p.pos -= 1;
+                    p.error("integer")
+                }
+            }
+        })?;
+        Ok(DynEnum {
+            name,
+            int,
+            variants,
+        })
+    }
+
+    fn item(&mut self) -> Result<DynItem, ParseError> {
+        if self.is_ident("if") {
+            return self.dyn_if(None);
+        }
+        if let (Tok::Ident(name), Tok::Punct("~")) = (self.peek().clone(), self.peek_at(1)) {
+            self.pos += 2;
+            if self.is_ident("if") {
+                return self.dyn_if(Some(name));
+            }
+            let ty = self.dyn_type()?;
+            return Ok(DynItem::Field { name, ty });
+        }
+        self.value()
+    }
+
+    // Items stored without a name: `Tag::new(..)`, `Any::new(..)` and `Padding::new(..)`.
+    fn value(&mut self) -> Result<DynItem, ParseError> {
+        let start = self.pos;
+        let kind = self.ident()?;
+        if !self.eat("::") || !self.is_ident("new") {
+            return self.error_at(
+                start,
+                "expected `name ~ deener`, `Tag::new`, `Any::new`, `Padding::new` or `if`"
+                    .to_string(),
+            );
+        }
+        self.pos += 1;
+        self.expect("(")?;
+        let item = match kind.as_str() {
+            "Tag" => {
+                let ty = self.dyn_type()?;
+                self.expect(",")?;
+                let value = self.expr()?;
+                DynItem::Tag { ty, value }
+            }
+            "Any" => DynItem::Any(self.dyn_type()?),
+            "Padding" => DynItem::Padding(self.expr()?),
+            _ => return self.error_at(start, format!("unsupported value {}::new", kind)),
+        };
+        self.eat(",");
+        self.expect(")")?;
+        Ok(item)
+    }
+
+    fn dyn_if(&mut self, name: Option<String>) -> Result<DynItem, ParseError> {
+        let mut branches = Vec::new();
+        loop {
+            self.keyword("if")?;
+            let condition = Some(self.expr()?);
+            let items = self.block(name.as_deref())?;
+            branches.push(DynBranch { condition, items });
+            if !self.is_ident("else") {
+                break;
+            }
+            self.pos += 1;
+            if !self.is_ident("if") {
+                let items = self.block(name.as_deref())?;
+                branches.push(DynBranch {
+                    condition: None,
+                    items,
+                });
+                break;
+            }
+        }
+        Ok(DynItem::If { name, branches })
+    }
+
+    // Branch of an `if` chain: values separated by `;`, followed by the deener of the field
+    // if the chain has a name.
+    fn block(&mut self, name: Option<&str>) -> Result<Vec<DynItem>, ParseError> {
+        self.expect("{")?;
+        let mut items = Vec::new();
+        let mut field = false;
+        while !self.eat("}") {
+            if field {
+                return self.error("`}` after the deener of the field");
+            }
+            let is_value = matches!(self.peek(), Tok::Ident(i) if ["Tag", "Any", "Padding"].contains(&i.as_str()))
+                && *self.peek_at(1) == Tok::Punct("::");
+            match name {
+                Some(name) if !is_value => {
+                    field = true;
+                    items.push(DynItem::Field {
+                        name: name.to_string(),
+                        ty: self.dyn_type()?,
+                    });
+                }
+                _ => items.push(self.value()?),
+            }
+            if !self.eat(";") {
+                self.expect("}")?;
+                break;
+            }
+        }
+        if name.is_some() && !field {
+            return self.error_at(
+                self.pos - 1,
+                "branch has no deener for the field".to_string(),
+            );
+        }
+        Ok(items)
+    }
+
+    fn int_type(&mut self) -> Result<IntType, ParseError> {
+        match self.peek() {
+            Tok::Ident(name) => match IntType::from_name(name) {
+                Some(int) => {
+                    self.pos += 1;
+                    Ok(int)
+                }
+                None => self.error("integer deener like `U16be`"),
+            },
+            _ => self.error("integer deener like `U16be`"),
+        }
+    }
+
+    fn float(&mut self) -> Result<f64, ParseError> {
+        let negative = self.eat("-");
+        let v = match self.peek() {
+            Tok::Float(v) => *v,
+            Tok::Int(v, _) => *v as f64,
+            _ => return self.error("number"),
+        };
+        self.pos += 1;
+        Ok(if negative { -v } else { v })
+    }
+
+    fn call<T>(
+        &mut self,
+        args: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        self.expect("(")?;
+        let v = args(self)?;
+        self.eat(",");
+        self.expect(")")?;
+        Ok(v)
+    }
+
+    // `.method()` if it follows.
+    fn method(&mut self, name: &str) -> Result<bool, ParseError> {
+        let found = self.is(".") && *self.peek_at(1) == Tok::Ident(name.to_string());
+        if found {
+            self.pos += 2;
+            self.call(|_| Ok(()))?;
+        }
+        Ok(found)
+    }
+
+    fn dyn_type(&mut self) -> Result<DynType, ParseError> {
+        let start = self.pos;
+        let name = self.ident()?;
+        if let Some(int) = IntType::from_name(&name) {
+            return Ok(DynType::Int(int));
+        }
+        match name.as_str() {
+            "VarU64" => return Ok(DynType::VarU64),
+            "VarI64" => return Ok(DynType::VarI64),
+            _ => {}
+        }
+        if !self.eat("::") {
+            let args = if self.eat("{") {
+                self.list("}", |p| {
+                    let param = p.ident()?;
+                    let e = if p.eat(":") {
+                        p.expr()?
+                    } else {
+                        Expr::Var(param.clone())
+                    };
+                    Ok((param, e))
+                })?
+            } else {
+                Vec::new()
+            };
+            let params = args.iter().map(|(n, _)| n.clone()).collect();
+            self.refs.push((Ref::Parser(name.clone(), params), start));
+            return Ok(DynType::Parser { name, args });
+        }
+        if name == "Optional" {
+            return self.optional();
+        }
+        let f = self.ident()?;
+        Ok(match (name.as_str(), f.as_str()) {
+            ("Fixed", "new") => self.call(|p| {
+                let int = p.int_type()?;
+                p.expect(",")?;
+                match p.next() {
+                    Tok::Int(bits, _) if (0..=128).contains(&bits) => {
+                        Ok(DynType::Fixed(int, bits as u32))
+                    }
+                    _ => {
+                        p.pos -= 1;
+                        p.error("number of fractional bits")
+                    }
+                }
+            })?,
+            ("Scaled", "new") => {
+                let (int, scale) = self.call(|p| {
+                    let int = p.int_type()?;
+                    p.expect(",")?;
+                    Ok((int, p.float()?))
+                })?;
+                let offset = if self.is(".") && *self.peek_at(1) == Tok::Ident("with_offset".into())
+                {
+                    self.pos += 2;
+                    self.call(Self::float)?
+                } else {
+                    0.0
+                };
+                DynType::Scaled { int, scale, offset }
+            }
+            ("Bytes", "new") => DynType::Bytes(Some(self.call(Self::expr)?)),
+            ("Bytes", "remaining") => self.call(|_| Ok(DynType::Bytes(None)))?,
+            ("Utf8", "new") => DynType::Utf8(Some(self.call(Self::expr)?)),
+            ("Utf8", "remaining") => self.call(|_| Ok(DynType::Utf8(None)))?,
+            ("Repeat", "new") => self.call(|p| {
+                let count = p.expr()?;
+                p.expect(",")?;
+                Ok(DynType::Repeat(Some(count), Box::new(p.dyn_type()?)))
+            })?,
+            ("Repeat", "remaining") => {
+                self.call(|p| Ok(DynType::Repeat(None, Box::new(p.dyn_type()?))))?
+            }
+            ("Take", "new") => {
+                let (len, inner) = self.call(|p| {
+                    let len = p.expr()?;
+                    p.expect(",")?;
+                    Ok((len, Box::new(p.dyn_type()?)))
+                })?;
+                let skip_remaining = self.method("skip_remaining")?;
+                DynType::Take {
+                    len,
+                    inner,
+                    skip_remaining,
+                }
+            }
+            ("Take", "prefixed") => {
+                let (len, inner) = self.call(|p| {
+                    let len = p.int_type()?;
+                    p.expect(",")?;
+                    Ok((len, Box::new(p.dyn_type()?)))
+                })?;
+                let skip_remaining = self.method("skip_remaining")?;
+                DynType::Prefixed {
+                    len,
+                    inner,
+                    skip_remaining,
+                }
+            }
+            (_, "deen") => {
+                self.call(|_| Ok(()))?;
+                self.refs.push((Ref::Enum(name.clone()), start));
+                DynType::Enum(name)
+            }
+            _ => return self.error_at(start, format!("unsupported deener {}::{}", name, f)),
+        })
+    }
+
+    // `Optional::<T>::wrap(deener)` followed by how presence is decided.
+    fn optional(&mut self) -> Result<DynType, ParseError> {
+        let item = if self.eat("<") {
+            let item = self.type_text()?;
+            if self.is(">>") {
+                // `Optional::<Vec<u8>>` closes both lists with one token.
+                return self.error("`> ::` after the item type");
+            }
+            self.expect(">")?;
+            self.expect("::")?;
+            item
+        } else {
+            "_".to_string()
+        };
+        self.keyword("wrap")?;
+        let inner = Box::new(self.call(Self::dyn_type)?);
+        let presence = if self.method("if_remaining")? {
+            DynPresence::RemainingInput
+        } else if self.method("with_presence_byte")? {
+            DynPresence::Byte
+        } else if self.is(".") && *self.peek_at(1) == Tok::Ident("decode_when".into()) {
+            self.pos += 2;
+            DynPresence::When(self.call(|p| {
+                if p.is("|") {
+                    let message = "closures over the value are not supported, use `|| condition`";
+                    return p.error_at(p.pos, message.to_string());
+                }
+                p.expect("||")?;
+                p.expr()
+            })?)
+        } else {
+            return self.error("`.decode_when(..)`, `.if_remaining()` or `.with_presence_byte()`");
+        };
+        Ok(DynType::Optional {
+            item,
+            inner,
+            presence,
+        })
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, min_precedence: u8) -> Result<Expr, ParseError> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Tok::Punct(p) => match bin_op(p) {
+                    Some(op) if op.precedence() >= min_precedence => op,
+                    _ => return Ok(lhs),
+                },
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.binary(op.precedence() + 1)?;
+            lhs = Expr::binary(op, lhs, rhs);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        let e = if self.eat("-") {
+            Expr::Unary(UnOp::Neg, Box::new(self.unary()?))
+        } else if self.eat("!") {
+            Expr::Unary(UnOp::Not, Box::new(self.unary()?))
+        } else {
+            self.primary()?
+        };
+        // Casts do not change integer values, which are all i128 here.
+        while self.is_ident("as") {
+            self.pos += 1;
+            self.ident()?;
+        }
+        Ok(e)
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let start = self.pos;
+        match self.next() {
+            Tok::Int(v, true) => Ok(Expr::Hex(v)),
+            Tok::Int(v, false) => Ok(Expr::Int(v)),
+            Tok::Ident(b) if b == "true" || b == "false" => Ok(Expr::Int((b == "true") as i128)),
+            Tok::Ident(name) => {
+                if self.eat("::") {
+                    let variant = self.ident()?;
+                    self.refs
+                        .push((Ref::Variant(name.clone(), variant.clone()), start));
+                    Ok(Expr::Variant(name, variant))
+                } else {
+                    Ok(Expr::Var(name))
+                }
+            }
+            Tok::Punct("(") => {
+                let e = self.expr()?;
+                self.expect(")")?;
+                Ok(e)
+            }
+            _ => {
+                self.pos = start;
+                self.error("expression")
+            }
+        }
+    }
+
+    fn check_refs(&self, schema: &DynSchema) -> Result<(), ParseError> {
+        for (r, pos) in &self.refs {
+            match r {
+                Ref::Parser(name, args) => {
+                    let s = match schema.structs.iter().find(|s| s.parser == *name) {
+                        Some(s) => s,
+                        None => return self.error_at(*pos, format!("unknown deener {}", name)),
+                    };
+                    let mut params: Vec<_> = s.params.iter().map(|(n, _)| n).collect();
+                    let mut args: Vec<_> = args.iter().collect();
+                    params.sort();
+                    args.sort();
+                    if params != args {
+                        let params: Vec<_> = s.params.iter().map(|(n, _)| n.as_str()).collect();
+                        return self.error_at(
+                            *pos,
+                            format!("{} takes arguments {{ {} }}", name, params.join(", ")),
+                        );
+                    }
+                }
+                Ref::Enum(name) => {
+                    if schema.find_enum(name).is_none() {
+                        return self.error_at(*pos, format!("unknown enum {}", name));
+                    }
+                }
+                Ref::Variant(name, variant) => {
+                    let known = schema
+                        .find_enum(name)
+                        .is_some_and(|e| e.variants.iter().any(|(v, _)| v == variant));
+                    if !known {
+                        return self
+                            .error_at(*pos, format!("unknown enum variant {}::{}", name, variant));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn bin_op(p: &str) -> Option<BinOp> {
+    Some(match p {
+        "*" => BinOp::Mul,
+        "/" => BinOp::Div,
+        "%" => BinOp::Rem,
+        "+" => BinOp::Add,
+        "-" => BinOp::Sub,
+        "<<" => BinOp::Shl,
+        ">>" => BinOp::Shr,
+        "&" => BinOp::BitAnd,
+        "^" => BinOp::BitXor,
+        "|" => BinOp::BitOr,
+        "==" => BinOp::Eq,
+        "!=" => BinOp::Ne,
+        "<" => BinOp::Lt,
+        "<=" => BinOp::Le,
+        ">" => BinOp::Gt,
+        ">=" => BinOp::Ge,
+        "&&" => BinOp::And,
+        "||" => BinOp::Or,
+        _ => return None,
+    })
+}
@@ -0,0 +1,291 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    io,
+    iter::FromIterator,
+    marker::PhantomData,
+    ops::{
+        BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Sub, SubAssign,
+    },
+};
+
+use crate::{invalid_data_error, Deen, Schema};
+
+/// Integer types that can hold a set of flags.
+pub trait FlagBits:
+    Copy
+    + Eq
+    + Hash
+    + Default
+    + fmt::LowerHex
+    + BitOr<Output = Self>
+    + BitAnd<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+{
+}
+
+impl<T> FlagBits for T where
+    T: Copy
+        + Eq
+        + Hash
+        + Default
+        + fmt::LowerHex
+        + BitOr<Output = Self>
+        + BitAnd<Output = Self>
+        + BitXor<Output = Self>
+        + Not<Output = Self>
+{
+}
+
+/// Single flag of a bit set, usually implemented with `#[derive(Bitflags)]` on an enum.
+pub trait Flag: Copy + 'static {
+    type Bits: FlagBits;
+
+    /// Every flag with its name, in declaration order.
+    const FLAGS: &'static [(Self, &'static str)];
+
+    fn bits(self) -> Self::Bits;
+}
+
+/// Set of flags `F`. It may also hold bits that have no flag, see `Flags::lenient`.
+pub struct Flags<F: Flag> {
+    bits: F::Bits,
+}
+
+impl<F: Flag> Flags<F> {
+    pub fn empty() -> Flags<F> {
+        Self::from_bits_retain(F::Bits::default())
+    }
+
+    pub fn all() -> Flags<F> {
+        F::FLAGS.iter().map(|(f, _)| *f).collect()
+    }
+
+    /// Returns `None` if `bits` contains bits that do not belong to any flag.
+    pub fn from_bits(bits: F::Bits) -> Option<Flags<F>> {
+        let flags = Self::from_bits_retain(bits);
+        if flags.unknown_bits() == F::Bits::default() {
+            Some(flags)
+        } else {
+            None
+        }
+    }
+
+    pub fn from_bits_truncate(bits: F::Bits) -> Flags<F> {
+        Self::from_bits_retain(bits & Self::all().bits)
+    }
+
+    pub fn from_bits_retain(bits: F::Bits) -> Flags<F> {
+        Self { bits }
+    }
+
+    pub fn bits(&self) -> F::Bits {
+        self.bits
+    }
+
+    /// Bits that are set but do not belong to any flag.
+    pub fn unknown_bits(&self) -> F::Bits {
+        self.bits & !Self::all().bits
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == F::Bits::default()
+    }
+
+    pub fn contains(&self, other: impl Into<Flags<F>>) -> bool {
+        let other = other.into();
+        self.bits & other.bits == other.bits
+    }
+
+    pub fn intersects(&self, other: impl Into<Flags<F>>) -> bool {
+        self.bits & other.into().bits != F::Bits::default()
+    }
+
+    pub fn insert(&mut self, other: impl Into<Flags<F>>) {
+        self.bits = self.bits | other.into().bits;
+    }
+
+    pub fn remove(&mut self, other: impl Into<Flags<F>>) {
+        self.bits = self.bits & !other.into().bits;
+    }
+
+    pub fn toggle(&mut self, other: impl Into<Flags<F>>) {
+        self.bits = self.bits ^ other.into().bits;
+    }
+
+    /// Flags that are set, in declaration order.
+    pub fn iter(&self) -> impl Iterator<Item = F> + '_ {
+        self.named().map(|(f, _)| f)
+    }
+
+    fn named(&self) -> impl Iterator<Item = (F, &'static str)> + '_ {
+        F::FLAGS
+            .iter()
+            .filter(move |(f, _)| f.bits() != F::Bits::default() && self.contains(*f))
+            .copied()
+    }
+
+    /// Deener that rejects bits without a flag, both on decode and on encode.
+    pub fn strict<T: Deen<Item = F::Bits>>(deener: T) -> FlagsDeen<T, F> {
+        FlagsDeen {
+            deener,
+            strict: true,
+            p: PhantomData,
+        }
+    }
+
+    /// Deener that keeps bits without a flag, so they survive a decode/encode roundtrip.
+    pub fn lenient<T: Deen<Item = F::Bits>>(deener: T) -> FlagsDeen<T, F> {
+        FlagsDeen {
+            deener,
+            strict: false,
+            p: PhantomData,
+        }
+    }
+}
+
+impl<F: Flag> Clone for Flags<F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<F: Flag> Copy for Flags<F> {}
+
+impl<F: Flag> Default for Flags<F> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<F: Flag> PartialEq for Flags<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits == other.bits
+    }
+}
+
+impl<F: Flag> Eq for Flags<F> {}
+
+impl<F: Flag> Hash for Flags<F> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bits.hash(state)
+    }
+}
+
+impl<F: Flag> From<F> for Flags<F> {
+    fn from(flag: F) -> Self {
+        Self::from_bits_retain(flag.bits())
+    }
+}
+
+impl<F: Flag> FromIterator<F> for Flags<F> {
+    fn from_iter<I: IntoIterator<Item = F>>(iter: I) -> Self {
+        let mut flags = Self::from_bits_retain(F::Bits::default());
+        for f in iter {
+            flags.insert(f);
+        }
+        flags
+    }
+}
+
+impl<F: Flag> fmt::Debug for Flags<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Flags(")?;
+        let mut first = true;
+        for (_, name) in self.named() {
+            if !first {
+                f.write_str(" | ")?;
+            }
+            f.write_str(name)?;
+            first = false;
+        }
+        let unknown = self.unknown_bits();
+        if unknown != F::Bits::default() {
+            if !first {
+                f.write_str(" | ")?;
+            }
+            write!(f, "{:#x}", unknown)?;
+            first = false;
+        }
+        if first {
+            f.write_str("empty")?;
+        }
+        f.write_str(")")
+    }
+}
+
+macro_rules! flags_op {
+    ($op:ident, $fn:ident, $assign:ident, $assign_fn:ident, $expr:expr) => {
+        impl<F: Flag, R: Into<Flags<F>>> $op<R> for Flags<F> {
+            type Output = Flags<F>;
+
+            fn $fn(self, rhs: R) -> Flags<F> {
+                let f: fn(F::Bits, F::Bits) -> F::Bits = $expr;
+                Self::from_bits_retain(f(self.bits, rhs.into().bits))
+            }
+        }
+
+        impl<F: Flag, R: Into<Flags<F>>> $assign<R> for Flags<F> {
+            fn $assign_fn(&mut self, rhs: R) {
+                *self = $op::$fn(*self, rhs);
+            }
+        }
+    };
+}
+
+flags_op!(BitOr, bitor, BitOrAssign, bitor_assign, |a, b| a | b);
+flags_op!(BitAnd, bitand, BitAndAssign, bitand_assign, |a, b| a & b);
+flags_op!(BitXor, bitxor, BitXorAssign, bitxor_assign, |a, b| a ^ b);
+flags_op!(Sub, sub, SubAssign, sub_assign, |a, b| a & !b);
+
+impl<F: Flag> Not for Flags<F> {
+    type Output = Flags<F>;
+
+    /// Complement within the known flags.
+    fn not(self) -> Flags<F> {
+        Self::from_bits_truncate(!self.bits)
+    }
+}
+
+pub struct FlagsDeen<T, F> {
+    deener: T,
+    strict: bool,
+    p: PhantomData<F>,
+}
+
+impl<T, F> FlagsDeen<T, F>
+where
+    F: Flag,
+{
+    fn check(&self, flags: Flags<F>) -> io::Result<Flags<F>> {
+        let unknown = flags.unknown_bits();
+        if self.strict && unknown != F::Bits::default() {
+            Err(invalid_data_error(format!("unknown flags {:#x}", unknown)))
+        } else {
+            Ok(flags)
+        }
+    }
+}
+
+impl<T, F> Deen for FlagsDeen<T, F>
+where
+    T: Deen<Item = F::Bits>,
+    F: Flag,
+{
+    type Item = Flags<F>;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        let flags = self.check(*value)?;
+        self.deener.encode(&flags.bits(), buf)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        let bits = self.deener.decode(buf)?;
+        self.check(Flags::from_bits_retain(bits))
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::wrap("Flags", self.deener.schema())
+    }
+}
@@ -1,11 +1,59 @@
-use std::io;
+use std::{convert::TryFrom, io};
 
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
 
-use crate::Deen;
+use crate::{Deen, Endian, Schema};
+
+/// Integer items of the built-in deeners, used by adapters that do arithmetic on them.
+pub trait Integer: Copy {
+    fn to_f64(self) -> f64;
+    fn from_f64(value: f64) -> Option<Self>;
+    fn to_i128(self) -> Option<i128>;
+    fn from_i128(value: i128) -> Option<Self>;
+}
+
+macro_rules! integer {
+    ($($type:ty),*) => {
+        $(
+            impl Integer for $type {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+                fn from_f64(value: f64) -> Option<Self> {
+                    // `MAX as f64` rounds up to the next power of two for wide types, which
+                    // would then saturate, so compare against that power exactly.
+                    let signed = (<$type>::MIN != 0) as u32;
+                    let end = 2f64.powi((<$type>::BITS - signed) as i32);
+                    if value >= <$type>::MIN as f64 && value < end {
+                        Some(value as $type)
+                    } else {
+                        None
+                    }
+                }
+                fn to_i128(self) -> Option<i128> {
+                    i128::try_from(self).ok()
+                }
+                fn from_i128(value: i128) -> Option<Self> {
+                    <$type>::try_from(value).ok()
+                }
+            }
+        )*
+    };
+}
+
+integer!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+macro_rules! endian {
+    (BigEndian) => {
+        Endian::Big
+    };
+    (LittleEndian) => {
+        Endian::Little
+    };
+}
 
 macro_rules! deen_integer {
-    ($name:ident, $type:ty, $wr:ident, $rd:ident, $endian:ident) => {
+    ($name:ident, $type:ty, $bits:expr, $wr:ident, $rd:ident, $endian:ident) => {
         #[derive(Clone, Copy, Debug)]
         pub struct $name;
 
@@ -18,6 +66,9 @@ macro_rules! deen_integer {
             fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
                 buf.$rd::<$endian>()
             }
+            fn schema(&self) -> Schema {
+                Schema::int(<$type>::MIN != 0, $bits, endian!($endian))
+            }
         }
     };
 }
@@ -34,30 +85,50 @@ impl Deen for U8 {
     fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
         buf.read_u8()
     }
+    fn schema(&self) -> Schema {
+        Schema::int(false, 8, Endian::Big)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct I8;
+
+impl Deen for I8 {
+    type Item = i8;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        buf.write_i8(*value)
+    }
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        buf.read_i8()
+    }
+    fn schema(&self) -> Schema {
+        Schema::int(true, 8, Endian::Big)
+    }
 }
 
-deen_integer!(U16be, u16, write_u16, read_u16, BigEndian);
-deen_integer!(U16le, u16, write_u16, read_u16, LittleEndian);
-deen_integer!(U24be, u32, write_u24, read_u24, BigEndian);
-deen_integer!(U24le, u32, write_u24, read_u24, LittleEndian);
-deen_integer!(U32be, u32, write_u32, read_u32, BigEndian);
-deen_integer!(U32le, u32, write_u32, read_u32, LittleEndian);
-deen_integer!(U48be, u64, write_u48, read_u48, BigEndian);
-deen_integer!(U48le, u64, write_u48, read_u48, LittleEndian);
-deen_integer!(U64be, u64, write_u64, read_u64, BigEndian);
-deen_integer!(U64le, u64, write_u64, read_u64, LittleEndian);
-deen_integer!(U128be, u128, write_u128, read_u128, BigEndian);
-deen_integer!(U128le, u128, write_u128, read_u128, LittleEndian);
-
-deen_integer!(I16be, i16, write_i16, read_i16, BigEndian);
-deen_integer!(I16le, i16, write_i16, read_i16, LittleEndian);
-deen_integer!(I24be, i32, write_i24, read_i24, BigEndian);
-deen_integer!(I24le, i32, write_i24, read_i24, LittleEndian);
-deen_integer!(I32be, i32, write_i32, read_i32, BigEndian);
-deen_integer!(I32le, i32, write_i32, read_i32, LittleEndian);
-deen_integer!(I48be, i64, write_i48, read_i48, BigEndian);
-deen_integer!(I48le, i64, write_i48, read_i48, LittleEndian);
-deen_integer!(I64be, i64, write_i64, read_i64, BigEndian);
-deen_integer!(I64le, i64, write_i64, read_i64, LittleEndian);
-deen_integer!(I128be, i128, write_i128, read_i128, BigEndian);
-deen_integer!(I128le, i128, write_i128, read_i128, LittleEndian);
+deen_integer!(U16be, u16, 16, write_u16, read_u16, BigEndian);
+deen_integer!(U16le, u16, 16, write_u16, read_u16, LittleEndian);
+deen_integer!(U24be, u32, 24, write_u24, read_u24, BigEndian);
+deen_integer!(U24le, u32, 24, write_u24, read_u24, LittleEndian);
+deen_integer!(U32be, u32, 32, write_u32, read_u32, BigEndian);
+deen_integer!(U32le, u32, 32, write_u32, read_u32, LittleEndian);
+deen_integer!(U48be, u64, 48, write_u48, read_u48, BigEndian);
+deen_integer!(U48le, u64, 48, write_u48, read_u48, LittleEndian);
+deen_integer!(U64be, u64, 64, write_u64, read_u64, BigEndian);
+deen_integer!(U64le, u64, 64, write_u64, read_u64, LittleEndian);
+deen_integer!(U128be, u128, 128, write_u128, read_u128, BigEndian);
+deen_integer!(U128le, u128, 128, write_u128, read_u128, LittleEndian);
+
+deen_integer!(I16be, i16, 16, write_i16, read_i16, BigEndian);
+deen_integer!(I16le, i16, 16, write_i16, read_i16, LittleEndian);
+deen_integer!(I24be, i32, 24, write_i24, read_i24, BigEndian);
+deen_integer!(I24le, i32, 24, write_i24, read_i24, LittleEndian);
+deen_integer!(I32be, i32, 32, write_i32, read_i32, BigEndian);
+deen_integer!(I32le, i32, 32, write_i32, read_i32, LittleEndian);
+deen_integer!(I48be, i64, 48, write_i48, read_i48, BigEndian);
+deen_integer!(I48le, i64, 48, write_i48, read_i48, LittleEndian);
+deen_integer!(I64be, i64, 64, write_i64, read_i64, BigEndian);
+deen_integer!(I64le, i64, 64, write_i64, read_i64, LittleEndian);
+deen_integer!(I128be, i128, 128, write_i128, read_i128, BigEndian);
+deen_integer!(I128le, i128, 128, write_i128, read_i128, LittleEndian);
@@ -1,21 +1,132 @@
+mod bytes;
+mod dynamic;
+mod flags;
 mod integers;
+mod limits;
+mod map;
+mod net;
+mod numeric;
 mod optional;
+mod position;
+mod primitive;
+mod schema;
+#[cfg(feature = "serde")]
+mod serde;
+mod strict;
+mod take;
+mod time;
+mod trace;
+mod uuid;
+mod varint;
 
+pub use bytes::*;
+pub use dynamic::*;
+pub use flags::*;
 pub use integers::*;
+pub use limits::*;
+pub use map::*;
+pub use net::*;
+pub use numeric::*;
 pub use optional::*;
+pub use position::*;
+pub use primitive::*;
+pub use schema::*;
+#[cfg(feature = "serde")]
+pub use self::serde::*;
+pub use strict::*;
+pub use take::*;
+pub use time::*;
+pub use trace::*;
+pub use uuid::*;
+pub use varint::*;
 
-use std::{fmt, io};
+use std::{
+    convert::TryFrom,
+    fmt,
+    io::{self, Read},
+};
 
 pub trait Deen {
     type Item;
 
     fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()>;
     fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item>;
+
+    /// Describes the wire format. Deeners that do not override this are `Schema::Opaque`.
+    fn schema(&self) -> Schema {
+        Schema::Opaque(std::any::type_name::<Self>().to_string())
+    }
+
+    fn to_vec(&self, value: &Self::Item) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.encode(value, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Encodes into `buf` and returns the number of bytes written. Fails with
+    /// `io::ErrorKind::WriteZero` if `buf` is too short.
+    fn encode_into(&self, value: &Self::Item, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len();
+        let mut rest = buf;
+        self.encode(value, &mut rest)?;
+        Ok(len - rest.len())
+    }
+
+    /// Decodes from the start of `bytes` and returns the value with the number of bytes consumed.
+    #[allow(clippy::wrong_self_convention)]
+    fn from_slice(&self, bytes: &[u8]) -> io::Result<(Self::Item, usize)> {
+        let mut rest = bytes;
+        let item = self.decode(&mut rest)?;
+        Ok((item, bytes.len() - rest.len()))
+    }
+
+    /// Decodes the next value from a stream. Errors are wrapped in `PositionedError`, which
+    /// records where the value started and where decoding failed.
+    fn decode_from_reader<R: io::Read>(
+        &self,
+        reader: &mut Positioned<R>,
+    ) -> io::Result<Self::Item> {
+        let start = reader.position();
+        self.decode(&mut *reader).map_err(|source| {
+            PositionedError {
+                start,
+                position: reader.position(),
+                source,
+            }
+            .into()
+        })
+    }
+
+    /// Decodes `bytes` and fails if any of them are left over.
+    fn decode_exact(&self, bytes: &[u8]) -> io::Result<Self::Item> {
+        let mut rest = bytes;
+        let item = self.decode(&mut rest)?;
+        if rest.is_empty() {
+            Ok(item)
+        } else {
+            Err(invalid_data_error(format!(
+                "{} trailing bytes after decoded value",
+                rest.len()
+            )))
+        }
+    }
+}
+
+pub fn from_bytes<T: Deen>(deener: &T, bytes: &[u8]) -> io::Result<T::Item> {
+    deener.decode_exact(bytes)
+}
+
+pub fn to_vec<T: Deen>(deener: &T, value: &T::Item) -> io::Result<Vec<u8>> {
+    deener.to_vec(value)
 }
 
 pub trait Value {
     fn encode_value(&self, buf: impl io::Write) -> io::Result<()>;
     fn compare(&self, buf: impl io::Read) -> io::Result<()>;
+
+    fn schema(&self) -> Schema {
+        Schema::Opaque(std::any::type_name::<Self>().to_string())
+    }
 }
 
 pub struct Tag<T: Deen> {
@@ -54,6 +165,13 @@ where
             )))
         }
     }
+
+    fn schema(&self) -> Schema {
+        Schema::Tag {
+            inner: Box::new(self.deener.schema()),
+            value: format!("{:?}", self.value),
+        }
+    }
 }
 
 pub struct Any<T: Deen> {
@@ -79,10 +197,97 @@ where
     }
 
     fn compare(&self, buf: impl io::Read) -> io::Result<()> {
-        self.deener.decode(buf)?;
+        let other = self.deener.decode(buf)?;
+
+        if is_canonical()
+            && self.deener.to_vec(&other)? != self.deener.to_vec(&Default::default())?
+        {
+            return Err(invalid_data_error("non-canonical value of ignored field"));
+        }
+
+        Ok(())
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::Any(Box::new(self.deener.schema()))
+    }
+}
+
+/// Reserved bytes, written as zeroes and skipped on decode.
+pub struct Padding {
+    pub len: usize,
+}
+
+impl Padding {
+    pub fn new(len: usize) -> Padding {
+        Self { len }
+    }
+}
+
+impl Value for Padding {
+    fn encode_value(&self, mut buf: impl io::Write) -> io::Result<()> {
+        io::copy(&mut io::repeat(0).take(self.len as u64), &mut buf)?;
+
+        Ok(())
+    }
+
+    fn compare(&self, mut buf: impl io::Read) -> io::Result<()> {
+        let mut chunk = [0; 64];
+        let mut left = self.len;
+        while left > 0 {
+            let n = left.min(chunk.len());
+            buf.read_exact(&mut chunk[..n])?;
+            if is_canonical() && chunk[..n].iter().any(|&b| b != 0) {
+                return Err(invalid_data_error("non-zero padding"));
+            }
+            left -= n;
+        }
 
         Ok(())
     }
+
+    fn schema(&self) -> Schema {
+        Schema::Padding(self.len as u64)
+    }
+}
+
+// Lets `deen!` encode a field by reference when its type is the item of the deener and convert
+// it with `TryFrom` otherwise: `(&EncodeField(&d, &v)).encode_field(..)` resolves to
+// `EncodeSame` if it applies.
+#[doc(hidden)]
+pub struct EncodeField<'a, D, T>(pub &'a D, pub &'a T);
+
+#[doc(hidden)]
+pub trait EncodeSame {
+    fn encode_field(&self, name: &str, buf: impl io::Write) -> io::Result<()>;
+}
+
+impl<D, T> EncodeSame for EncodeField<'_, D, T>
+where
+    D: Deen<Item = T>,
+{
+    fn encode_field(&self, _name: &str, buf: impl io::Write) -> io::Result<()> {
+        self.0.encode(self.1, buf)
+    }
+}
+
+#[doc(hidden)]
+pub trait EncodeConverted {
+    fn encode_field(&self, name: &str, buf: impl io::Write) -> io::Result<()>;
+}
+
+impl<D, T> EncodeConverted for &EncodeField<'_, D, T>
+where
+    D: Deen,
+    T: Clone,
+    <D as Deen>::Item: TryFrom<T>,
+    <<D as Deen>::Item as TryFrom<T>>::Error: fmt::Display,
+{
+    fn encode_field(&self, name: &str, buf: impl io::Write) -> io::Result<()> {
+        let item = <D as Deen>::Item::try_from(self.1.clone())
+            .map_err(|e| invalid_data_error(format!("failed to convert {}: {}", name, e)))?;
+        self.0.encode(&item, buf)
+    }
 }
 
 fn invalid_data_error<D: fmt::Display>(d: D) -> io::Error {
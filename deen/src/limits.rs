@@ -0,0 +1,262 @@
+use std::{cell::RefCell, convert::TryFrom, error, fmt, io};
+
+use crate::Deen;
+
+/// Resource limits for decoding untrusted input, see `DecodeLimits::decode`.
+///
+/// Limits are kept per thread for the duration of the decode, so every deener reached from it
+/// respects them without having to pass them around. Deeners that allocate based on decoded
+/// lengths call `check_allocation` and `check_elements` first, `deen!` parsers call `enter`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecodeLimits {
+    max_total_bytes: Option<u64>,
+    max_allocation: Option<usize>,
+    max_elements: Option<usize>,
+    max_depth: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    TotalBytes,
+    Allocation,
+    Elements,
+    Depth,
+}
+
+/// Error carried by the `io::Error` returned when a limit is hit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    pub requested: u64,
+    pub max: u64,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.limit {
+            Limit::TotalBytes => "reading more than",
+            Limit::Allocation => "allocation of",
+            Limit::Elements => "sequence of",
+            Limit::Depth => "nesting depth of",
+        };
+        write!(
+            f,
+            "decode limit exceeded: {} {} exceeds maximum of {}",
+            what, self.requested, self.max
+        )
+    }
+}
+
+impl error::Error for LimitExceeded {}
+
+impl From<LimitExceeded> for io::Error {
+    fn from(e: LimitExceeded) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct State {
+    limits: DecodeLimits,
+    bytes_read: u64,
+    depth: usize,
+    // Nesting of `DecodeLimits::decode` calls, so that only the innermost reader counts bytes.
+    level: usize,
+}
+
+thread_local! {
+    static STATE: RefCell<Option<State>> = const { RefCell::new(None) };
+}
+
+fn stricter<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn check(limit: Limit, requested: u64, max: Option<u64>) -> io::Result<()> {
+    match max {
+        Some(max) if requested > max => Err(LimitExceeded {
+            limit,
+            requested,
+            max,
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+impl DecodeLimits {
+    /// No limits at all; set the ones you need with the builder methods.
+    pub fn new() -> DecodeLimits {
+        Self::default()
+    }
+
+    /// Maximum number of bytes read from the input.
+    pub fn max_total_bytes(self, max: u64) -> DecodeLimits {
+        Self {
+            max_total_bytes: Some(max),
+            ..self
+        }
+    }
+
+    /// Maximum size in bytes of a single buffer allocated for a decoded length.
+    pub fn max_allocation(self, max: usize) -> DecodeLimits {
+        Self {
+            max_allocation: Some(max),
+            ..self
+        }
+    }
+
+    /// Maximum number of elements in a single sequence.
+    pub fn max_elements(self, max: usize) -> DecodeLimits {
+        Self {
+            max_elements: Some(max),
+            ..self
+        }
+    }
+
+    /// Maximum nesting depth of `deen!` parsers.
+    pub fn max_depth(self, max: usize) -> DecodeLimits {
+        Self {
+            max_depth: Some(max),
+            ..self
+        }
+    }
+
+    /// Decodes with these limits active on the current thread. Within another decode, the
+    /// stricter of both limits apply and bytes read and depth count towards the enclosing ones.
+    pub fn decode<T: Deen>(&self, deener: &T, buf: impl io::Read) -> io::Result<T::Item> {
+        let outer = STATE.with(|s| *s.borrow());
+        let state = match outer {
+            Some(outer) => State {
+                limits: self.stricter(&outer.limits),
+                level: outer.level + 1,
+                ..outer
+            },
+            None => State {
+                limits: *self,
+                bytes_read: 0,
+                depth: 0,
+                level: 0,
+            },
+        };
+        let level = state.level;
+        let _restore = Restore(STATE.with(|s| s.borrow_mut().replace(state)));
+        deener.decode(LimitedReader { inner: buf, level })
+    }
+
+    fn stricter(&self, other: &DecodeLimits) -> DecodeLimits {
+        DecodeLimits {
+            max_total_bytes: stricter(self.max_total_bytes, other.max_total_bytes),
+            max_allocation: stricter(self.max_allocation, other.max_allocation),
+            max_elements: stricter(self.max_elements, other.max_elements),
+            max_depth: stricter(self.max_depth, other.max_depth),
+        }
+    }
+
+    pub(crate) fn limits_depth(&self) -> bool {
+        self.max_depth.is_some()
+    }
+
+    /// Limits of the decode running on the current thread, if any.
+    pub fn current() -> Option<DecodeLimits> {
+        STATE.with(|s| s.borrow().map(|s| s.limits))
+    }
+
+    pub fn check_allocation(len: usize) -> io::Result<()> {
+        let max = Self::current().and_then(|l| l.max_allocation);
+        check(Limit::Allocation, len as u64, max.map(|m| m as u64))
+    }
+
+    pub fn check_elements(count: usize) -> io::Result<()> {
+        let max = Self::current().and_then(|l| l.max_elements);
+        check(Limit::Elements, count as u64, max.map(|m| m as u64))
+    }
+
+    /// Enters one level of nesting until the returned guard is dropped.
+    pub fn enter() -> io::Result<DepthGuard> {
+        STATE.with(|s| match s.borrow_mut().as_mut() {
+            Some(state) => {
+                let max = state.limits.max_depth.map(|m| m as u64);
+                check(Limit::Depth, state.depth as u64 + 1, max)?;
+                state.depth += 1;
+                Ok(DepthGuard { active: true })
+            }
+            None => Ok(DepthGuard { active: false }),
+        })
+    }
+}
+
+// Puts back the state of an enclosing decode, even if the inner one panics, with the bytes
+// read by the inner one added.
+struct Restore(Option<State>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        STATE.with(|s| {
+            let mut s = s.borrow_mut();
+            let outer = self.0.take().map(|outer| State {
+                bytes_read: s.map_or(outer.bytes_read, |inner| inner.bytes_read),
+                ..outer
+            });
+            *s = outer;
+        });
+    }
+}
+
+pub struct DepthGuard {
+    active: bool,
+}
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        if self.active {
+            STATE.with(|s| {
+                if let Some(state) = s.borrow_mut().as_mut() {
+                    state.depth = state.depth.saturating_sub(1);
+                }
+            });
+        }
+    }
+}
+
+struct LimitedReader<R> {
+    inner: R,
+    level: usize,
+}
+
+impl<R: io::Read> io::Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let state = STATE.with(|s| *s.borrow());
+        let (bytes_read, max) = match state {
+            Some(state) if state.level == self.level => {
+                (state.bytes_read, state.limits.max_total_bytes)
+            }
+            _ => return self.inner.read(buf),
+        };
+        // Reads no further than the limit, but one byte past it to tell it from the end of
+        // the input.
+        let len = match max {
+            Some(max) => {
+                let left = max.saturating_sub(bytes_read).max(1);
+                usize::try_from(left).map_or(buf.len(), |left| left.min(buf.len()))
+            }
+            None => buf.len(),
+        };
+        let n = self.inner.read(&mut buf[..len])?;
+        STATE.with(|s| match s.borrow_mut().as_mut() {
+            Some(state) => {
+                state.bytes_read += n as u64;
+                check(
+                    Limit::TotalBytes,
+                    state.bytes_read,
+                    state.limits.max_total_bytes,
+                )
+            }
+            None => Ok(()),
+        })?;
+        Ok(n)
+    }
+}
@@ -0,0 +1,218 @@
+use std::{fmt, io, marker::PhantomData};
+
+use core::convert::TryFrom;
+
+use crate::{invalid_data_error, Deen, Integer, Schema};
+
+pub struct Map<T, D, E> {
+    deener: T,
+    decode_fn: D,
+    encode_fn: E,
+}
+
+impl<T, D, E, O> Map<T, D, E>
+where
+    T: Deen,
+    D: Fn(<T as Deen>::Item) -> O,
+    E: Fn(&O) -> <T as Deen>::Item,
+{
+    pub fn new(deener: T, decode_fn: D, encode_fn: E) -> Map<T, D, E> {
+        Self {
+            deener,
+            decode_fn,
+            encode_fn,
+        }
+    }
+}
+
+impl<T, D, E, O> Deen for Map<T, D, E>
+where
+    T: Deen,
+    D: Fn(<T as Deen>::Item) -> O,
+    E: Fn(&O) -> <T as Deen>::Item,
+{
+    type Item = O;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        self.deener.encode(&(self.encode_fn)(value), buf)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        self.deener.decode(buf).map(&self.decode_fn)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::wrap("Map", self.deener.schema())
+    }
+}
+
+pub struct TryMap<T, D, E> {
+    deener: T,
+    decode_fn: D,
+    encode_fn: E,
+}
+
+impl<T, D, E, O, DE, EE> TryMap<T, D, E>
+where
+    T: Deen,
+    D: Fn(<T as Deen>::Item) -> Result<O, DE>,
+    E: Fn(&O) -> Result<<T as Deen>::Item, EE>,
+    DE: fmt::Display,
+    EE: fmt::Display,
+{
+    pub fn new(deener: T, decode_fn: D, encode_fn: E) -> TryMap<T, D, E> {
+        Self {
+            deener,
+            decode_fn,
+            encode_fn,
+        }
+    }
+}
+
+impl<T, D, E, O, DE, EE> Deen for TryMap<T, D, E>
+where
+    T: Deen,
+    D: Fn(<T as Deen>::Item) -> Result<O, DE>,
+    E: Fn(&O) -> Result<<T as Deen>::Item, EE>,
+    DE: fmt::Display,
+    EE: fmt::Display,
+{
+    type Item = O;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        let t = (self.encode_fn)(value).map_err(invalid_data_error)?;
+        self.deener.encode(&t, buf)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        let t = self.deener.decode(buf)?;
+        (self.decode_fn)(t).map_err(invalid_data_error)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::wrap("TryMap", self.deener.schema())
+    }
+}
+
+/// Fixed-point value stored as `raw * scale + offset`.
+pub struct Scaled<T> {
+    deener: T,
+    scale: f64,
+    offset: f64,
+}
+
+impl<T> Scaled<T>
+where
+    T: Deen,
+    <T as Deen>::Item: Integer,
+{
+    pub fn new(deener: T, scale: f64) -> Scaled<T> {
+        Self {
+            deener,
+            scale,
+            offset: 0.0,
+        }
+    }
+
+    pub fn with_offset(self, offset: f64) -> Scaled<T> {
+        Self { offset, ..self }
+    }
+}
+
+impl<T> Deen for Scaled<T>
+where
+    T: Deen,
+    <T as Deen>::Item: Integer,
+{
+    type Item = f64;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        let raw = ((value - self.offset) / self.scale).round();
+        let t = <T as Deen>::Item::from_f64(raw).ok_or_else(|| {
+            invalid_data_error(format!("{} is out of range of the scaled field", value))
+        })?;
+        self.deener.encode(&t, buf)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        let t = self.deener.decode(buf)?;
+        Ok(t.to_f64() * self.scale + self.offset)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::wrap("Scaled", self.deener.schema())
+    }
+}
+
+/// Enum stored as the integer produced by `T`, usually derived with `TryFromPrimitive`.
+pub struct Enum<T, I> {
+    deener: T,
+    name: Option<&'static str>,
+    variants: &'static [(&'static str, i128)],
+    p: PhantomData<I>,
+}
+
+impl<T, I> Enum<T, I> {
+    pub fn new(deener: T) -> Enum<T, I> {
+        Self {
+            deener,
+            name: None,
+            variants: &[],
+            p: PhantomData,
+        }
+    }
+
+    /// Name of the enum and names and values of its variants, for the schema.
+    pub fn with_variants(
+        self,
+        name: &'static str,
+        variants: &'static [(&'static str, i128)],
+    ) -> Enum<T, I> {
+        Self {
+            name: Some(name),
+            variants,
+            ..self
+        }
+    }
+}
+
+// Name of `I` without module paths, also in its generic arguments.
+fn type_name<I>() -> String {
+    std::any::type_name::<I>()
+        .split_inclusive(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+        .map(|part| part.rsplit("::").next().unwrap_or(part))
+        .collect()
+}
+
+impl<T, I> Deen for Enum<T, I>
+where
+    T: Deen,
+    <T as Deen>::Item: TryFrom<I>,
+    <<T as Deen>::Item as TryFrom<I>>::Error: fmt::Display,
+    I: TryFrom<<T as Deen>::Item> + Clone,
+    <I as TryFrom<<T as Deen>::Item>>::Error: fmt::Display,
+{
+    type Item = I;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        let t = <T as Deen>::Item::try_from(value.clone()).map_err(invalid_data_error)?;
+        self.deener.encode(&t, buf)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        let t = self.deener.decode(buf)?;
+        I::try_from(t).map_err(invalid_data_error)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::Enum {
+            name: self.name.map_or_else(type_name::<I>, str::to_string),
+            inner: Box::new(self.deener.schema()),
+            variants: self
+                .variants
+                .iter()
+                .map(|(name, v)| (name.to_string(), *v))
+                .collect(),
+        }
+    }
+}
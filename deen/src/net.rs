@@ -0,0 +1,178 @@
+use std::{
+    fmt, io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddrV4},
+    str::FromStr,
+};
+
+use crate::{Deen, Endian, Schema, U128be, U16be, U32be};
+
+/// IEEE MAC-48 hardware address.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MacAddr(pub [u8; 6]);
+
+impl MacAddr {
+    pub fn octets(&self) -> [u8; 6] {
+        self.0
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        self.0 == [0xff; 6]
+    }
+
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+
+    /// Modified EUI-64 identifier as used in IPv6 interface identifiers.
+    pub fn to_eui64(&self) -> [u8; 8] {
+        let o = self.0;
+        [o[0] ^ 0x02, o[1], o[2], 0xff, 0xfe, o[3], o[4], o[5]]
+    }
+}
+
+impl From<[u8; 6]> for MacAddr {
+    fn from(octets: [u8; 6]) -> Self {
+        MacAddr(octets)
+    }
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let o = self.0;
+        write!(
+            f,
+            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            o[0], o[1], o[2], o[3], o[4], o[5]
+        )
+    }
+}
+
+impl fmt::Debug for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMacAddrError(String);
+
+impl fmt::Display for ParseMacAddrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid MAC address: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseMacAddrError {}
+
+impl FromStr for MacAddr {
+    type Err = ParseMacAddrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut octets = [0; 6];
+        let mut parts = s.split(&[':', '-'][..]);
+        for o in octets.iter_mut() {
+            *o = parts
+                .next()
+                .filter(|p| p.len() == 2)
+                .and_then(|p| u8::from_str_radix(p, 16).ok())
+                .ok_or_else(|| ParseMacAddrError(s.to_string()))?;
+        }
+        if parts.next().is_some() {
+            return Err(ParseMacAddrError(s.to_string()));
+        }
+        Ok(MacAddr(octets))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Ipv4;
+
+impl Deen for Ipv4 {
+    type Item = Ipv4Addr;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        U32be.encode(&u32::from(*value), buf)
+    }
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        U32be.decode(buf).map(Ipv4Addr::from)
+    }
+    fn schema(&self) -> Schema {
+        Schema::leaf("Ipv4", Some(4), Some(Endian::Big))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Ipv6;
+
+impl Deen for Ipv6 {
+    type Item = Ipv6Addr;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        U128be.encode(&u128::from(*value), buf)
+    }
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        U128be.decode(buf).map(Ipv6Addr::from)
+    }
+    fn schema(&self) -> Schema {
+        Schema::leaf("Ipv6", Some(16), Some(Endian::Big))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Mac48;
+
+impl Deen for Mac48 {
+    type Item = MacAddr;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        buf.write_all(&value.0)
+    }
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        let mut octets = [0; 6];
+        buf.read_exact(&mut octets)?;
+        Ok(MacAddr(octets))
+    }
+    fn schema(&self) -> Schema {
+        Schema::leaf("Mac48", Some(6), None)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Eui64;
+
+impl Deen for Eui64 {
+    type Item = [u8; 8];
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        buf.write_all(value)
+    }
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        let mut octets = [0; 8];
+        buf.read_exact(&mut octets)?;
+        Ok(octets)
+    }
+    fn schema(&self) -> Schema {
+        Schema::leaf("Eui64", Some(8), None)
+    }
+}
+
+/// IPv4 address followed by a port, both in network byte order.
+#[derive(Clone, Copy, Debug)]
+pub struct SocketAddrV4Be;
+
+impl Deen for SocketAddrV4Be {
+    type Item = SocketAddrV4;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        Ipv4.encode(value.ip(), &mut buf)?;
+        U16be.encode(&value.port(), &mut buf)
+    }
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        let ip = Ipv4.decode(&mut buf)?;
+        let port = U16be.decode(&mut buf)?;
+        Ok(SocketAddrV4::new(ip, port))
+    }
+    fn schema(&self) -> Schema {
+        Schema::leaf("SocketAddrV4Be", Some(6), Some(Endian::Big))
+    }
+}
@@ -0,0 +1,222 @@
+use std::io;
+
+use crate::{invalid_data_error, DecodeLimits, Deen, Integer, Schema};
+
+/// Q-format fixed point number with `frac_bits` fractional bits, e.g. `Fixed::new(I16be, 15)`
+/// for Q15 or `Fixed::new(I32be, 16)` for Q16.16.
+pub struct Fixed<T> {
+    deener: T,
+    frac_bits: u32,
+}
+
+impl<T> Fixed<T>
+where
+    T: Deen,
+    <T as Deen>::Item: Integer,
+{
+    pub fn new(deener: T, frac_bits: u32) -> Fixed<T> {
+        Self { deener, frac_bits }
+    }
+
+    fn one(&self) -> f64 {
+        2f64.powi(self.frac_bits as i32)
+    }
+}
+
+impl<T> Deen for Fixed<T>
+where
+    T: Deen,
+    <T as Deen>::Item: Integer,
+{
+    type Item = f64;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        let raw = (value * self.one()).round();
+        let t = <T as Deen>::Item::from_f64(raw).ok_or_else(|| {
+            invalid_data_error(format!(
+                "{} is out of range of the fixed point field with {} fractional bits",
+                value, self.frac_bits
+            ))
+        })?;
+        self.deener.encode(&t, buf)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        let t = self.deener.decode(buf)?;
+        Ok(t.to_f64() / self.one())
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::wrap("Fixed", self.deener.schema())
+    }
+}
+
+/// Packed BCD number of `len` bytes, two decimal digits per byte.
+#[derive(Clone, Copy, Debug)]
+pub struct Bcd {
+    len: usize,
+    swapped: bool,
+}
+
+impl Bcd {
+    pub fn new(len: usize) -> Bcd {
+        Self {
+            len,
+            swapped: false,
+        }
+    }
+
+    /// Low nibble holds the first digit of each byte, as in GSM semi-octets.
+    pub fn swapped(len: usize) -> Bcd {
+        Self { len, swapped: true }
+    }
+
+    fn max_value(&self) -> u64 {
+        10u64.checked_pow(2 * self.len as u32).map_or(u64::MAX, |m| m - 1)
+    }
+}
+
+impl Deen for Bcd {
+    type Item = u64;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        if *value > self.max_value() {
+            return Err(invalid_data_error(format!(
+                "{} does not fit into {} BCD bytes",
+                value, self.len
+            )));
+        }
+
+        let mut bytes = vec![0; self.len];
+        let mut v = *value;
+        for b in bytes.iter_mut().rev() {
+            let (lo, hi) = ((v % 10) as u8, (v / 10 % 10) as u8);
+            *b = if self.swapped { lo << 4 | hi } else { hi << 4 | lo };
+            v /= 100;
+        }
+        buf.write_all(&bytes)
+    }
+
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        DecodeLimits::check_allocation(self.len)?;
+        let mut bytes = vec![0; self.len];
+        buf.read_exact(&mut bytes)?;
+
+        let mut value = 0u64;
+        for b in bytes {
+            let (hi, lo) = if self.swapped {
+                (b & 0x0f, b >> 4)
+            } else {
+                (b >> 4, b & 0x0f)
+            };
+            if hi > 9 || lo > 9 {
+                return Err(invalid_data_error(format!("{:#04x} is not a valid BCD byte", b)));
+            }
+            value = value
+                .checked_mul(100)
+                .and_then(|v| v.checked_add(u64::from(hi * 10 + lo)))
+                .ok_or_else(|| invalid_data_error("BCD value overflows u64"))?;
+        }
+        Ok(value)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::leaf("Bcd", Some(self.len as u64), None)
+    }
+}
+
+/// Number written as ASCII digits in a field of `width` bytes, like the octal sizes in tar
+/// headers. Leading spaces and trailing spaces or NULs are ignored on decode, and a field of
+/// only spaces and NULs is 0.
+#[derive(Clone, Copy, Debug)]
+pub struct AsciiNum {
+    width: usize,
+    radix: u32,
+    nul_terminated: bool,
+}
+
+impl AsciiNum {
+    pub fn new(width: usize, radix: u32) -> AsciiNum {
+        assert!((2..=36).contains(&radix), "radix must be in 2..=36");
+        Self {
+            width,
+            radix,
+            nul_terminated: false,
+        }
+    }
+
+    pub fn decimal(width: usize) -> AsciiNum {
+        Self::new(width, 10)
+    }
+
+    pub fn octal(width: usize) -> AsciiNum {
+        Self::new(width, 8)
+    }
+
+    /// Reserve the last byte of the field for a NUL terminator on encode.
+    pub fn nul_terminated(self) -> AsciiNum {
+        Self {
+            nul_terminated: true,
+            ..self
+        }
+    }
+}
+
+impl Deen for AsciiNum {
+    type Item = u64;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        let digits = self
+            .width
+            .checked_sub(self.nul_terminated as usize)
+            .ok_or_else(|| {
+                invalid_data_error("no room for the NUL terminator in a field of width 0")
+            })?;
+        let mut bytes = vec![b'0'; digits];
+        let mut v = *value;
+        for b in bytes.iter_mut().rev() {
+            let d = (v % u64::from(self.radix)) as u32;
+            *b = std::char::from_digit(d, self.radix).unwrap() as u8;
+            v /= u64::from(self.radix);
+        }
+        if v != 0 {
+            return Err(invalid_data_error(format!(
+                "{} does not fit into {} digits of radix {}",
+                value, digits, self.radix
+            )));
+        }
+        if self.nul_terminated {
+            bytes.push(0);
+        }
+        buf.write_all(&bytes)
+    }
+
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        DecodeLimits::check_allocation(self.width)?;
+        let mut bytes = vec![0; self.width];
+        buf.read_exact(&mut bytes)?;
+
+        let text = std::str::from_utf8(&bytes)
+            .map_err(invalid_data_error)?
+            .trim_start_matches(' ')
+            .trim_end_matches(&[' ', '\0'][..]);
+        // Zero-filled fields, as tar writes for unused numbers, are 0.
+        if text.is_empty() {
+            return Ok(0);
+        }
+        // `from_str_radix` accepts a leading `+`, which is not a digit of the field.
+        if text.starts_with('+') {
+            return Err(invalid_data_error(format!(
+                "invalid numeric field {:?}: sign is not allowed",
+                text
+            )));
+        }
+        u64::from_str_radix(text, self.radix).map_err(|e| {
+            invalid_data_error(format!("invalid numeric field {:?}: {}", text, e))
+        })
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::leaf("AsciiNum", Some(self.width as u64), None)
+    }
+}
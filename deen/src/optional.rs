@@ -1,8 +1,12 @@
-use std::{fmt, io, marker::PhantomData};
+use std::{
+    fmt,
+    io::{self, Read},
+    marker::PhantomData,
+};
 
 use core::convert::TryFrom;
 
-use crate::{Deen, invalid_data_error};
+use crate::{Deen, PresenceRule, Schema, invalid_data_error};
 
 pub struct Optional<I> {
     p: PhantomData<I>,
@@ -23,22 +27,60 @@ pub struct OptionalWithEncoder<I, T> {
 }
 
 impl<I, T> OptionalWithEncoder<I, T> {
-    pub fn decode_when<F>(self, pred: F) -> OptionalImpl<I, T, F> {
+    pub fn decode_when<F>(self, pred: F) -> OptionalImpl<I, T, F, fn(&I) -> bool>
+    where
+        F: Fn() -> bool,
+    {
         OptionalImpl {
             encoder: self.encoder,
             pred,
+            check: |_| true,
+            p: PhantomData,
+        }
+    }
+
+    /// Present if any input remains, as for trailing extension fields. Encoding `None` writes
+    /// nothing, so the value must be the last one in the input.
+    pub fn if_remaining(self) -> OptionalTrailing<I, T> {
+        OptionalTrailing {
+            encoder: self.encoder,
+            p: PhantomData,
+        }
+    }
+
+    /// Preceded by a presence byte, `0` for `None` and `1` for `Some`, like `Option` in
+    /// bincode or Borsh.
+    pub fn with_presence_byte(self) -> OptionalTagged<I, T> {
+        OptionalTagged {
+            encoder: self.encoder,
             p: PhantomData,
         }
     }
 }
 
-pub struct OptionalImpl<I, T, F> {
+pub struct OptionalImpl<I, T, F, C> {
     encoder: T,
     pred: F,
+    check: C,
     p: PhantomData<I>,
 }
 
-impl<I, T, F> Deen for OptionalImpl<I, T, F>
+impl<I, T, F, C> OptionalImpl<I, T, F, C> {
+    /// Checks the value being encoded, which fails if `check` returns false.
+    pub fn check<G>(self, check: G) -> OptionalImpl<I, T, F, G>
+    where
+        G: Fn(&I) -> bool,
+    {
+        OptionalImpl {
+            encoder: self.encoder,
+            pred: self.pred,
+            check,
+            p: PhantomData,
+        }
+    }
+}
+
+impl<I, T, F, C> Deen for OptionalImpl<I, T, F, C>
 where
     T: Deen,
     <T as Deen>::Item: TryFrom<I> + Clone,
@@ -46,27 +88,157 @@ where
     I: TryFrom<<T as Deen>::Item> + Clone,
     <I as TryFrom<<T as Deen>::Item>>::Error: fmt::Display,
     F: Fn() -> bool,
+    C: Fn(&I) -> bool,
 {
     type Item = Option<I>;
 
+    /// Fails if the value does not agree with the predicate, since decoding would not read it
+    /// back the same way.
     fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
-        if let Some(v) = &value {
-            let t = <T as Deen>::Item::try_from(v.clone()).map_err(invalid_data_error)?;
-            self.encoder.encode(&t, buf)?;
+        match (value, (self.pred)()) {
+            (Some(v), true) if (self.check)(v) => encode_inner(&self.encoder, v, buf),
+            (Some(_), true) => Err(invalid_data_error("optional value fails its check")),
+            (None, false) => Ok(()),
+            (Some(_), false) => Err(invalid_data_error(
+                "optional value is set but its condition is false",
+            )),
+            (None, true) => Err(invalid_data_error(
+                "optional value is missing but its condition is true",
+            )),
         }
-
-        Ok(())
     }
 
     fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
         let r = if (self.pred)() {
-            let t = self.encoder.decode(buf)?;
-            let v = I::try_from(t.clone()).map_err(invalid_data_error)?;
-            Some(v)
+            Some(decode_inner(&self.encoder, buf)?)
         } else {
             None
         };
 
         Ok(r)
     }
+
+    fn schema(&self) -> Schema {
+        Schema::Optional {
+            inner: Box::new(self.encoder.schema()),
+            presence: PresenceRule::Condition,
+        }
+    }
+}
+
+pub struct OptionalTrailing<I, T> {
+    encoder: T,
+    p: PhantomData<I>,
+}
+
+impl<I, T> Deen for OptionalTrailing<I, T>
+where
+    T: Deen,
+    <T as Deen>::Item: TryFrom<I> + Clone,
+    <<T as Deen>::Item as TryFrom<I>>::Error: fmt::Display,
+    I: TryFrom<<T as Deen>::Item> + Clone,
+    <I as TryFrom<<T as Deen>::Item>>::Error: fmt::Display,
+{
+    type Item = Option<I>;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        match value {
+            Some(v) => encode_inner(&self.encoder, v, buf),
+            None => Ok(()),
+        }
+    }
+
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        let mut first = [0];
+        if read_byte(&mut buf, &mut first)? {
+            decode_inner(&self.encoder, (&first[..]).chain(buf)).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::Optional {
+            inner: Box::new(self.encoder.schema()),
+            presence: PresenceRule::RemainingInput,
+        }
+    }
+}
+
+pub struct OptionalTagged<I, T> {
+    encoder: T,
+    p: PhantomData<I>,
+}
+
+impl<I, T> Deen for OptionalTagged<I, T>
+where
+    T: Deen,
+    <T as Deen>::Item: TryFrom<I> + Clone,
+    <<T as Deen>::Item as TryFrom<I>>::Error: fmt::Display,
+    I: TryFrom<<T as Deen>::Item> + Clone,
+    <I as TryFrom<<T as Deen>::Item>>::Error: fmt::Display,
+{
+    type Item = Option<I>;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        match value {
+            Some(v) => {
+                buf.write_all(&[1])?;
+                encode_inner(&self.encoder, v, buf)
+            }
+            None => buf.write_all(&[0]),
+        }
+    }
+
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        let mut tag = [0];
+        buf.read_exact(&mut tag)?;
+        match tag[0] {
+            0 => Ok(None),
+            1 => decode_inner(&self.encoder, buf).map(Some),
+            b => Err(invalid_data_error(format!(
+                "{:#04x} is not a valid presence byte",
+                b
+            ))),
+        }
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::Optional {
+            inner: Box::new(self.encoder.schema()),
+            presence: PresenceRule::Byte,
+        }
+    }
+}
+
+fn encode_inner<I, T>(encoder: &T, value: &I, buf: impl io::Write) -> io::Result<()>
+where
+    T: Deen,
+    <T as Deen>::Item: TryFrom<I>,
+    <<T as Deen>::Item as TryFrom<I>>::Error: fmt::Display,
+    I: Clone,
+{
+    let t = <T as Deen>::Item::try_from(value.clone()).map_err(invalid_data_error)?;
+    encoder.encode(&t, buf)
+}
+
+fn decode_inner<I, T>(encoder: &T, buf: impl io::Read) -> io::Result<I>
+where
+    T: Deen,
+    I: TryFrom<<T as Deen>::Item>,
+    <I as TryFrom<<T as Deen>::Item>>::Error: fmt::Display,
+{
+    let t = encoder.decode(buf)?;
+    I::try_from(t).map_err(invalid_data_error)
+}
+
+// Returns false at the end of the input.
+pub(crate) fn read_byte(mut buf: impl io::Read, byte: &mut [u8; 1]) -> io::Result<bool> {
+    loop {
+        match buf.read(byte) {
+            Ok(n) => return Ok(n == 1),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
 }
@@ -0,0 +1,84 @@
+use std::{error, fmt, io};
+
+/// Reader or writer that counts the bytes going through it, see `Deen::decode_from_reader`.
+pub struct Positioned<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R> Positioned<R> {
+    pub fn new(inner: R) -> Positioned<R> {
+        Self::with_position(inner, 0)
+    }
+
+    /// Starts counting from `position`, e.g. after seeking the inner reader.
+    pub fn with_position(inner: R, position: u64) -> Positioned<R> {
+        Self { inner, position }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: io::Read> io::Read for Positioned<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<W: io::Write> io::Write for Positioned<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Error of a decode from a `Positioned` reader, with the offsets of the value and the failure.
+#[derive(Debug)]
+pub struct PositionedError {
+    pub start: u64,
+    pub position: u64,
+    pub source: io::Error,
+}
+
+impl fmt::Display for PositionedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "failed to decode value at offset {} (failed at offset {}): {}",
+            self.start, self.position, self.source
+        )
+    }
+}
+
+impl error::Error for PositionedError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<PositionedError> for io::Error {
+    fn from(e: PositionedError) -> io::Error {
+        io::Error::new(e.source.kind(), e)
+    }
+}
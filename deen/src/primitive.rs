@@ -0,0 +1,74 @@
+use std::{error, fmt, io, marker::PhantomData};
+
+/// Error of the `TryFrom` conversions generated by `#[derive(TryFromPrimitive)]`: `value` does
+/// not correspond to any variant of the enum `E`.
+pub struct TryFromPrimitiveError<E, P> {
+    value: P,
+    enum_name: &'static str,
+    p: PhantomData<fn() -> E>,
+}
+
+impl<E, P> TryFromPrimitiveError<E, P> {
+    pub fn new(value: P, enum_name: &'static str) -> TryFromPrimitiveError<E, P> {
+        Self {
+            value,
+            enum_name,
+            p: PhantomData,
+        }
+    }
+
+    pub fn value(&self) -> &P {
+        &self.value
+    }
+
+    pub fn into_value(self) -> P {
+        self.value
+    }
+
+    pub fn enum_name(&self) -> &'static str {
+        self.enum_name
+    }
+}
+
+impl<E, P: Clone> Clone for TryFromPrimitiveError<E, P> {
+    fn clone(&self) -> Self {
+        Self::new(self.value.clone(), self.enum_name)
+    }
+}
+
+impl<E, P: Copy> Copy for TryFromPrimitiveError<E, P> {}
+
+impl<E, P: PartialEq> PartialEq for TryFromPrimitiveError<E, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.enum_name == other.enum_name
+    }
+}
+
+impl<E, P: Eq> Eq for TryFromPrimitiveError<E, P> {}
+
+impl<E, P: fmt::Debug> fmt::Debug for TryFromPrimitiveError<E, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TryFromPrimitiveError")
+            .field("value", &self.value)
+            .field("enum_name", &self.enum_name)
+            .finish()
+    }
+}
+
+impl<E, P: fmt::Display> fmt::Display for TryFromPrimitiveError<E, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is not a valid {} value", self.value, self.enum_name)
+    }
+}
+
+impl<E, P: fmt::Debug + fmt::Display> error::Error for TryFromPrimitiveError<E, P> {}
+
+impl<E, P> From<TryFromPrimitiveError<E, P>> for io::Error
+where
+    E: 'static,
+    P: fmt::Debug + fmt::Display + Send + Sync + 'static,
+{
+    fn from(e: TryFromPrimitiveError<E, P>) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
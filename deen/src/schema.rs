@@ -0,0 +1,253 @@
+use std::fmt;
+
+/// Byte order of a multi-byte value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+/// How an optional value signals whether it is present.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresenceRule {
+    /// A predicate over previously decoded fields.
+    Condition,
+    /// Present if any input remains.
+    RemainingInput,
+    /// Preceded by a `0` or `1` byte.
+    Byte,
+}
+
+/// Description of a wire format, as returned by `Deen::schema`.
+///
+/// Schemas compare with `==`, so two versions of a protocol can be diffed without decoding
+/// anything.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schema {
+    /// Integer of `bits` bits. `endian` is `None` for single bytes.
+    Int {
+        signed: bool,
+        bits: u32,
+        endian: Option<Endian>,
+    },
+    /// Built-in value such as `Ipv4` or `VarU64`. `size` is `None` for variable width.
+    Leaf {
+        name: String,
+        size: Option<u64>,
+        endian: Option<Endian>,
+    },
+    /// Adapter that changes the decoded value but not how it is laid out, like `Map` or `Fixed`.
+    Wrap { name: String, inner: Box<Schema> },
+    /// Constant value, formatted with `Debug`.
+    Tag { inner: Box<Schema>, value: String },
+    /// Ignored value, written as its default.
+    Any(Box<Schema>),
+    /// Reserved bytes.
+    Padding(u64),
+    Optional {
+        inner: Box<Schema>,
+        presence: PresenceRule,
+    },
+    /// Value limited to a number of bytes, given by `prefix` or by the parser.
+    Take {
+        prefix: Option<Box<Schema>>,
+        inner: Box<Schema>,
+    },
+    /// Sequence of `count` values, or of values until the end of input.
+    List {
+        count: Option<u64>,
+        inner: Box<Schema>,
+    },
+    /// Fields of a `deen!` parser, in order. `params` are the names and types of the parser
+    /// fields.
+    Struct {
+        name: String,
+        parser: String,
+        params: Vec<(String, String)>,
+        fields: Vec<Field>,
+    },
+    /// Enum stored as an integer, with its variants if the enum lists them.
+    Enum {
+        name: String,
+        inner: Box<Schema>,
+        variants: Vec<(String, i128)>,
+    },
+    /// `if` chain of a `deen!` parser.
+    Conditional(Vec<Branch>),
+    /// Deener that does not describe itself, by type name.
+    Opaque(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    /// `None` for values that are not stored, like tags and padding.
+    pub name: Option<String>,
+    /// Source text of the deener expression. Lengths and conditions that depend on other
+    /// fields are only known from here, see `DynSchema::from_schema`.
+    pub source: String,
+    pub schema: Schema,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Branch {
+    /// Source text of the condition, `None` for the final `else`.
+    pub condition: Option<String>,
+    pub fields: Vec<Field>,
+}
+
+impl Schema {
+    pub fn int(signed: bool, bits: u32, endian: Endian) -> Schema {
+        Schema::Int {
+            signed,
+            bits,
+            endian: if bits > 8 { Some(endian) } else { None },
+        }
+    }
+
+    pub fn leaf(name: &str, size: Option<u64>, endian: Option<Endian>) -> Schema {
+        Schema::Leaf {
+            name: name.to_string(),
+            size,
+            endian,
+        }
+    }
+
+    pub fn wrap(name: &str, inner: Schema) -> Schema {
+        Schema::Wrap {
+            name: name.to_string(),
+            inner: Box::new(inner),
+        }
+    }
+
+    /// Encoded size in bytes, if it is the same for every value.
+    pub fn size(&self) -> Option<u64> {
+        match self {
+            Schema::Int { bits, .. } => Some(u64::from(*bits / 8)),
+            Schema::Leaf { size, .. } => *size,
+            Schema::Wrap { inner, .. }
+            | Schema::Tag { inner, .. }
+            | Schema::Any(inner)
+            | Schema::Enum { inner, .. } => inner.size(),
+            Schema::Padding(len) => Some(*len),
+            Schema::Optional { .. } | Schema::Take { .. } | Schema::Opaque(_) => None,
+            Schema::List { count, inner } => count.as_ref()?.checked_mul(inner.size()?),
+            Schema::Struct { fields, .. } => fields_size(fields),
+            Schema::Conditional(branches) => {
+                let mut sizes = branches.iter().map(|b| fields_size(&b.fields));
+                let first = sizes.next()?;
+                if sizes.all(|s| s == first) {
+                    first
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        match self {
+            Schema::Int {
+                signed,
+                bits,
+                endian,
+            } => {
+                write!(f, "{}{}", if *signed { 'i' } else { 'u' }, bits)?;
+                fmt_endian(f, *endian)
+            }
+            Schema::Leaf { name, .. } => f.write_str(name),
+            Schema::Wrap { name, inner } | Schema::Enum { name, inner, .. } => {
+                write!(f, "{}(", name)?;
+                inner.fmt_indented(f, indent)?;
+                f.write_str(")")
+            }
+            Schema::Tag { inner, value } => {
+                f.write_str("tag ")?;
+                inner.fmt_indented(f, indent)?;
+                write!(f, " = {}", value)
+            }
+            Schema::Any(inner) => {
+                f.write_str("any ")?;
+                inner.fmt_indented(f, indent)
+            }
+            Schema::Padding(len) => write!(f, "padding {}", len),
+            Schema::Optional { inner, presence } => {
+                let presence = match presence {
+                    PresenceRule::Condition => "condition",
+                    PresenceRule::RemainingInput => "if remaining",
+                    PresenceRule::Byte => "presence byte",
+                };
+                f.write_str("optional ")?;
+                inner.fmt_indented(f, indent)?;
+                write!(f, " ({})", presence)
+            }
+            Schema::Take { prefix, inner } => {
+                f.write_str("take")?;
+                if let Some(prefix) = prefix {
+                    f.write_str(" prefixed ")?;
+                    prefix.fmt_indented(f, indent)?;
+                }
+                f.write_str(" ")?;
+                inner.fmt_indented(f, indent)
+            }
+            Schema::List { count, inner } => {
+                f.write_str("[")?;
+                inner.fmt_indented(f, indent)?;
+                match count {
+                    Some(count) => write!(f, "; {}]", count),
+                    None => f.write_str("]"),
+                }
+            }
+            Schema::Struct { name, fields, .. } => {
+                write!(f, "{} ", name)?;
+                fmt_fields(f, fields, indent)
+            }
+            Schema::Conditional(branches) => {
+                for (i, branch) in branches.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" else ")?;
+                    }
+                    if let Some(condition) = &branch.condition {
+                        write!(f, "if {} ", condition)?;
+                    }
+                    fmt_fields(f, &branch.fields, indent)?;
+                }
+                Ok(())
+            }
+            Schema::Opaque(name) => write!(f, "<{}>", name),
+        }
+    }
+}
+
+fn fields_size(fields: &[Field]) -> Option<u64> {
+    fields
+        .iter()
+        .try_fold(0u64, |size, f| size.checked_add(f.schema.size()?))
+}
+
+fn fmt_endian(f: &mut fmt::Formatter, endian: Option<Endian>) -> fmt::Result {
+    match endian {
+        Some(Endian::Big) => f.write_str("be"),
+        Some(Endian::Little) => f.write_str("le"),
+        None => Ok(()),
+    }
+}
+
+fn fmt_fields(f: &mut fmt::Formatter, fields: &[Field], indent: usize) -> fmt::Result {
+    f.write_str("{\n")?;
+    for field in fields {
+        write!(f, "{:1$}", "", (indent + 1) * 4)?;
+        if let Some(name) = &field.name {
+            write!(f, "{}: ", name)?;
+        }
+        field.schema.fmt_indented(f, indent + 1)?;
+        f.write_str("\n")?;
+    }
+    write!(f, "{:1$}}}", "", indent * 4)
+}
+
+/// Indented outline of the schema, one field per line.
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
@@ -0,0 +1,821 @@
+use std::{any, convert::TryFrom, error, fmt, io, marker::PhantomData};
+
+use serde::{
+    de::{self, DeserializeOwned, IntoDeserializer, Visitor},
+    ser::{self, Serialize},
+};
+
+use crate::{
+    invalid_data_error, Bytes, DecodeLimits, Deen, Endian, I128be, I128le, I16be, I16le, I32be,
+    I32le, I64be, I64le, Integer, Schema, U128be, U128le, U16be, U16le, U32be, U32le, U64be, U64le,
+    Utf8, VarI64, VarU64, I8, U8,
+};
+
+/// Deener for any serde type, in a binary layout without field names: structs and tuples are
+/// their fields in order, strings, bytes, sequences and maps are prefixed by their length,
+/// options by a presence byte and enum variants by their index as a `u32`.
+///
+/// Integers are fixed-width big-endian and lengths `VarU64` unless configured otherwise.
+/// Types that need a self-describing format, like `serde_json::Value`, can not be decoded.
+pub struct Serde<T, S = VarU64, Q = VarU64> {
+    endian: Endian,
+    varint: bool,
+    str_len: S,
+    seq_len: Q,
+    p: PhantomData<fn() -> T>,
+}
+
+impl<T> Serde<T> {
+    pub fn new() -> Serde<T> {
+        Self {
+            endian: Endian::Big,
+            varint: false,
+            str_len: VarU64,
+            seq_len: VarU64,
+            p: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for Serde<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, S, Q> Serde<T, S, Q> {
+    /// Byte order of fixed-width integers, floats and chars.
+    pub fn endian(self, endian: Endian) -> Serde<T, S, Q> {
+        Self { endian, ..self }
+    }
+
+    /// Integers from 16 to 64 bits, chars and variant indices as `VarU64` or `VarI64`.
+    pub fn varint(self) -> Serde<T, S, Q> {
+        Self {
+            varint: true,
+            ..self
+        }
+    }
+
+    /// Length prefix of strings and bytes.
+    pub fn str_len<L>(self, len_deener: L) -> Serde<T, L, Q>
+    where
+        L: Deen,
+        <L as Deen>::Item: Integer,
+    {
+        Serde {
+            endian: self.endian,
+            varint: self.varint,
+            str_len: len_deener,
+            seq_len: self.seq_len,
+            p: PhantomData,
+        }
+    }
+
+    /// Length prefix of sequences and maps.
+    pub fn seq_len<L>(self, len_deener: L) -> Serde<T, S, L>
+    where
+        L: Deen,
+        <L as Deen>::Item: Integer,
+    {
+        Serde {
+            endian: self.endian,
+            varint: self.varint,
+            str_len: self.str_len,
+            seq_len: len_deener,
+            p: PhantomData,
+        }
+    }
+}
+
+impl<T, S, Q> Deen for Serde<T, S, Q>
+where
+    T: Serialize + DeserializeOwned,
+    S: Deen,
+    <S as Deen>::Item: Integer,
+    Q: Deen,
+    <Q as Deen>::Item: Integer,
+{
+    type Item = T;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        let mut serializer = Serializer { config: self, buf };
+        value.serialize(&mut serializer).map_err(|e| e.0)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        let mut deserializer = Deserializer { config: self, buf };
+        T::deserialize(&mut deserializer).map_err(|e| e.0)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::Opaque(any::type_name::<T>().to_string())
+    }
+}
+
+// `io::Error` with the constructors serde needs.
+#[derive(Debug)]
+struct Error(io::Error);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error(e)
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<M: fmt::Display>(msg: M) -> Error {
+        Error(invalid_data_error(msg))
+    }
+}
+
+impl de::Error for Error {
+    fn custom<M: fmt::Display>(msg: M) -> Error {
+        Error(invalid_data_error(msg))
+    }
+}
+
+fn write_len<L>(len_deener: &L, len: usize, buf: impl io::Write) -> Result<(), Error>
+where
+    L: Deen,
+    <L as Deen>::Item: Integer,
+{
+    let prefix = <L as Deen>::Item::from_i128(len as i128)
+        .ok_or_else(|| invalid_data_error(format!("length {} does not fit the prefix", len)))?;
+    Ok(len_deener.encode(&prefix, buf)?)
+}
+
+fn read_len<L>(len_deener: &L, buf: impl io::Read) -> Result<usize, Error>
+where
+    L: Deen,
+    <L as Deen>::Item: Integer,
+{
+    let len = len_deener.decode(buf)?;
+    let len = len
+        .to_i128()
+        .and_then(|l| usize::try_from(l).ok())
+        .ok_or_else(|| invalid_data_error("invalid length prefix"))?;
+    Ok(len)
+}
+
+struct Serializer<'a, T, S, Q, W> {
+    config: &'a Serde<T, S, Q>,
+    buf: W,
+}
+
+// Fixed-width deeners for both byte orders and the varint used instead of them.
+macro_rules! serialize_int {
+    ($method:ident, $type:ty, $be:ident, $le:ident, $var:ident as $var_type:ty) => {
+        fn $method(self, v: $type) -> Result<(), Error> {
+            match (self.config.varint, self.config.endian) {
+                (true, _) => $var.encode(&(v as $var_type), &mut self.buf)?,
+                (false, Endian::Big) => $be.encode(&v, &mut self.buf)?,
+                (false, Endian::Little) => $le.encode(&v, &mut self.buf)?,
+            }
+            Ok(())
+        }
+    };
+    ($method:ident, $type:ty, $be:ident, $le:ident) => {
+        fn $method(self, v: $type) -> Result<(), Error> {
+            match self.config.endian {
+                Endian::Big => $be.encode(&v, &mut self.buf)?,
+                Endian::Little => $le.encode(&v, &mut self.buf)?,
+            }
+            Ok(())
+        }
+    };
+}
+
+impl<'a, 'b, T, S, Q, W> ser::Serializer for &'b mut Serializer<'a, T, S, Q, W>
+where
+    S: Deen,
+    <S as Deen>::Item: Integer,
+    Q: Deen,
+    <Q as Deen>::Item: Integer,
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    serialize_int!(serialize_i16, i16, I16be, I16le, VarI64 as i64);
+    serialize_int!(serialize_i32, i32, I32be, I32le, VarI64 as i64);
+    serialize_int!(serialize_i64, i64, I64be, I64le, VarI64 as i64);
+    serialize_int!(serialize_i128, i128, I128be, I128le);
+    serialize_int!(serialize_u16, u16, U16be, U16le, VarU64 as u64);
+    serialize_int!(serialize_u32, u32, U32be, U32le, VarU64 as u64);
+    serialize_int!(serialize_u64, u64, U64be, U64le, VarU64 as u64);
+    serialize_int!(serialize_u128, u128, U128be, U128le);
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.serialize_u8(v as u8)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        Ok(I8.encode(&v, &mut self.buf)?)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        Ok(U8.encode(&v, &mut self.buf)?)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        match self.config.endian {
+            Endian::Big => U32be.encode(&v.to_bits(), &mut self.buf)?,
+            Endian::Little => U32le.encode(&v.to_bits(), &mut self.buf)?,
+        }
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        match self.config.endian {
+            Endian::Big => U64be.encode(&v.to_bits(), &mut self.buf)?,
+            Endian::Little => U64le.encode(&v.to_bits(), &mut self.buf)?,
+        }
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        write_len(&self.config.str_len, v.len(), &mut self.buf)?;
+        Ok(self.buf.write_all(v)?)
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.serialize_u8(0)
+    }
+
+    fn serialize_some<V: ?Sized + Serialize>(self, value: &V) -> Result<(), Error> {
+        self.serialize_u8(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_u32(index)
+    }
+
+    fn serialize_newtype_struct<V: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &V,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<V: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        value: &V,
+    ) -> Result<(), Error> {
+        self.serialize_u32(index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, Error> {
+        let len = len.ok_or_else(|| invalid_data_error("sequence length must be known"))?;
+        write_len(&self.config.seq_len, len, &mut self.buf)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, Error> {
+        self.serialize_u32(index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, Error> {
+        let len = len.ok_or_else(|| invalid_data_error("map length must be known"))?;
+        write_len(&self.config.seq_len, len, &mut self.buf)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, Error> {
+        self.serialize_u32(index)?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+// Compound values are their elements in order, the length prefix is already written.
+macro_rules! serialize_elements {
+    ($($trait:ident::$method:ident),*) => {
+        $(
+            impl<'a, 'b, T, S, Q, W> ser::$trait for &'b mut Serializer<'a, T, S, Q, W>
+            where
+                S: Deen,
+                <S as Deen>::Item: Integer,
+                Q: Deen,
+                <Q as Deen>::Item: Integer,
+                W: io::Write,
+            {
+                type Ok = ();
+                type Error = Error;
+
+                fn $method<V: ?Sized + Serialize>(&mut self, value: &V) -> Result<(), Error> {
+                    value.serialize(&mut **self)
+                }
+
+                fn end(self) -> Result<(), Error> {
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+serialize_elements!(
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field
+);
+
+impl<'a, 'b, T, S, Q, W> ser::SerializeMap for &'b mut Serializer<'a, T, S, Q, W>
+where
+    S: Deen,
+    <S as Deen>::Item: Integer,
+    Q: Deen,
+    <Q as Deen>::Item: Integer,
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<K: ?Sized + Serialize>(&mut self, key: &K) -> Result<(), Error> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<V: ?Sized + Serialize>(&mut self, value: &V) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+macro_rules! serialize_fields {
+    ($($trait:ident),*) => {
+        $(
+            impl<'a, 'b, T, S, Q, W> ser::$trait for &'b mut Serializer<'a, T, S, Q, W>
+            where
+                S: Deen,
+                <S as Deen>::Item: Integer,
+                Q: Deen,
+                <Q as Deen>::Item: Integer,
+                W: io::Write,
+            {
+                type Ok = ();
+                type Error = Error;
+
+                fn serialize_field<V: ?Sized + Serialize>(
+                    &mut self,
+                    _key: &'static str,
+                    value: &V,
+                ) -> Result<(), Error> {
+                    value.serialize(&mut **self)
+                }
+
+                fn end(self) -> Result<(), Error> {
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+serialize_fields!(SerializeStruct, SerializeStructVariant);
+
+struct Deserializer<'a, T, S, Q, R> {
+    config: &'a Serde<T, S, Q>,
+    buf: R,
+}
+
+macro_rules! deserialize_int {
+    ($method:ident, $visit:ident, $type:ty, $be:ident, $le:ident, $var:ident) => {
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            let v = match (self.config.varint, self.config.endian) {
+                (true, _) => {
+                    let v = $var.decode(&mut self.buf)?;
+                    <$type>::try_from(v).map_err(|_| {
+                        invalid_data_error(format!(
+                            "{} is out of range of {}",
+                            v,
+                            stringify!($type)
+                        ))
+                    })?
+                }
+                (false, Endian::Big) => $be.decode(&mut self.buf)?,
+                (false, Endian::Little) => $le.decode(&mut self.buf)?,
+            };
+            visitor.$visit(v)
+        }
+    };
+    ($method:ident, $visit:ident, $be:ident, $le:ident) => {
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            let v = match self.config.endian {
+                Endian::Big => $be.decode(&mut self.buf)?,
+                Endian::Little => $le.decode(&mut self.buf)?,
+            };
+            visitor.$visit(v)
+        }
+    };
+}
+
+impl<'a, T, S, Q, R> Deserializer<'a, T, S, Q, R>
+where
+    S: Deen,
+    <S as Deen>::Item: Integer,
+    Q: Deen,
+    <Q as Deen>::Item: Integer,
+    R: io::Read,
+{
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(U8.decode(&mut self.buf)?)
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        de::Deserializer::deserialize_u32(self, U32Visitor)
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, Error> {
+        let len = read_len(&self.config.str_len, &mut self.buf)?;
+        Ok(Bytes::new(len as u64).decode(&mut self.buf)?)
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let len = read_len(&self.config.str_len, &mut self.buf)?;
+        Ok(Utf8::new(len as u64).decode(&mut self.buf)?)
+    }
+
+    // Sequences and maps keep the element count within the decode limits.
+    fn seq_len(&mut self) -> Result<usize, Error> {
+        let len = read_len(&self.config.seq_len, &mut self.buf)?;
+        DecodeLimits::check_elements(len)?;
+        Ok(len)
+    }
+}
+
+struct U32Visitor;
+
+impl<'de> Visitor<'de> for U32Visitor {
+    type Value = u32;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("u32")
+    }
+
+    fn visit_u32<E: de::Error>(self, v: u32) -> Result<u32, E> {
+        Ok(v)
+    }
+}
+
+fn not_self_describing() -> Error {
+    Error(invalid_data_error(
+        "binary serde layout can not be decoded without knowing the type",
+    ))
+}
+
+impl<'de, 'a, 'b, T, S, Q, R> de::Deserializer<'de> for &'b mut Deserializer<'a, T, S, Q, R>
+where
+    S: Deen,
+    <S as Deen>::Item: Integer,
+    Q: Deen,
+    <Q as Deen>::Item: Integer,
+    R: io::Read,
+{
+    type Error = Error;
+
+    deserialize_int!(deserialize_i16, visit_i16, i16, I16be, I16le, VarI64);
+    deserialize_int!(deserialize_i32, visit_i32, i32, I32be, I32le, VarI64);
+    deserialize_int!(deserialize_i64, visit_i64, i64, I64be, I64le, VarI64);
+    deserialize_int!(deserialize_i128, visit_i128, I128be, I128le);
+    deserialize_int!(deserialize_u16, visit_u16, u16, U16be, U16le, VarU64);
+    deserialize_int!(deserialize_u32, visit_u32, u32, U32be, U32le, VarU64);
+    deserialize_int!(deserialize_u64, visit_u64, u64, U64be, U64le, VarU64);
+    deserialize_int!(deserialize_u128, visit_u128, U128be, U128le);
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(not_self_describing())
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.u8()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            b => Err(Error(invalid_data_error(format!(
+                "{:#04x} is not a valid bool",
+                b
+            )))),
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_i8(I8.decode(&mut self.buf)?)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_u8(self.u8()?)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let bits = match self.config.endian {
+            Endian::Big => U32be.decode(&mut self.buf)?,
+            Endian::Little => U32le.decode(&mut self.buf)?,
+        };
+        visitor.visit_f32(f32::from_bits(bits))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let bits = match self.config.endian {
+            Endian::Big => U64be.decode(&mut self.buf)?,
+            Endian::Little => U64le.decode(&mut self.buf)?,
+        };
+        visitor.visit_f64(f64::from_bits(bits))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let v = self.u32()?;
+        let c = std::char::from_u32(v)
+            .ok_or_else(|| invalid_data_error(format!("{:#x} is not a valid char", v)))?;
+        visitor.visit_char(c)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.string()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.string()?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_byte_buf(self.bytes()?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_byte_buf(self.bytes()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let _depth = DecodeLimits::enter()?;
+        match self.u8()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            b => Err(Error(invalid_data_error(format!(
+                "{:#04x} is not a valid presence byte",
+                b
+            )))),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let _depth = DecodeLimits::enter()?;
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let len = self.seq_len()?;
+        let _depth = DecodeLimits::enter()?;
+        visitor.visit_seq(Elements { de: self, len })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        let _depth = DecodeLimits::enter()?;
+        visitor.visit_seq(Elements { de: self, len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let len = self.seq_len()?;
+        let _depth = DecodeLimits::enter()?;
+        visitor.visit_map(Elements { de: self, len })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let _depth = DecodeLimits::enter()?;
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(not_self_describing())
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(not_self_describing())
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+// Elements of a sequence, tuple or struct, or entries of a map.
+struct Elements<'b, 'a, T, S, Q, R> {
+    de: &'b mut Deserializer<'a, T, S, Q, R>,
+    len: usize,
+}
+
+impl<'de, 'a, 'b, T, S, Q, R> de::SeqAccess<'de> for Elements<'b, 'a, T, S, Q, R>
+where
+    S: Deen,
+    <S as Deen>::Item: Integer,
+    Q: Deen,
+    <Q as Deen>::Item: Integer,
+    R: io::Read,
+{
+    type Error = Error;
+
+    fn next_element_seed<E: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: E,
+    ) -> Result<Option<E::Value>, Error> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, 'a, 'b, T, S, Q, R> de::MapAccess<'de> for Elements<'b, 'a, T, S, Q, R>
+where
+    S: Deen,
+    <S as Deen>::Item: Integer,
+    Q: Deen,
+    <Q as Deen>::Item: Integer,
+    R: io::Read,
+{
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, 'a, 'b, T, S, Q, R> de::EnumAccess<'de> for &'b mut Deserializer<'a, T, S, Q, R>
+where
+    S: Deen,
+    <S as Deen>::Item: Integer,
+    Q: Deen,
+    <Q as Deen>::Item: Integer,
+    R: io::Read,
+{
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let index: de::value::U32Deserializer<Error> = self.u32()?.into_deserializer();
+        Ok((seed.deserialize(index)?, self))
+    }
+}
+
+impl<'de, 'a, 'b, T, S, Q, R> de::VariantAccess<'de> for &'b mut Deserializer<'a, T, S, Q, R>
+where
+    S: Deen,
+    <S as Deen>::Item: Integer,
+    Q: Deen,
+    <Q as Deen>::Item: Integer,
+    R: io::Read,
+{
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<V::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
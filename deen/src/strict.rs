@@ -0,0 +1,52 @@
+use std::{cell::Cell, io};
+
+use crate::{Deen, Schema};
+
+thread_local! {
+    static CANONICAL: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether the decode running on the current thread must only accept canonical encodings.
+/// Deeners with several encodings of the same value check this and reject the redundant ones.
+pub fn is_canonical() -> bool {
+    CANONICAL.with(Cell::get)
+}
+
+/// Decodes the inner deener in canonical mode: varints must be minimal, padding must be zeroed
+/// and `Any` fields must hold the value they are encoded with. This makes re-encoding a
+/// decoded value reproduce the input, so signatures over it stay valid.
+pub struct Canonical<T> {
+    deener: T,
+}
+
+impl<T> Canonical<T> {
+    pub fn new(deener: T) -> Canonical<T> {
+        Self { deener }
+    }
+}
+
+impl<T: Deen> Deen for Canonical<T> {
+    type Item = T::Item;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        self.deener.encode(value, buf)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        let prev = CANONICAL.with(|c| c.replace(true));
+        let _restore = Restore(prev);
+        self.deener.decode(buf)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::wrap("Canonical", self.deener.schema())
+    }
+}
+
+struct Restore(bool);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CANONICAL.with(|c| c.set(self.0));
+    }
+}
@@ -0,0 +1,144 @@
+use std::io;
+
+use crate::{invalid_data_error, Deen, Integer, Padding, Schema, Value};
+
+/// Decodes the inner deener from the next `len` bytes only, as for the body of TLV records and
+/// chunks. The inner value must use all of them, unless `skip_remaining` is set.
+pub struct Take<T> {
+    len: u64,
+    deener: T,
+    skip_remaining: bool,
+}
+
+impl<T: Deen> Take<T> {
+    pub fn new(len: impl Into<u64>, deener: T) -> Take<T> {
+        Self {
+            len: len.into(),
+            deener,
+            skip_remaining: false,
+        }
+    }
+
+    /// Length is decoded with `len_deener` right before the inner value and derived from the
+    /// encoded inner value on encode.
+    pub fn prefixed<L>(len_deener: L, deener: T) -> Prefixed<L, T>
+    where
+        L: Deen,
+        <L as Deen>::Item: Integer,
+    {
+        Prefixed {
+            len_deener,
+            deener,
+            skip_remaining: false,
+        }
+    }
+
+    /// Skip bytes the inner value does not use on decode, and pad with zeroes on encode.
+    /// In canonical mode skipped bytes must be zero.
+    pub fn skip_remaining(self) -> Take<T> {
+        Self {
+            skip_remaining: true,
+            ..self
+        }
+    }
+}
+
+fn decode_limited<T: Deen>(
+    deener: &T,
+    len: u64,
+    skip_remaining: bool,
+    buf: impl io::Read,
+) -> io::Result<T::Item> {
+    let mut limited = buf.take(len);
+    let item = deener.decode(&mut limited)?;
+    let left = limited.limit();
+    if left > 0 {
+        if !skip_remaining {
+            return Err(invalid_data_error(format!(
+                "value used {} of {} bytes",
+                len - left,
+                len
+            )));
+        }
+        Padding::new(left as usize).compare(&mut limited)?;
+    }
+    Ok(item)
+}
+
+impl<T: Deen> Deen for Take<T> {
+    type Item = T::Item;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        let body = self.deener.to_vec(value)?;
+        let len = body.len() as u64;
+        if len > self.len || (len < self.len && !self.skip_remaining) {
+            return Err(invalid_data_error(format!(
+                "value takes {} bytes, but the length is {}",
+                len, self.len
+            )));
+        }
+        buf.write_all(&body)?;
+        Padding::new((self.len - len) as usize).encode_value(buf)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        decode_limited(&self.deener, self.len, self.skip_remaining, buf)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::Take {
+            prefix: None,
+            inner: Box::new(self.deener.schema()),
+        }
+    }
+}
+
+pub struct Prefixed<L, T> {
+    len_deener: L,
+    deener: T,
+    skip_remaining: bool,
+}
+
+impl<L, T> Prefixed<L, T> {
+    /// Skip bytes the inner value does not use on decode.
+    pub fn skip_remaining(self) -> Prefixed<L, T> {
+        Self {
+            skip_remaining: true,
+            ..self
+        }
+    }
+}
+
+impl<L, T> Deen for Prefixed<L, T>
+where
+    L: Deen,
+    <L as Deen>::Item: Integer,
+    T: Deen,
+{
+    type Item = T::Item;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        let body = self.deener.to_vec(value)?;
+        let len = <L as Deen>::Item::from_i128(body.len() as i128).ok_or_else(|| {
+            invalid_data_error(format!("length {} does not fit the prefix", body.len()))
+        })?;
+        self.len_deener.encode(&len, &mut buf)?;
+        buf.write_all(&body)
+    }
+
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        let len = self.len_deener.decode(&mut buf)?;
+        let len = len
+            .to_i128()
+            .filter(|l| *l >= 0 && *l <= i128::from(u64::MAX))
+            .ok_or_else(|| invalid_data_error("invalid length prefix"))?;
+        decode_limited(&self.deener, len as u64, self.skip_remaining, buf)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::Take {
+            prefix: Some(Box::new(self.len_deener.schema())),
+            inner: Box::new(self.deener.schema()),
+        }
+    }
+}
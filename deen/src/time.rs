@@ -0,0 +1,325 @@
+use std::{
+    io,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::{invalid_data_error, Deen, Endian, Integer, Schema, U16le, U64le};
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+// Seconds between the epoch of the format and the Unix epoch.
+const NTP_EPOCH_OFFSET: i128 = 2_208_988_800;
+const FILETIME_EPOCH_OFFSET: i128 = 11_644_473_600;
+const GPS_EPOCH_OFFSET: i128 = 315_964_800;
+
+/// Leap seconds between GPS time and UTC since the start of 2017.
+pub const GPS_LEAP_SECONDS: i64 = 18;
+
+fn to_nanos(time: &SystemTime) -> i128 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_nanos() as i128,
+        Err(e) => -(e.duration().as_nanos() as i128),
+    }
+}
+
+fn from_nanos(nanos: i128) -> io::Result<SystemTime> {
+    let d = Duration::new(
+        (nanos.abs() / NANOS_PER_SEC) as u64,
+        (nanos.abs() % NANOS_PER_SEC) as u32,
+    );
+    let time = if nanos >= 0 {
+        UNIX_EPOCH.checked_add(d)
+    } else {
+        UNIX_EPOCH.checked_sub(d)
+    };
+    time.ok_or_else(|| invalid_data_error("timestamp is out of range of SystemTime"))
+}
+
+fn out_of_range(time: &SystemTime, format: &str) -> io::Error {
+    invalid_data_error(format!("{:?} can not be represented as {}", time, format))
+}
+
+/// Integer count of `unit` nanoseconds since `epoch` seconds relative to the Unix epoch.
+pub struct EpochTime<T> {
+    deener: T,
+    unit: i128,
+    epoch: i128,
+}
+
+impl<T> EpochTime<T>
+where
+    T: Deen,
+    <T as Deen>::Item: Integer,
+{
+    fn new(deener: T, unit: i128, epoch: i128) -> EpochTime<T> {
+        Self {
+            deener,
+            unit,
+            epoch,
+        }
+    }
+}
+
+impl<T> Deen for EpochTime<T>
+where
+    T: Deen,
+    <T as Deen>::Item: Integer,
+{
+    type Item = SystemTime;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        let ticks = (to_nanos(value) - self.epoch * NANOS_PER_SEC).div_euclid(self.unit);
+        let t = <T as Deen>::Item::from_i128(ticks)
+            .ok_or_else(|| out_of_range(value, "an epoch timestamp"))?;
+        self.deener.encode(&t, buf)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        let ticks = self
+            .deener
+            .decode(buf)?
+            .to_i128()
+            .ok_or_else(|| invalid_data_error("timestamp is out of range of SystemTime"))?;
+        let nanos = ticks
+            .checked_mul(self.unit)
+            .and_then(|n| n.checked_add(self.epoch * NANOS_PER_SEC))
+            .ok_or_else(|| invalid_data_error("timestamp is out of range of SystemTime"))?;
+        from_nanos(nanos)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::wrap("EpochTime", self.deener.schema())
+    }
+}
+
+/// Unix time in seconds, milliseconds, microseconds or nanoseconds stored by any integer deener,
+/// e.g. `UnixTime::seconds(I32be)`.
+pub struct UnixTime;
+
+impl UnixTime {
+    pub fn seconds<T: Deen>(deener: T) -> EpochTime<T>
+    where
+        <T as Deen>::Item: Integer,
+    {
+        EpochTime::new(deener, NANOS_PER_SEC, 0)
+    }
+
+    pub fn millis<T: Deen>(deener: T) -> EpochTime<T>
+    where
+        <T as Deen>::Item: Integer,
+    {
+        EpochTime::new(deener, 1_000_000, 0)
+    }
+
+    pub fn micros<T: Deen>(deener: T) -> EpochTime<T>
+    where
+        <T as Deen>::Item: Integer,
+    {
+        EpochTime::new(deener, 1_000, 0)
+    }
+
+    pub fn nanos<T: Deen>(deener: T) -> EpochTime<T>
+    where
+        <T as Deen>::Item: Integer,
+    {
+        EpochTime::new(deener, 1, 0)
+    }
+}
+
+/// GPS time in seconds since 1980-01-06, converted to UTC with a fixed leap second count.
+pub struct GpsTime;
+
+impl GpsTime {
+    pub fn seconds<T: Deen>(deener: T) -> EpochTime<T>
+    where
+        <T as Deen>::Item: Integer,
+    {
+        Self::with_leap_seconds(deener, GPS_LEAP_SECONDS)
+    }
+
+    pub fn with_leap_seconds<T: Deen>(deener: T, leap_seconds: i64) -> EpochTime<T>
+    where
+        <T as Deen>::Item: Integer,
+    {
+        EpochTime::new(
+            deener,
+            NANOS_PER_SEC,
+            GPS_EPOCH_OFFSET - i128::from(leap_seconds),
+        )
+    }
+}
+
+/// Windows FILETIME: 100 ns intervals since 1601-01-01, little-endian.
+#[derive(Clone, Copy, Debug)]
+pub struct FileTime;
+
+impl Deen for FileTime {
+    type Item = SystemTime;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        EpochTime::new(U64le, 100, -FILETIME_EPOCH_OFFSET).encode(value, buf)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        EpochTime::new(U64le, 100, -FILETIME_EPOCH_OFFSET).decode(buf)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::leaf("FileTime", Some(8), Some(Endian::Little))
+    }
+}
+
+fn ntp_fraction_to_nanos(fraction: u64, bits: u32) -> i128 {
+    (i128::from(fraction) * NANOS_PER_SEC) >> bits
+}
+
+fn nanos_to_ntp_fraction(nanos: i128, bits: u32) -> u64 {
+    ((nanos << bits) / NANOS_PER_SEC) as u64
+}
+
+/// NTP 64-bit timestamp: 32-bit seconds since 1900 and 32-bit fraction. Timestamps with the
+/// most significant bit clear are taken to be in era 1 (2036-2104), as RFC 4330 recommends.
+#[derive(Clone, Copy, Debug)]
+pub struct NtpTimestamp;
+
+impl Deen for NtpTimestamp {
+    type Item = SystemTime;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        let nanos = to_nanos(value) + NTP_EPOCH_OFFSET * NANOS_PER_SEC;
+        let seconds = nanos.div_euclid(NANOS_PER_SEC);
+        let fraction = nanos_to_ntp_fraction(nanos.rem_euclid(NANOS_PER_SEC), 32);
+        let pivot = 1i128 << 31;
+        if seconds < pivot || seconds >= (1 << 32) + pivot {
+            return Err(out_of_range(value, "an NTP timestamp"));
+        }
+        buf.write_u32::<BigEndian>(seconds as u32)?;
+        buf.write_u32::<BigEndian>(fraction as u32)
+    }
+
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        let seconds = buf.read_u32::<BigEndian>()?;
+        let fraction = buf.read_u32::<BigEndian>()?;
+        let era = if seconds & 0x8000_0000 == 0 { 1 << 32 } else { 0 };
+        let seconds = era + i128::from(seconds) - NTP_EPOCH_OFFSET;
+        from_nanos(seconds * NANOS_PER_SEC + ntp_fraction_to_nanos(fraction.into(), 32))
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::leaf("NtpTimestamp", Some(8), Some(Endian::Big))
+    }
+}
+
+/// NTP 128-bit date format: signed era number, 32-bit era offset and 64-bit fraction.
+#[derive(Clone, Copy, Debug)]
+pub struct NtpDate;
+
+impl Deen for NtpDate {
+    type Item = SystemTime;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        let nanos = to_nanos(value) + NTP_EPOCH_OFFSET * NANOS_PER_SEC;
+        let seconds = nanos.div_euclid(NANOS_PER_SEC);
+        let fraction = nanos_to_ntp_fraction(nanos.rem_euclid(NANOS_PER_SEC), 64);
+        buf.write_i32::<BigEndian>(seconds.div_euclid(1 << 32) as i32)?;
+        buf.write_u32::<BigEndian>(seconds.rem_euclid(1 << 32) as u32)?;
+        buf.write_u64::<BigEndian>(fraction)
+    }
+
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        let era = buf.read_i32::<BigEndian>()?;
+        let offset = buf.read_u32::<BigEndian>()?;
+        let fraction = buf.read_u64::<BigEndian>()?;
+        let seconds = (i128::from(era) << 32) + i128::from(offset) - NTP_EPOCH_OFFSET;
+        from_nanos(seconds * NANOS_PER_SEC + ntp_fraction_to_nanos(fraction, 64))
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::leaf("NtpDate", Some(16), Some(Endian::Big))
+    }
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date, see
+// http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// MS-DOS packed time and date, two little-endian words in that order as in ZIP headers.
+/// The fields carry no time zone and are treated as UTC; seconds have two second resolution.
+#[derive(Clone, Copy, Debug)]
+pub struct DosDateTime;
+
+impl Deen for DosDateTime {
+    type Item = SystemTime;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        let seconds = to_nanos(value).div_euclid(NANOS_PER_SEC) as i64;
+        let (days, secs) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+        let (year, month, day) = civil_from_days(days);
+        if !(1980..=2107).contains(&year) {
+            return Err(out_of_range(value, "a DOS date"));
+        }
+
+        let time = (secs / 3600) << 11 | (secs / 60 % 60) << 5 | (secs % 60 / 2);
+        let date = (year - 1980) << 9 | month << 5 | day;
+        U16le.encode(&(time as u16), &mut buf)?;
+        U16le.encode(&(date as u16), &mut buf)
+    }
+
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        let time = i64::from(U16le.decode(&mut buf)?);
+        let date = i64::from(U16le.decode(&mut buf)?);
+
+        let (hour, minute, second) = (time >> 11, time >> 5 & 0x3f, (time & 0x1f) * 2);
+        let (year, month, day) = (1980 + (date >> 9), date >> 5 & 0x0f, date & 0x1f);
+        if hour > 23
+            || minute > 59
+            || second > 59
+            || !(1..=12).contains(&month)
+            || !(1..=days_in_month(year, month)).contains(&day)
+        {
+            return Err(invalid_data_error(format!(
+                "invalid DOS date/time {:#06x} {:#06x}",
+                date, time
+            )));
+        }
+
+        let days = days_from_civil(year, month, day);
+        let seconds = days * 86_400 + hour * 3600 + minute * 60 + second;
+        from_nanos(i128::from(seconds) * NANOS_PER_SEC)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::leaf("DosDateTime", Some(4), Some(Endian::Little))
+    }
+}
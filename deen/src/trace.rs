@@ -0,0 +1,242 @@
+use std::{cell::RefCell, fmt, io};
+
+use crate::Deen;
+
+/// Record of how each byte was interpreted by a decode, see `Trace::decode`.
+///
+/// `deen!` parsers add an entry for every item they decode, nested parsers add entries below
+/// the field they are decoded for. `Display` renders the trace as an annotated hexdump.
+#[derive(Clone, Debug, Default)]
+pub struct Trace {
+    pub entries: Vec<TraceEntry>,
+    /// Number of bytes read, up to the error if the decode failed.
+    pub decoded: u64,
+    pub error: Option<String>,
+    input: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceEntry {
+    /// Field name, or source text of values like tags.
+    pub label: String,
+    /// Number of enclosing entries.
+    pub depth: usize,
+    pub start: u64,
+    pub end: u64,
+    /// `Debug` of the decoded field, if its type implements it.
+    pub value: Option<String>,
+    /// The decode failed inside this entry.
+    pub failed: bool,
+}
+
+struct State {
+    position: u64,
+    entries: Vec<TraceEntry>,
+    open: Vec<usize>,
+}
+
+thread_local! {
+    static STATE: RefCell<Option<State>> = const { RefCell::new(None) };
+}
+
+impl Trace {
+    /// Decodes `bytes` while recording a trace. The trace is returned even if the decode fails.
+    pub fn decode<T: Deen>(deener: &T, bytes: &[u8]) -> (io::Result<T::Item>, Trace) {
+        let state = State {
+            position: 0,
+            entries: Vec::new(),
+            open: Vec::new(),
+        };
+        let _restore = Restore(STATE.with(|s| s.borrow_mut().replace(state)));
+        let result = deener.decode(TracingReader { inner: bytes });
+        let state = STATE.with(|s| s.borrow_mut().take()).unwrap();
+
+        let trace = Trace {
+            entries: state.entries,
+            decoded: state.position,
+            error: result.as_ref().err().map(|e| e.to_string()),
+            input: bytes.to_vec(),
+        };
+        (result, trace)
+    }
+
+    /// Starts an entry, finished with `TraceGuard::done` or marked as failed if the guard is
+    /// dropped first. Called by `deen!` parsers.
+    pub fn enter(label: &str) -> TraceGuard {
+        STATE.with(|s| match s.borrow_mut().as_mut() {
+            Some(state) => {
+                state.open.push(state.entries.len());
+                state.entries.push(TraceEntry {
+                    label: label.to_string(),
+                    depth: state.open.len() - 1,
+                    start: state.position,
+                    end: state.position,
+                    value: None,
+                    failed: false,
+                });
+                TraceGuard { active: true }
+            }
+            None => TraceGuard { active: false },
+        })
+    }
+}
+
+// Puts back the state of an enclosing trace, even if the decode panics.
+struct Restore(Option<State>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        STATE.with(|s| *s.borrow_mut() = self.0.take());
+    }
+}
+
+pub struct TraceGuard {
+    active: bool,
+}
+
+impl TraceGuard {
+    /// Finishes the entry, `value` is only called while tracing.
+    pub fn done(mut self, value: impl FnOnce() -> Option<String>) {
+        if self.active {
+            self.active = false;
+            finish(value(), false);
+        }
+    }
+}
+
+impl Drop for TraceGuard {
+    fn drop(&mut self) {
+        if self.active {
+            finish(None, true);
+        }
+    }
+}
+
+fn finish(value: Option<String>, failed: bool) {
+    STATE.with(|s| {
+        if let Some(state) = s.borrow_mut().as_mut() {
+            if let Some(i) = state.open.pop() {
+                let entry = &mut state.entries[i];
+                entry.end = state.position;
+                entry.value = value;
+                entry.failed = failed;
+            }
+        }
+    });
+}
+
+struct TracingReader<R> {
+    inner: R,
+}
+
+impl<R: io::Read> io::Read for TracingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        STATE.with(|s| {
+            if let Some(state) = s.borrow_mut().as_mut() {
+                state.position += n as u64;
+            }
+        });
+        Ok(n)
+    }
+}
+
+// Lets `deen!` format decoded values that implement `Debug` and skip the others:
+// `(&TraceValue(&v)).trace_value()` resolves to `TraceDebug` if it applies.
+#[doc(hidden)]
+pub struct TraceValue<'a, T>(pub &'a T);
+
+#[doc(hidden)]
+pub trait TraceDebug {
+    fn trace_value(&self) -> Option<String>;
+}
+
+impl<T: fmt::Debug> TraceDebug for TraceValue<'_, T> {
+    fn trace_value(&self) -> Option<String> {
+        Some(format!("{:?}", self.0))
+    }
+}
+
+#[doc(hidden)]
+pub trait TraceOpaque {
+    fn trace_value(&self) -> Option<String>;
+}
+
+impl<T> TraceOpaque for &TraceValue<'_, T> {
+    fn trace_value(&self) -> Option<String> {
+        None
+    }
+}
+
+const ROW: usize = 16;
+
+impl Trace {
+    fn fmt_rows(&self, f: &mut fmt::Formatter, start: u64, end: u64, label: &str) -> fmt::Result {
+        let bytes = &self.input[start as usize..end as usize];
+        if bytes.is_empty() {
+            return writeln!(f, "{:08x}  {:48}  {:16}  {}", start, "", "", label);
+        }
+        for (n, row) in bytes.chunks(ROW).enumerate() {
+            let hex: Vec<_> = row.iter().map(|b| format!("{:02x}", b)).collect();
+            let ascii: String = row
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            write!(
+                f,
+                "{:08x}  {:48}  {:16}",
+                start as usize + n * ROW,
+                hex.join(" "),
+                ascii
+            )?;
+            if n == 0 {
+                write!(f, "  {}", label)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// One line per entry with offset, hex, ASCII and label, followed by the error and the bytes
+/// that were not decoded.
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, entry) in self.entries.iter().enumerate() {
+            let mut label = format!("{:1$}{2}", "", entry.depth * 2, entry.label);
+            if let Some(value) = &entry.value {
+                label = format!("{} = {}", label, value);
+            }
+            let innermost_failure = entry.failed
+                && !self.entries[i + 1..]
+                    .iter()
+                    .take_while(|e| e.depth > entry.depth)
+                    .any(|e| e.failed);
+            if innermost_failure {
+                label.push_str("  <-- failed here");
+            }
+            let has_children = self
+                .entries
+                .get(i + 1)
+                .is_some_and(|e| e.depth > entry.depth);
+            if has_children {
+                writeln!(f, "{:08x}  {:48}  {:16}  {}", entry.start, "", "", label)?;
+            } else {
+                self.fmt_rows(f, entry.start, entry.end, &label)?;
+            }
+        }
+        if let Some(message) = &self.error {
+            writeln!(f, "error at {:#x}: {}", self.decoded, message)?;
+        }
+        if self.decoded < self.input.len() as u64 {
+            self.fmt_rows(f, self.decoded, self.input.len() as u64, "(not decoded)")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use std::{fmt, io, str::FromStr};
+
+use crate::{Deen, Endian, Schema};
+
+/// 128-bit UUID, stored in RFC 4122 (big-endian) byte order regardless of the wire layout.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid(pub [u8; 16]);
+
+impl Uuid {
+    pub fn nil() -> Uuid {
+        Uuid([0; 16])
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    pub fn is_nil(&self) -> bool {
+        self.0 == [0; 16]
+    }
+
+    pub fn version(&self) -> u8 {
+        self.0[6] >> 4
+    }
+
+    // Swaps the first three groups between RFC 4122 and Microsoft mixed-endian layouts.
+    fn swap_mixed(mut bytes: [u8; 16]) -> [u8; 16] {
+        bytes[0..4].reverse();
+        bytes[4..6].reverse();
+        bytes[6..8].reverse();
+        bytes
+    }
+}
+
+impl From<[u8; 16]> for Uuid {
+    fn from(bytes: [u8; 16]) -> Self {
+        Uuid(bytes)
+    }
+}
+
+impl fmt::Display for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, b) in self.0.iter().enumerate() {
+            if i == 4 || i == 6 || i == 8 || i == 10 {
+                f.write_str("-")?;
+            }
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseUuidError(String);
+
+impl fmt::Display for ParseUuidError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid UUID: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseUuidError {}
+
+impl FromStr for Uuid {
+    type Err = ParseUuidError;
+
+    /// Accepts the hyphenated form, optionally in braces as Windows prints GUIDs.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseUuidError(s.to_string());
+        let hex = s.trim_start_matches('{').trim_end_matches('}');
+        let groups = hex.split('-').map(str::len).collect::<Vec<_>>();
+        if groups != [8, 4, 4, 4, 12] {
+            return Err(err());
+        }
+
+        let digits = hex.replace('-', "");
+        let mut bytes = [0; 16];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = digits
+                .get(2 * i..2 * i + 2)
+                .and_then(|d| u8::from_str_radix(d, 16).ok())
+                .ok_or_else(err)?;
+        }
+        Ok(Uuid(bytes))
+    }
+}
+
+/// UUID in RFC 4122 layout: all fields big-endian.
+#[derive(Clone, Copy, Debug)]
+pub struct UuidBe;
+
+impl Deen for UuidBe {
+    type Item = Uuid;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        buf.write_all(&value.0)
+    }
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        let mut bytes = [0; 16];
+        buf.read_exact(&mut bytes)?;
+        Ok(Uuid(bytes))
+    }
+    fn schema(&self) -> Schema {
+        Schema::leaf("UuidBe", Some(16), Some(Endian::Big))
+    }
+}
+
+/// GUID in Microsoft layout used by Windows, EFI and GPT: the first three groups are
+/// little-endian, the last eight bytes are stored as is.
+#[derive(Clone, Copy, Debug)]
+pub struct Guid;
+
+impl Deen for Guid {
+    type Item = Uuid;
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        buf.write_all(&Uuid::swap_mixed(value.0))
+    }
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        let mut bytes = [0; 16];
+        buf.read_exact(&mut bytes)?;
+        Ok(Uuid(Uuid::swap_mixed(bytes)))
+    }
+    fn schema(&self) -> Schema {
+        Schema::leaf("Guid", Some(16), Some(Endian::Little))
+    }
+}
@@ -0,0 +1,97 @@
+use std::io;
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use crate::{invalid_data_error, is_canonical, Deen, Schema};
+
+fn write_leb128(mut value: u64, mut buf: impl io::Write) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return buf.write_u8(byte);
+        }
+        buf.write_u8(byte | 0x80)?;
+    }
+}
+
+// Returns the value and the number of bytes it took.
+fn read_leb128(mut buf: impl io::Read) -> io::Result<(u64, usize)> {
+    let mut value = 0u64;
+    for i in 0..10 {
+        let byte = buf.read_u8()?;
+        let bits = u64::from(byte & 0x7f);
+        if i == 9 && bits > 1 {
+            break;
+        }
+        value |= bits << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    Err(invalid_data_error("varint overflows 64 bits"))
+}
+
+fn leb128_len(mut value: u64) -> usize {
+    let mut len = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        len += 1;
+    }
+    len
+}
+
+fn check_canonical(len: usize, value: u64) -> io::Result<()> {
+    if is_canonical() && len != leb128_len(value) {
+        Err(invalid_data_error(format!(
+            "non-canonical varint: {} encoded in {} bytes",
+            value, len
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Unsigned LEB128 varint as used by protobuf, WebAssembly and DWARF.
+#[derive(Clone, Copy, Debug)]
+pub struct VarU64;
+
+impl Deen for VarU64 {
+    type Item = u64;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        write_leb128(*value, buf)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        let (value, len) = read_leb128(buf)?;
+        check_canonical(len, value)?;
+        Ok(value)
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::leaf("VarU64", None, None)
+    }
+}
+
+/// Signed varint with zigzag encoding, so that small negative numbers stay short.
+#[derive(Clone, Copy, Debug)]
+pub struct VarI64;
+
+impl Deen for VarI64 {
+    type Item = i64;
+
+    fn encode(&self, value: &Self::Item, buf: impl io::Write) -> io::Result<()> {
+        write_leb128(((value << 1) ^ (value >> 63)) as u64, buf)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<Self::Item> {
+        let (value, len) = read_leb128(buf)?;
+        check_canonical(len, value)?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::leaf("VarI64", None, None)
+    }
+}
@@ -0,0 +1,256 @@
+use std::{
+    env, fs, io,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+const SCHEMA: &str = "
+#[deen(U8)]
+enum Kind {
+    File = 1,
+    Dir = 2,
+}
+
+struct EntryParser for Entry {
+    Tag::new(U16be, 0xcafe),
+    kind ~ Kind::deen(),
+    len ~ U8,
+    name ~ Utf8::new(len),
+    data ~ Take::prefixed(U8, Bytes::remaining()),
+}
+";
+
+// File unique to the test, removed at the end of it.
+struct TempFile(PathBuf);
+
+impl Deref for TempFile {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn temp_file(name: &str, contents: impl AsRef<[u8]>) -> TempFile {
+    let path = env::temp_dir().join(format!("deen-cli-{}-{}", std::process::id(), name));
+    fs::write(&path, contents).unwrap();
+    TempFile(path)
+}
+
+fn run(args: &[&str], stdin: &[u8]) -> (io::Result<()>, String, String) {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+    let result = deen_cli::run(&args, &mut &stdin[..], &mut stdout, &mut stderr);
+    let stdout = String::from_utf8(stdout).unwrap();
+    (result, stdout, String::from_utf8(stderr).unwrap())
+}
+
+#[test]
+fn decode_and_encode() {
+    let schema = temp_file("decode.deen", SCHEMA);
+    let schema = schema.to_str().unwrap();
+    let hex = "cafe 01 02 6869 02 0a0b";
+
+    let (result, json, _) = run(&["decode", schema, "--hex", hex], b"");
+    result.unwrap();
+    assert_eq!(
+        json,
+        "{\n  \"kind\": \"File\",\n  \"len\": 2,\n  \"name\": \"hi\",\n  \"data\": \"0a0b\"\n}\n"
+    );
+
+    let (result, encoded, _) = run(&["encode", schema, "--hex"], json.as_bytes());
+    result.unwrap();
+    assert_eq!(encoded, "cafe01026869020a0b\n");
+}
+
+#[test]
+fn decode_records() {
+    let schema = temp_file("records.deen", SCHEMA);
+    let schema = schema.to_str().unwrap();
+    let input = temp_file(
+        "records.bin",
+        [
+            0xff, 0xca, 0xfe, 1, 0, 0, 0xca, 0xfe, 2, 1, b'd', 1, 9, 0xee,
+        ],
+    );
+
+    let args = [
+        "decode",
+        schema,
+        input.to_str().unwrap(),
+        "--offset",
+        "1",
+        "--count",
+        "2",
+        "--format",
+        "tree",
+    ];
+    let (result, tree, warning) = run(&args, b"");
+    result.unwrap();
+    assert_eq!(
+        tree,
+        "[0]\nkind: File\nlen: 0\nname: \"\"\ndata: []\n\
+         [1]\nkind: Dir\nlen: 1\nname: \"d\"\ndata: [09]\n"
+    );
+    assert_eq!(warning, "warning: 1 bytes after 0xd were not decoded\n");
+}
+
+#[test]
+fn trace_and_errors() {
+    let schema = temp_file("trace.deen", SCHEMA);
+    let schema = schema.to_str().unwrap();
+
+    let (result, trace, _) = run(&["decode", schema, "--hex", "cafe03", "--trace"], b"");
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "at 0x3: 3 is not a valid Kind value"
+    );
+    assert_eq!(
+        trace,
+        format!(
+            "00000000  ca fe{0:43}  ..{0:14}  Tag::new(U16be, 0xcafe)\n\
+             00000002  03{0:46}  .{0:15}  kind  <-- failed here\n\
+             error at 0x3: 3 is not a valid Kind value\n",
+            ""
+        )
+    );
+
+    let bad = temp_file("bad.deen", "struct P for S {\n    a ~ U8\n}\nstruct");
+    let (result, _, _) = run(&["decode", bad.to_str().unwrap(), "--hex", "00"], b"");
+    let err = result.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "{}:4:7: expected identifier, found end of input",
+            bad.display()
+        )
+    );
+
+    let (result, _, _) = run(&["decode", schema, "--bogus"], b"");
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    let (result, _, _) = run(&["decode", schema, "--hex", "00", "--count", "-1"], b"");
+    let err = result.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(
+        err.to_string(),
+        "--count needs a non-negative number, found -1"
+    );
+
+    let (result, _, _) = run(&["encode", schema], b"{\"kind\": \"File\", \"size\": 1}");
+    assert_eq!(result.unwrap_err().to_string(), "Entry has no field size");
+}
+
+#[test]
+fn import_ksy() {
+    let ksy = temp_file(
+        "import.ksy",
+        "meta:\n  id: point\n  endian: be\nseq:\n  - id: x\n    type: s2\n  - id: y\n    type: s2\n",
+    );
+    let ksy = ksy.to_str().unwrap();
+    let (result, schema, _) = run(&["import-ksy", ksy], b"");
+    result.unwrap();
+    assert_eq!(
+        schema,
+        "struct PointParser for Point {\n    x ~ I16be,\n    y ~ I16be,\n}\n"
+    );
+
+    let (result, rust, _) = run(&["import-ksy", ksy, "--format", "rust"], b"");
+    result.unwrap();
+    assert!(rust.contains("pub struct Point {\n    pub x: i16,\n    pub y: i16,\n}\n"));
+
+    let (result, _, _) = run(&["import-ksy", ksy, "--format", "tree"], b"");
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+    let bad = temp_file(
+        "bad.ksy",
+        "meta:\n  id: bad\nseq:\n  - id: f\n    type: f8le\n",
+    );
+    let (result, _, _) = run(&["import-ksy", bad.to_str().unwrap()], b"");
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        format!(
+            "{}: can not import .ksy:\n    seq[0] (f): type f8le is not supported",
+            bad.display()
+        )
+    );
+}
+
+#[test]
+fn export() {
+    let schema = temp_file("export.deen", SCHEMA);
+    let schema = schema.to_str().unwrap();
+    let (result, ksy, _) = run(&["export", schema], b"");
+    result.unwrap();
+    assert!(ksy.starts_with("meta:\n  id: entry\nseq:\n"));
+
+    let output = temp_file("export.lua", "");
+    let args = [
+        "export",
+        schema,
+        "--format",
+        "lua",
+        "--output",
+        output.to_str().unwrap(),
+    ];
+    let (result, stdout, _) = run(&args, b"");
+    result.unwrap();
+    assert_eq!(stdout, "");
+    let lua = fs::read_to_string(&*output).unwrap();
+    assert!(lua.contains("local proto = Proto(\"entry\", \"Entry\")\n"));
+
+    let (result, _, _) = run(&["export", schema, "--parser", "Missing"], b"");
+    assert_eq!(result.unwrap_err().to_string(), "no parser Missing");
+    let (result, _, _) = run(&["export", schema, "--format", "tree"], b"");
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn decode_limits() {
+    let schema = temp_file(
+        "limits.deen",
+        "struct P for S {\n    n ~ U32be,\n    data ~ Bytes::new(n),\n    next ~ Optional::<S>::wrap(P).decode_when(|| n == 0),\n}\n",
+    );
+    let schema = schema.to_str().unwrap();
+
+    let (result, _, _) = run(&["decode", schema, "--hex", "ffffffff"], b"");
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "at 0x4: decode limit exceeded: allocation of 4294967295 exceeds maximum of 67108864"
+    );
+    let (result, _, _) = run(
+        &[
+            "decode",
+            schema,
+            "--hex",
+            "ffffffff",
+            "--max-alloc",
+            "0x100000000",
+        ],
+        b"",
+    );
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+    let nested = "00000000".repeat(3) + "00000001ff";
+    let (result, _, _) = run(&["decode", schema, "--hex", &nested], b"");
+    result.unwrap();
+    let (result, _, _) = run(
+        &["decode", schema, "--hex", &nested, "--max-depth", "3"],
+        b"",
+    );
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "at 0xc: decode limit exceeded: nesting depth of 4 exceeds maximum of 3"
+    );
+    let (result, _, _) = run(&["decode", schema, "--hex", &"00".repeat(4096)], b"");
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .ends_with("decode limit exceeded: nesting depth of 65 exceeds maximum of 64"));
+}
@@ -0,0 +1,53 @@
+use deen::{Positioned, PositionedError, U16be, U8};
+use deen_proc::deen;
+
+#[derive(Debug, PartialEq)]
+pub struct Record {
+    kind: u8,
+    length: u16,
+}
+
+deen! {
+    struct RecordParser for Record {
+        kind ~ U8,
+        length ~ U16be,
+    }
+}
+
+#[test]
+fn slices() {
+    let record = Record {
+        kind: 7,
+        length: 0x0102,
+    };
+    assert_eq!(RecordParser.to_vec(&record).unwrap(), [0x07, 0x01, 0x02]);
+
+    let mut buf = [0; 4];
+    assert_eq!(RecordParser.encode_into(&record, &mut buf).unwrap(), 3);
+    assert_eq!(buf, [0x07, 0x01, 0x02, 0x00]);
+
+    let err = RecordParser.encode_into(&record, &mut [0; 2]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+
+    assert_eq!(RecordParser.from_slice(&buf).unwrap(), (record, 3));
+}
+
+#[test]
+fn reader_position() {
+    let buf = [0x01, 0x00, 0x10, 0x02, 0x00, 0x20, 0x03, 0x00];
+    let mut reader = Positioned::new(&buf[..]);
+
+    let first = RecordParser.decode_from_reader(&mut reader).unwrap();
+    assert_eq!(first.kind, 1);
+    assert_eq!(reader.position(), 3);
+    RecordParser.decode_from_reader(&mut reader).unwrap();
+    assert_eq!(reader.position(), 6);
+
+    let err = RecordParser.decode_from_reader(&mut reader).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    let inner = err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<PositionedError>())
+        .unwrap();
+    assert_eq!((inner.start, inner.position), (6, 8));
+}
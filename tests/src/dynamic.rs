@@ -0,0 +1,399 @@
+use std::io;
+
+use deen::{
+    BinOp, Bytes, DecodeLimits, Deen, DynBranch, DynEnum, DynItem, DynPresence, DynSchema,
+    DynStruct, DynType, DynValue, Expr, IntType, Limit, LimitExceeded, Repeat, Trace, U16le, Utf8,
+    DEFAULT_MAX_DEPTH, U8,
+};
+
+use crate::{Encoder, Foo, Header};
+
+fn int(name: &str) -> DynType {
+    DynType::Int(IntType::from_name(name).unwrap())
+}
+
+fn field(name: &str, ty: DynType) -> DynItem {
+    DynItem::Field {
+        name: name.to_string(),
+        ty,
+    }
+}
+
+fn foo(int_name: &str, min_length: i128) -> DynItem {
+    field(
+        "foo",
+        DynType::Optional {
+            item: "Foo".to_string(),
+            inner: Box::new(int(int_name)),
+            presence: DynPresence::When(Expr::binary(
+                BinOp::Gt,
+                Expr::var("length"),
+                Expr::Int(min_length),
+            )),
+        },
+    )
+}
+
+fn branch(version: Option<i128>, tag: Option<i128>, foo: DynItem) -> DynBranch {
+    let tag = tag.map(|t| DynItem::Tag {
+        ty: int("U8"),
+        value: Expr::Hex(t),
+    });
+    DynBranch {
+        condition: version.map(|v| Expr::binary(BinOp::Gt, Expr::var("version"), Expr::Int(v))),
+        items: tag.into_iter().chain(Some(foo)).collect(),
+    }
+}
+
+// Runtime version of `crate::Encoder`.
+fn header_schema() -> DynSchema {
+    DynSchema::new()
+        .with_enum(DynEnum {
+            name: "Foo".to_string(),
+            int: IntType::from_name("U16be").unwrap(),
+            variants: vec![("Hello".to_string(), 0xff00), ("World".to_string(), 0x00ff)],
+        })
+        .with_struct(DynStruct {
+            parser: "Encoder".to_string(),
+            name: "Header".to_string(),
+            params: vec![("magic".to_string(), "u32".to_string())],
+            items: vec![
+                DynItem::Tag {
+                    ty: int("U32be"),
+                    value: Expr::var("magic"),
+                },
+                field("version", int("U8")),
+                DynItem::Any(int("U8")),
+                field("length", int("U16be")),
+                DynItem::If {
+                    name: Some("foo".to_string()),
+                    branches: vec![
+                        branch(Some(2), Some(0xff), foo("U32be", 1)),
+                        branch(Some(1), Some(0x00), foo("U32le", 1)),
+                        branch(None, None, foo("U32le", 0)),
+                    ],
+                },
+            ],
+        })
+}
+
+fn header_value(version: i128, length: i128, foo: Option<&str>) -> DynValue {
+    DynValue::Struct(vec![
+        ("version".to_string(), DynValue::Int(version)),
+        ("length".to_string(), DynValue::Int(length)),
+        (
+            "foo".to_string(),
+            DynValue::Option(foo.map(|f| Box::new(DynValue::Enum(f.to_string())))),
+        ),
+    ])
+}
+
+#[test]
+fn matches_deen_parser() {
+    let header = Header::new(3, 0x542, Some(Foo::Hello));
+    let buf = Encoder { magic: 0xcafebabe }.to_vec(&header).unwrap();
+
+    let schema = header_schema();
+    let parser = schema.parser("Encoder", &[("magic", 0xcafebabe)]).unwrap();
+    let value = parser.decode_exact(&buf).unwrap();
+    assert_eq!(value, header_value(3, 0x542, Some("Hello")));
+    assert_eq!(parser.to_vec(&value).unwrap(), buf);
+    let reflected = DynSchema::from_schema(&Encoder { magic: 0xcafebabe }.schema()).unwrap();
+    assert_eq!(
+        parser.schema().to_string(),
+        reflected
+            .parser("Encoder", &[("magic", 0xcafebabe)])
+            .unwrap()
+            .schema()
+            .to_string()
+    );
+    assert_eq!(
+        Trace::decode(&parser, &buf).1.to_string(),
+        Trace::decode(&Encoder { magic: 0xcafebabe }, &buf)
+            .1
+            .to_string()
+    );
+
+    let header = Header::new(1, 0, None);
+    let buf = Encoder { magic: 0xcafebabe }.to_vec(&header).unwrap();
+    assert_eq!(parser.decode_exact(&buf).unwrap(), header_value(1, 0, None));
+}
+
+#[test]
+fn invalid_input() {
+    let schema = header_schema();
+    let parser = schema.parser("Encoder", &[("magic", 1)]).unwrap();
+
+    let err = parser.decode_exact(&[0, 0, 0, 2]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "unexpected tag - expected: 1, found: 2");
+
+    let err = parser
+        .decode_exact(&[0, 0, 0, 1, 3, 0, 0, 2, 0xff, 0, 0, 0, 1])
+        .unwrap_err();
+    assert_eq!(err.to_string(), "1 is not a valid Foo value");
+
+    let err = parser
+        .to_vec(&header_value(3, 2, Some("Other")))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Other is not a variant of Foo");
+}
+
+fn record_schema() -> DynSchema {
+    let len = Expr::var("len");
+    DynSchema::new()
+        .with_struct(DynStruct {
+            parser: "RecordParser".to_string(),
+            name: "Record".to_string(),
+            params: vec![],
+            items: vec![
+                field("len", int("U8")),
+                field("name", DynType::Utf8(Some(len.clone()))),
+                field(
+                    "entries",
+                    DynType::Prefixed {
+                        len: IntType::from_name("U16le").unwrap(),
+                        inner: Box::new(DynType::Repeat(
+                            None,
+                            Box::new(DynType::Parser {
+                                name: "EntryParser".to_string(),
+                                args: vec![("len".to_string(), len)],
+                            }),
+                        )),
+                        skip_remaining: false,
+                    },
+                ),
+                field("rest", DynType::Bytes(None)),
+            ],
+        })
+        .with_struct(DynStruct {
+            parser: "EntryParser".to_string(),
+            name: "Entry".to_string(),
+            params: vec![("len".to_string(), "u8".to_string())],
+            items: vec![
+                field("id", DynType::VarU64),
+                field(
+                    "data",
+                    DynType::Bytes(Some(Expr::binary(
+                        BinOp::Sub,
+                        Expr::var("len"),
+                        Expr::Int(1),
+                    ))),
+                ),
+            ],
+        })
+}
+
+fn entry(id: i128, data: &[u8]) -> DynValue {
+    DynValue::Struct(vec![
+        ("id".to_string(), DynValue::Int(id)),
+        ("data".to_string(), DynValue::Bytes(data.to_vec())),
+    ])
+}
+
+#[test]
+fn nested_parsers_and_sequences() {
+    let buf = [3, b'a', b'b', b'c', 6, 0, 1, 7, 8, 2, 9, 10, 0xee];
+    let schema = record_schema();
+    let parser = schema.root().unwrap();
+    let value = parser.decode_exact(&buf).unwrap();
+    assert_eq!(
+        value.field("name"),
+        Some(&DynValue::String("abc".to_string()))
+    );
+    assert_eq!(
+        value.field("entries"),
+        Some(&DynValue::List(vec![entry(1, &[7, 8]), entry(2, &[9, 10])]))
+    );
+    assert_eq!(value.field("rest"), Some(&DynValue::Bytes(vec![0xee])));
+    assert_eq!(
+        value.to_string(),
+        "{ len: 3, name: \"abc\", entries: [{ id: 1, data: [07 08] }, \
+         { id: 2, data: [09 0a] }], rest: [ee] }"
+    );
+    assert_eq!(parser.to_vec(&value).unwrap(), buf);
+}
+
+#[test]
+fn displays_as_deen_source() {
+    let schema = record_schema();
+    assert_eq!(
+        schema.structs[0].to_string(),
+        "struct RecordParser for Record {
+    len ~ U8,
+    name ~ Utf8::new(len),
+    entries ~ Take::prefixed(U16le, Repeat::remaining(EntryParser { len })),
+    rest ~ Bytes::remaining(),
+}"
+    );
+    assert_eq!(
+        header_schema().structs[0].items[4].to_string(),
+        "foo ~ if version > 2 {
+    Tag::new(U8, 0xff);
+    Optional::<Foo>::wrap(U32be).decode_when(|| length > 1)
+} else if version > 1 {
+    Tag::new(U8, 0x0);
+    Optional::<Foo>::wrap(U32le).decode_when(|| length > 1)
+} else {
+    Optional::<Foo>::wrap(U32le).decode_when(|| length > 0)
+}"
+    );
+    assert_eq!(
+        Expr::binary(
+            BinOp::Mul,
+            Expr::binary(BinOp::Add, Expr::var("a"), Expr::Int(1)),
+            Expr::var("b")
+        )
+        .to_string(),
+        "(a + 1) * b"
+    );
+}
+
+#[test]
+fn bytes_and_sequences() {
+    assert_eq!(Bytes::new(2u8).decode_exact(&[1, 2]).unwrap(), [1, 2]);
+    assert!(Bytes::new(2u8).to_vec(&vec![1]).is_err());
+    assert_eq!(
+        Utf8::remaining().decode_exact(b"hi").unwrap(),
+        "hi".to_string()
+    );
+    assert!(Utf8::remaining().decode_exact(&[0xff]).is_err());
+    assert_eq!(
+        Repeat::new(2u8, U16le).decode_exact(&[1, 0, 2, 0]).unwrap(),
+        [1, 2]
+    );
+    assert_eq!(
+        Repeat::remaining(U8).decode_exact(&[1, 2, 3]).unwrap(),
+        [1, 2, 3]
+    );
+    let err = Repeat::remaining(U16le)
+        .decode_exact(&[1, 0, 2])
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    let err = Repeat::remaining(Bytes::new(0u8))
+        .decode_exact(&[1])
+        .unwrap_err();
+    assert_eq!(err.to_string(), "sequence element consumed no input");
+}
+
+const HEADER: &str = "
+#[repr(u16)]
+#[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive, DeenEnum)]
+#[deen(U16be)]
+enum Foo {
+    Hello = 0xff00,
+    World = 0x00ff,
+}
+
+// Same as `crate::Encoder`.
+#[derive(Debug)]
+pub struct Encoder(magic: u32) for Header {
+    Tag::new(U32be, magic),
+    version ~ U8,
+    Any::new(U8),
+    length ~ U16be,
+    foo ~ if version > 2 {
+        Tag::new(U8, 0xff);
+        Optional::<Foo>::wrap(U32be).decode_when(|| length > 1)
+    } else if version > 1{
+        Tag::new(U8, 0x00);
+        Optional::<Foo>::wrap(U32le).decode_when(|| length > 1)
+    } else {
+        Optional::<Foo>::wrap(U32le).decode_when(|| length > 0)
+    }
+}
+";
+
+#[test]
+fn parse_text() {
+    let schema: DynSchema = HEADER.parse().unwrap();
+    assert_eq!(schema, header_schema());
+    assert_eq!(schema.to_string().parse::<DynSchema>().unwrap(), schema);
+
+    let schema = record_schema();
+    assert_eq!(schema.to_string().parse::<DynSchema>().unwrap(), schema);
+}
+
+#[test]
+fn parse_expressions() {
+    let schema: DynSchema = "
+        struct P(n: u8) for S {
+            a ~ U8,
+            b ~ Bytes::new((a as usize + 1) * 2 - n),
+            c ~ Optional::<u8>::wrap(U8).decode_when(|| a & 0x80 != 0 || !(n >= 3)),
+            Padding::new(2usize),
+        }"
+    .parse()
+    .unwrap();
+    let parser = schema.parser("P", &[("n", 3)]).unwrap();
+    let value = parser.decode_exact(&[1, 9, 0, 0]).unwrap();
+    assert_eq!(value.field("b"), Some(&DynValue::Bytes(vec![9])));
+    assert_eq!(value.field("c"), Some(&DynValue::Option(None)));
+    assert_eq!(
+        schema.structs[0].items[1].to_string(),
+        "b ~ Bytes::new((a + 1) * 2 - n)"
+    );
+}
+
+fn parse_error(text: &str) -> String {
+    text.parse::<DynSchema>().unwrap_err().to_string()
+}
+
+#[test]
+fn parse_errors() {
+    assert_eq!(
+        parse_error("struct P for S {\n    a ~ U8\n    b ~ U8,\n}"),
+        "3:5: expected `}`, found `b`"
+    );
+    assert_eq!(
+        parse_error("struct P for S {\n    a ~ U17be,\n}"),
+        "2:9: unknown deener U17be"
+    );
+    assert_eq!(
+        parse_error("struct P for S {\n    a ~ Bytes::new(len $ 2),\n}"),
+        "2:24: unexpected character `$`"
+    );
+    assert_eq!(
+        parse_error("struct P for S {\n    a ~ Kind::deen(),\n}"),
+        "2:9: unknown enum Kind"
+    );
+    assert_eq!(
+        parse_error("struct P for S { a ~ Q { x: 1 } }\nstruct Q(x: u8, y: u8) for T { a ~ U8 }"),
+        "1:22: Q takes arguments { x, y }"
+    );
+    assert_eq!(
+        parse_error("enum Kind { A = 1 }"),
+        "1:1: enum Kind needs a `#[deen(..)]` attribute"
+    );
+    assert_eq!(
+        parse_error("struct P for S {\n    a ~ Optional::<u8>::wrap(U8).decode_when(|v| true),\n}"),
+        "2:46: closures over the value are not supported, use `|| condition`"
+    );
+}
+
+#[test]
+fn self_reference_without_input() {
+    let schema: DynSchema = "
+        struct P for S {
+            a ~ P,
+        }"
+    .parse()
+    .unwrap();
+    let parser = schema.parser("P", &[]).unwrap();
+    let err = parser.decode(&[0u8; 4][..]).unwrap_err();
+    let limit = err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<LimitExceeded>())
+        .unwrap();
+    assert_eq!(limit.limit, Limit::Depth);
+    assert_eq!(limit.max, DEFAULT_MAX_DEPTH as u64);
+
+    let err = DecodeLimits::new()
+        .max_depth(3)
+        .decode(&parser, &[0u8; 4][..])
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "decode limit exceeded: nesting depth of 4 exceeds maximum of 3"
+    );
+}
@@ -0,0 +1,162 @@
+use std::net::Ipv4Addr;
+
+use deen::{DynSchema, DynValue, Ipv4, U8};
+use deen_cli::{export_ksy, import_ksy, wireshark_dissector};
+use deen_proc::deen;
+
+use super::Encoder;
+
+const SCHEMA: &str = "
+#[deen(U8)]
+enum Kind {
+    File = 1,
+    Dir = 2,
+}
+
+struct EntryParser for Entry {
+    Tag::new(U16be, 0xcafe),
+    kind ~ Kind::deen(),
+    len ~ U8,
+    name ~ Utf8::new(len),
+    data ~ Take::prefixed(U8, Bytes::remaining()),
+}
+";
+
+pub struct Host {
+    port: u8,
+    addr: Ipv4Addr,
+}
+
+deen! {
+    struct HostParser for Host {
+        port ~ U8,
+        addr ~ Ipv4,
+    }
+}
+
+#[test]
+fn reflected() {
+    let schema = DynSchema::from_schema(&Encoder { magic: 0xcafebabe }.schema()).unwrap();
+    assert_eq!(
+        schema.to_string(),
+        "\
+struct Encoder(magic: u32) for Header {
+    Tag::new(U32be, magic),
+    version ~ U8,
+    Any::new(U8),
+    length ~ U16be,
+    foo ~ if version > 2 {
+        Tag::new(U8, 0xff);
+        Optional::<Foo>::wrap(U32be).decode_when(|| length > 1)
+    } else if version > 1 {
+        Tag::new(U8, 0x0);
+        Optional::<Foo>::wrap(U32le).decode_when(|| length > 1)
+    } else {
+        Optional::<Foo>::wrap(U32le).decode_when(|| length > 0)
+    },
+}"
+    );
+    let parser = schema.parser("Encoder", &[("magic", 0xcafebabe)]).unwrap();
+    let value = parser
+        .decode(&mut &[0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 0][..])
+        .unwrap();
+    assert_eq!(value.field("length"), Some(&DynValue::Int(0)));
+
+    let text: DynSchema = SCHEMA.parse().unwrap();
+    let parser = text.parser("EntryParser", &[]).unwrap();
+    assert_eq!(DynSchema::from_schema(&parser.schema()).unwrap(), text);
+
+    let err = DynSchema::from_schema(&HostParser.schema()).unwrap_err();
+    assert_eq!(err.to_string(), "HostParser.addr: unknown deener Ipv4");
+}
+
+#[test]
+fn ksy() {
+    let schema: DynSchema = SCHEMA.parse().unwrap();
+    let ksy = export_ksy(&schema).unwrap();
+    assert_eq!(
+        ksy,
+        "\
+meta:
+  id: entry
+seq:
+- contents:
+  - 202
+  - 254
+- id: kind
+  type: u1
+  enum: kind
+- id: len
+  type: u1
+- id: name
+  type: str
+  encoding: UTF-8
+  size: len
+- id: data_len
+  type: u1
+- id: data
+  size: data_len
+enums:
+  kind:
+    1: file
+    2: dir
+"
+    );
+
+    let imported = import_ksy(&ksy).unwrap();
+    let input = [0xca, 0xfe, 2, 1, b'd', 2, 9, 8];
+    let value = imported
+        .parser("EntryParser", &[])
+        .unwrap()
+        .decode(&mut &input[..])
+        .unwrap();
+    assert_eq!(
+        value.field("name"),
+        Some(&DynValue::String("d".to_string()))
+    );
+    assert_eq!(value.field("data"), Some(&DynValue::Bytes(vec![9, 8])));
+
+    let reflected = DynSchema::from_schema(&Encoder { magic: 1 }.schema()).unwrap();
+    assert_eq!(
+        export_ksy(&reflected).unwrap_err().to_string(),
+        "can not export to .ksy:\n    Encoder.foo: `if` chains with a name are not supported, \
+         use `Optional` or a parser per branch"
+    );
+}
+
+#[test]
+fn wireshark() {
+    let schema: DynSchema = SCHEMA.parse().unwrap();
+    let lua = wireshark_dissector(&schema, "entry", &[]).unwrap();
+    assert!(lua.starts_with("-- Wireshark dissector for Entry, generated by deen."));
+    assert!(lua.contains("local proto = Proto(\"entry\", \"Entry\")\n"));
+    assert!(lua.contains("local kind_values = { [1] = \"File\", [2] = \"Dir\" }\n"));
+    assert!(lua.contains(
+        "    [\"kind\"] = ProtoField.uint8(\"entry.kind\", \"kind\", base.DEC, kind_values),\n"
+    ));
+    assert!(lua.contains("        local range = buf(offset, v_len)\n"));
+    assert!(lua.contains("        local take_limit = offset + len\n"));
+    assert!(lua.ends_with("    dissect.EntryParser(buf, root, 0, buf:len())\nend\n"));
+
+    let reflected = DynSchema::from_schema(&Encoder { magic: 1 }.schema()).unwrap();
+    let lua = wireshark_dissector(&reflected, "header", &[("magic", 0xcafebabe)]).unwrap();
+    assert!(lua.contains("function dissect.Encoder(buf, tree, offset, limit, v_magic)\n"));
+    assert!(lua.contains("    if v_version > 2 then\n"));
+    assert!(lua.contains("    dissect.Encoder(buf, root, 0, buf:len(), 3405691582)\n"));
+    assert_eq!(
+        wireshark_dissector(&reflected, "header", &[])
+            .unwrap_err()
+            .to_string(),
+        "can not export to Lua:\n    Encoder: missing argument magic"
+    );
+
+    let schema: DynSchema = "struct IdParser for Id {\n    id ~ U128be,\n}"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        wireshark_dissector(&schema, "id", &[])
+            .unwrap_err()
+            .to_string(),
+        "can not export to Lua:\n    IdParser.id: 128-bit integers are not supported"
+    );
+}
@@ -0,0 +1,92 @@
+use deen::{Flags, U16le, U8};
+use deen_proc::deen;
+use try_from_primitive::Bitflags;
+
+#[repr(u8)]
+#[derive(Debug, PartialEq, Copy, Clone, Bitflags)]
+enum TcpFlag {
+    Fin = 0x01,
+    Syn = 0x02,
+    Rst = 0x04,
+    Psh = 0x08,
+    Ack = 0x10,
+}
+
+mod flag_path {
+    pub use deen::{Flag, Flags};
+}
+
+#[repr(u16)]
+#[derive(Debug, PartialEq, Copy, Clone, Bitflags)]
+#[try_from_primitive(crate = "self::flag_path")]
+enum Attribute {
+    ReadOnly = 0x0001,
+    Hidden = 0x0002,
+    System = 0x0004,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Segment {
+    flags: Flags<TcpFlag>,
+    attributes: Flags<Attribute>,
+}
+
+deen! {
+    struct SegmentParser for Segment {
+        flags ~ Flags::<TcpFlag>::strict(U8),
+        attributes ~ Flags::<Attribute>::lenient(U16le),
+    }
+}
+
+#[test]
+fn set_operations() {
+    let mut flags = TcpFlag::Syn | TcpFlag::Ack;
+    assert!(flags.contains(TcpFlag::Syn));
+    assert!(!flags.contains(TcpFlag::Syn | TcpFlag::Fin));
+    assert!(flags.intersects(TcpFlag::Syn | TcpFlag::Fin));
+    assert_eq!(flags.bits(), 0x12);
+
+    flags.remove(TcpFlag::Syn);
+    flags |= TcpFlag::Psh;
+    assert_eq!(flags.iter().collect::<Vec<_>>(), [TcpFlag::Psh, TcpFlag::Ack]);
+    assert_eq!(!flags, TcpFlag::Fin | TcpFlag::Syn | TcpFlag::Rst);
+    assert_eq!(flags - TcpFlag::Ack, Flags::from(TcpFlag::Psh));
+    assert_eq!(Flags::<TcpFlag>::all().bits(), 0x1f);
+    assert_eq!(Flags::<TcpFlag>::from_bits(0x20), None);
+    assert_eq!(Flags::<TcpFlag>::from_bits_truncate(0x31).bits(), 0x11);
+}
+
+#[test]
+fn debug() {
+    assert_eq!(format!("{:?}", TcpFlag::Syn | TcpFlag::Ack), "Flags(Syn | Ack)");
+    assert_eq!(format!("{:?}", Flags::<TcpFlag>::empty()), "Flags(empty)");
+    assert_eq!(
+        format!("{:?}", Flags::<Attribute>::from_bits_retain(0x8001)),
+        "Flags(ReadOnly | 0x8000)"
+    );
+}
+
+#[test]
+fn roundtrip() {
+    let buf = [0x12, 0x03, 0x80];
+    let segment = SegmentParser.decode(&mut &buf[..]).unwrap();
+    assert_eq!(segment.flags, TcpFlag::Syn | TcpFlag::Ack);
+    assert_eq!(segment.attributes.unknown_bits(), 0x8000);
+
+    let mut encoded = Vec::new();
+    SegmentParser.encode(&segment, &mut encoded).unwrap();
+    assert_eq!(encoded, buf);
+}
+
+#[test]
+fn strict_rejects_unknown_bits() {
+    let err = SegmentParser.decode(&mut &[0x42, 0x00, 0x00][..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let segment = Segment {
+        flags: Flags::from_bits_retain(0x80),
+        attributes: Flags::empty(),
+    };
+    let err = SegmentParser.encode(&segment, Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
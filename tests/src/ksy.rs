@@ -0,0 +1,190 @@
+use deen::{Deen, DynSchema, DynValue};
+use deen_cli::{import_ksy, rust_source};
+
+mod archive;
+
+use archive::{Archive, ArchiveParser, Body, BodyParser, Entry, EntryParser, Kind};
+
+const ARCHIVE: &str = "
+meta:
+  id: archive
+  endian: le
+  encoding: UTF-8
+doc: A toy archive.
+seq:
+  - id: magic
+    contents: [0x41, 0x52, 0x43, 1]
+  - id: num_entries
+    type: u2
+  - id: entries
+    type: entry(1)
+    repeat: expr
+    repeat-expr: num_entries
+types:
+  entry:
+    params:
+      - id: version
+        type: u1
+    seq:
+      - id: kind
+        type: u1
+        enum: kind
+      - id: name_len
+        type: u1
+      - id: name
+        type: str
+        size: name_len
+      - id: checksum
+        type: u4be
+        if: kind == kind::file and version >= 1
+      - id: body
+        type: body
+        size: name_len * 2
+  body:
+    seq:
+      - id: data
+        size-eos: true
+enums:
+  kind:
+    1: file
+    0x2:
+      id: dir
+";
+
+const INPUT: &[u8] = &[
+    0x41, 0x52, 0x43, 1, 2, 0, // header
+    1, 1, b'a', 0, 0, 0, 42, 11, 12, // file
+    2, 2, b'b', b'c', 1, 2, 3, 4, // dir
+];
+
+#[test]
+fn import_archive() {
+    let schema = import_ksy(ARCHIVE).unwrap();
+    assert_eq!(
+        schema.structs[1].to_string(),
+        "struct EntryParser(version: u8) for Entry {
+    kind ~ Kind::deen(),
+    name_len ~ U8,
+    name ~ Utf8::new(name_len),
+    checksum ~ Optional::<u32>::wrap(U32be).decode_when(|| kind == Kind::File && version >= 1),
+    body ~ Take::new(name_len * 2, BodyParser),
+}"
+    );
+    assert_eq!(schema.to_string().parse::<DynSchema>().unwrap(), schema);
+
+    let value = schema
+        .parser("ArchiveParser", &[])
+        .unwrap()
+        .decode_exact(INPUT)
+        .unwrap();
+    assert_eq!(
+        value.to_string(),
+        "{ num_entries: 2, entries: [\
+         { kind: File, name_len: 1, name: \"a\", checksum: Some(42), body: { data: [0b 0c] } }, \
+         { kind: Dir, name_len: 2, name: \"bc\", checksum: None, body: { data: [01 02 03 04] } }] }"
+    );
+}
+
+#[test]
+fn generated_rust() {
+    let schema = import_ksy(ARCHIVE).unwrap();
+    assert_eq!(rust_source(&schema), include_str!("ksy/archive.rs"));
+
+    let archive = ArchiveParser.decode_exact(INPUT).unwrap();
+    assert_eq!(
+        archive.entries[0],
+        Entry {
+            kind: Kind::File,
+            name_len: 1,
+            name: "a".to_string(),
+            checksum: Some(42),
+            body: Body { data: vec![11, 12] },
+        }
+    );
+    assert_eq!(
+        archive,
+        Archive {
+            num_entries: 2,
+            entries: vec![
+                archive.entries[0].clone(),
+                EntryParser { version: 1 }
+                    .decode_exact(&INPUT[15..])
+                    .unwrap(),
+            ],
+        }
+    );
+    assert_eq!(BodyParser.decode_exact(&[7]).unwrap().data, [7]);
+
+    let mut encoded = Vec::new();
+    ArchiveParser.encode(&archive, &mut encoded).unwrap();
+    assert_eq!(encoded, INPUT);
+}
+
+#[test]
+fn unsupported_constructs() {
+    let err = import_ksy(
+        "
+meta:
+  id: packet
+seq:
+  - id: len
+    type: u2
+  - id: name
+    type: strz
+    encoding: UTF-8
+  - id: body
+    type:
+      switch-on: len
+      cases:
+        1: u1
+  - id: ratio
+    type: f4
+  - id: parts
+    type: u1
+    repeat: until
+    repeat-until: _ == 0
+  - id: tail
+    size: _parent.len
+  - id: data
+    size: len
+    process: xor(0xff)
+instances:
+  total:
+    value: len * 2
+",
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.problems,
+        [
+            "instances: instances are not supported",
+            "seq[0] (len): u2 needs `meta.endian`",
+            "seq[1] (name): type strz is not supported",
+            "seq[2] (body): switch types are not supported",
+            "seq[3] (ratio): type f4 is not supported",
+            "seq[4] (parts): `repeat-until` is not supported",
+            "seq[4] (parts): `repeat: until` is not supported",
+            "seq[5] (tail): `_parent` in expressions is not supported",
+            "seq[6] (data): `process` is not supported",
+        ]
+    );
+    assert!(err
+        .to_string()
+        .starts_with("can not import .ksy:\n    instances: "));
+
+    let err = import_ksy("meta: {}\nseq: []").unwrap_err();
+    assert_eq!(err.problems, ["meta: `id` is missing"]);
+    assert!(import_ksy("- a\n- b").is_err());
+}
+
+#[test]
+fn dynamic_values() {
+    let schema = import_ksy(ARCHIVE).unwrap();
+    let entry = schema.parser("EntryParser", &[("version", 0)]).unwrap();
+    let value = entry.decode_exact(&[1, 1, b'x', 5, 6]).unwrap();
+    assert_eq!(value.field("checksum"), Some(&DynValue::Option(None)));
+    assert_eq!(
+        value.field("kind"),
+        Some(&DynValue::Enum("File".to_string()))
+    );
+}
@@ -0,0 +1,70 @@
+use deen::*;
+use deen_proc::deen;
+use try_from_primitive::{DeenEnum, TryFromPrimitive};
+
+#[repr(u8)]
+#[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive, DeenEnum)]
+#[deen(U8)]
+pub enum Kind {
+    File = 1,
+    Dir = 2,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Archive {
+    pub num_entries: u16,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Entry {
+    pub kind: Kind,
+    pub name_len: u8,
+    pub name: String,
+    pub checksum: Option<u32>,
+    pub body: Body,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Body {
+    pub data: Vec<u8>,
+}
+
+mod archive_parser {
+    use super::*;
+
+    deen! {
+        pub struct ArchiveParser for Archive {
+            Tag::new(U32be, 0x41524301),
+            num_entries ~ U16le,
+            entries ~ Repeat::new(num_entries, EntryParser { version: 1 }),
+        }
+    }
+}
+pub use archive_parser::ArchiveParser;
+
+mod entry_parser {
+    use super::*;
+
+    deen! {
+        pub struct EntryParser(version: u8) for Entry {
+            kind ~ Kind::deen(),
+            name_len ~ U8,
+            name ~ Utf8::new(name_len),
+            checksum ~ Optional::<u32>::wrap(U32be).decode_when(|| kind == Kind::File && version >= 1),
+            body ~ Take::new(name_len * 2, BodyParser),
+        }
+    }
+}
+pub use entry_parser::EntryParser;
+
+mod body_parser {
+    use super::*;
+
+    deen! {
+        pub struct BodyParser for Body {
+            data ~ Bytes::remaining(),
+        }
+    }
+}
+pub use body_parser::BodyParser;
@@ -1,6 +1,47 @@
+#[cfg(test)]
+mod cli;
+#[cfg(test)]
+mod convenience;
+#[cfg(test)]
+mod dynamic;
+#[cfg(test)]
+mod export;
+#[cfg(test)]
+mod flags;
+#[cfg(test)]
+mod ksy;
+#[cfg(test)]
+mod limits;
+#[cfg(test)]
+mod map;
+#[cfg(test)]
+mod nested;
+#[cfg(test)]
+mod net;
+#[cfg(test)]
+mod numeric;
+#[cfg(test)]
+mod optional;
+#[cfg(test)]
+mod primitive;
+#[cfg(test)]
+mod schema;
+#[cfg(test)]
+mod serde;
+#[cfg(test)]
+mod strict;
+#[cfg(test)]
+mod take;
+#[cfg(test)]
+mod time;
+#[cfg(test)]
+mod trace;
+#[cfg(test)]
+mod uuid;
+
 use deen::{Any, Optional, Tag, U16be, U32be, U32le, U8};
 use deen_proc::deen;
-use try_from_primitive::TryFromPrimitive;
+use try_from_primitive::{DeenEnum, TryFromPrimitive};
 
 #[derive(Debug, PartialEq)]
 pub struct Header {
@@ -9,8 +50,9 @@ pub struct Header {
     foo: Option<Foo>,
 }
 
-#[repr(u16)]
-#[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive)]
+#[repr(u32)]
+#[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive, DeenEnum)]
+#[deen(U32be)]
 enum Foo {
     Hello = 0xff00,
     World = 0x00ff,
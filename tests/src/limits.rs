@@ -0,0 +1,167 @@
+use deen::{Bcd, Bytes, DecodeLimits, Limit, LimitExceeded, U16be, Utf8, U8};
+use deen_proc::deen;
+
+mod inner {
+    use deen::U8;
+    use deen_proc::deen;
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub struct Inner {
+        pub value: u8,
+    }
+
+    deen! {
+        pub struct InnerParser for Inner {
+            value ~ U8,
+        }
+    }
+}
+
+use inner::{Inner, InnerParser};
+
+#[derive(Debug, PartialEq)]
+pub struct Outer {
+    version: u8,
+    inner: Inner,
+    serial: u64,
+}
+
+deen! {
+    struct OuterParser for Outer {
+        version ~ U8,
+        inner ~ InnerParser,
+        serial ~ Bcd::new(4),
+    }
+}
+
+fn limit(err: io::Error) -> Limit {
+    err.get_ref()
+        .and_then(|e| e.downcast_ref::<LimitExceeded>())
+        .expect("not a limit error")
+        .limit
+}
+
+const BUF: [u8; 6] = [0x01, 0x02, 0x12, 0x34, 0x56, 0x78];
+
+#[test]
+fn within_limits() {
+    let limits = DecodeLimits::new()
+        .max_total_bytes(6)
+        .max_allocation(4)
+        .max_depth(2);
+    let outer = limits.decode(&OuterParser, &BUF[..]).unwrap();
+    assert_eq!(outer.serial, 12_345_678);
+    assert_eq!(DecodeLimits::current(), None);
+}
+
+#[test]
+fn limits_exceeded() {
+    let err = DecodeLimits::new()
+        .max_total_bytes(5)
+        .decode(&OuterParser, &BUF[..])
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(limit(err), Limit::TotalBytes);
+
+    let err = DecodeLimits::new()
+        .max_allocation(3)
+        .decode(&OuterParser, &BUF[..])
+        .unwrap_err();
+    assert_eq!(limit(err), Limit::Allocation);
+
+    let err = DecodeLimits::new()
+        .max_depth(1)
+        .decode(&OuterParser, &BUF[..])
+        .unwrap_err();
+    assert_eq!(limit(err), Limit::Depth);
+    assert_eq!(
+        DecodeLimits::new()
+            .max_depth(1)
+            .decode(&OuterParser, &BUF[..])
+            .unwrap_err()
+            .to_string(),
+        "decode limit exceeded: nesting depth of 2 exceeds maximum of 1"
+    );
+}
+
+#[test]
+fn no_limits_outside_decode() {
+    assert!(DecodeLimits::check_allocation(usize::MAX).is_ok());
+    assert_eq!(U16be.decode(&mut &[0x01, 0x02][..]).unwrap(), 0x0102);
+}
+
+// Decodes with its own limits, like a deener handing part of the input to a separate decode.
+struct Nested<T>(DecodeLimits, T);
+
+impl<T: Deen> Deen for Nested<T> {
+    type Item = T::Item;
+
+    fn encode(&self, value: &T::Item, buf: impl io::Write) -> io::Result<()> {
+        self.1.encode(value, buf)
+    }
+
+    fn decode(&self, buf: impl io::Read) -> io::Result<T::Item> {
+        self.0.decode(&self.1, buf)
+    }
+}
+
+#[test]
+fn nested_decode_keeps_enclosing_limits() {
+    let nested = Nested(DecodeLimits::new().max_total_bytes(100), OuterParser);
+    let err = DecodeLimits::new()
+        .max_total_bytes(5)
+        .decode(&nested, &BUF[..])
+        .unwrap_err();
+    assert_eq!(limit(err), Limit::TotalBytes);
+
+    let nested = Nested(DecodeLimits::new(), OuterParser);
+    let err = DecodeLimits::new()
+        .max_depth(1)
+        .decode(&nested, &BUF[..])
+        .unwrap_err();
+    assert_eq!(limit(err), Limit::Depth);
+
+    // Bytes read inside count towards the enclosing decode.
+    let limits = DecodeLimits::new().max_total_bytes(1);
+    assert!(limits.decode(&Nested(DecodeLimits::new(), U8), &BUF[..]).is_ok());
+    let pair = Pair(Nested(DecodeLimits::new(), U8), U8);
+    let err = limits.decode(&pair, &BUF[..]).unwrap_err();
+    assert_eq!(limit(err), Limit::TotalBytes);
+    assert_eq!(DecodeLimits::current(), None);
+}
+
+struct Pair<A, B>(A, B);
+
+impl<A: Deen, B: Deen> Deen for Pair<A, B> {
+    type Item = (A::Item, B::Item);
+
+    fn encode(&self, value: &Self::Item, mut buf: impl io::Write) -> io::Result<()> {
+        self.0.encode(&value.0, &mut buf)?;
+        self.1.encode(&value.1, buf)
+    }
+
+    fn decode(&self, mut buf: impl io::Read) -> io::Result<Self::Item> {
+        Ok((self.0.decode(&mut buf)?, self.1.decode(buf)?))
+    }
+}
+
+#[test]
+fn reads_stop_at_limit() {
+    let input = [0u8; 100];
+    let mut rest = &input[..];
+    let err = DecodeLimits::new()
+        .max_total_bytes(5)
+        .decode(&Bytes::new(50u8), &mut rest)
+        .unwrap_err();
+    assert_eq!(limit(err), Limit::TotalBytes);
+    assert_eq!(rest.len(), 94);
+}
+
+#[test]
+fn huge_length_without_limits() {
+    let err = Bytes::new(1u64 << 40).decode(&[1, 2, 3][..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    let err = Utf8::new(u64::MAX >> 1).decode(&b"abc"[..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(Bytes::new(2u8).decode(&[1, 2, 3][..]).unwrap(), [1, 2]);
+}
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use deen::{Enum, Map, Scaled, TryMap, U16be, U32be, U64be, U8};
+use deen_proc::deen;
+use try_from_primitive::TryFromPrimitive;
+
+#[derive(Debug, PartialEq)]
+pub struct Reading {
+    uptime: Duration,
+    temperature: f64,
+    state: State,
+    ratio: u8,
+}
+
+#[repr(u8)]
+#[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive)]
+enum State {
+    Idle = 1,
+    Busy = 2,
+}
+
+deen! {
+    struct ReadingParser for Reading {
+        uptime ~ Map::new(U32be, |s| Duration::from_secs(s.into()), |d: &Duration| d.as_secs() as u32),
+        temperature ~ Scaled::new(U16be, 0.1).with_offset(-40.0),
+        state ~ Enum::<_, State>::new(U8),
+        ratio ~ TryMap::new(
+            U8,
+            |r| if r <= 100 { Ok(r) } else { Err(format!("{} is not a percentage", r)) },
+            |r: &u8| if *r <= 100 { Ok(*r) } else { Err(format!("{} is not a percentage", r)) }
+        ),
+    }
+}
+
+#[test]
+fn roundtrip() {
+    let reading = Reading {
+        uptime: Duration::from_secs(3600),
+        temperature: 21.5,
+        state: State::Busy,
+        ratio: 42,
+    };
+    let mut buf = Vec::new();
+    ReadingParser.encode(&reading, &mut buf).unwrap();
+    assert_eq!(&buf, &[0x00, 0x00, 0x0e, 0x10, 0x02, 0x67, 0x02, 0x2a]);
+
+    let decoded = ReadingParser.decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(decoded, reading);
+}
+
+#[test]
+fn decode_invalid() {
+    let buf = [0x00, 0x00, 0x0e, 0x10, 0x02, 0x67, 0x03, 0x2a];
+    let err = ReadingParser.decode(&mut &buf[..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let buf = [0x00, 0x00, 0x0e, 0x10, 0x02, 0x67, 0x02, 0x65];
+    let err = ReadingParser.decode(&mut &buf[..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn encode_out_of_range() {
+    let mut buf = Vec::new();
+    let err = Scaled::new(U8, 0.5).encode(&128.0, &mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    Scaled::new(U8, 1.0).encode(&255.0, &mut buf).unwrap();
+
+    // u64::MAX as f64 is 2^64, which must not saturate into u64::MAX.
+    let err = Scaled::new(U64be, 1.0)
+        .encode(&18446744073709551616.0, &mut buf)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,117 @@
+use deen::{U16be, U8};
+use deen_proc::deen;
+
+mod body {
+    use deen::{Tag, TryMap, U16be, U32be};
+    use deen_proc::deen;
+
+    #[derive(Debug, PartialEq, Clone)]
+    pub struct Body {
+        pub id: u16,
+        pub name: String,
+    }
+
+    deen! {
+        pub struct BodyParser(version: u8, length: u16) for Body {
+            Tag::new(U16be, length),
+            id ~ U16be,
+            name ~ if version > 1 {
+                TryMap::new(U32be, |v: u32| Ok::<_, String>(v.to_string()), |s: &String| s.parse())
+            } else {
+                TryMap::new(U16be, |v: u16| Ok::<_, String>(v.to_string()), |s: &String| s.parse())
+            }
+        }
+    }
+}
+
+use body::{Body, BodyParser};
+
+#[derive(Debug, PartialEq)]
+pub struct Record {
+    version: u8,
+    length: u16,
+    body: Body,
+}
+
+deen! {
+    struct RecordParser for Record {
+        version ~ U8,
+        length ~ U16be,
+        body ~ BodyParser { version, length },
+    }
+}
+
+fn record(version: u8) -> Record {
+    Record {
+        version,
+        length: 0x0102,
+        body: Body {
+            id: 7,
+            name: "1234".to_string(),
+        },
+    }
+}
+
+#[test]
+fn params_from_outer_fields() {
+    let mut buf = Vec::new();
+    RecordParser.encode(&record(2), &mut buf).unwrap();
+    assert_eq!(
+        buf,
+        [0x02, 0x01, 0x02, 0x01, 0x02, 0x00, 0x07, 0x00, 0x00, 0x04, 0xd2]
+    );
+    assert_eq!(RecordParser.decode(&mut buf.as_slice()).unwrap(), record(2));
+
+    let mut buf = Vec::new();
+    RecordParser.encode(&record(1), &mut buf).unwrap();
+    assert_eq!(buf, [0x01, 0x01, 0x02, 0x01, 0x02, 0x00, 0x07, 0x04, 0xd2]);
+    assert_eq!(RecordParser.decode(&mut buf.as_slice()).unwrap(), record(1));
+}
+
+#[test]
+fn params_checked_on_decode() {
+    let buf = [0x01, 0x01, 0x02, 0x01, 0x03, 0x00, 0x07, 0x04, 0xd2];
+    let err = RecordParser.decode(&mut &buf[..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn encode_error() {
+    let mut record = record(1);
+    record.body.name = "70000".to_string();
+    let err = RecordParser.to_vec(&record).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "number too large to fit in target type");
+}
+
+#[test]
+fn construct_inline() {
+    let parser = BodyParser {
+        version: 1,
+        length: 3,
+    };
+    let body = parser
+        .decode(&mut &[0x00, 0x03, 0x00, 0x01, 0x00, 0x2a][..])
+        .unwrap();
+    assert_eq!(
+        body,
+        Body {
+            id: 1,
+            name: "42".to_string(),
+        }
+    );
+}
+
+#[test]
+fn closures_are_not_reflected() {
+    let parser = BodyParser {
+        version: 2,
+        length: 0,
+    };
+    let err = deen::DynSchema::from_schema(&parser.schema()).unwrap_err();
+    assert_eq!(err.to_string(), "BodyParser.name: unsupported deener TryMap::new");
+
+    // The arguments are decoded fields, so only the source of the parser is known.
+    let err = deen::DynSchema::from_schema(&RecordParser.schema()).unwrap_err();
+    assert_eq!(err.to_string(), "RecordParser.body: unknown deener BodyParser");
+}
@@ -0,0 +1,54 @@
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4};
+
+use deen::{Eui64, Ipv4, Ipv6, Mac48, MacAddr, SocketAddrV4Be};
+use deen_proc::deen;
+
+#[derive(Debug, PartialEq)]
+pub struct Packet {
+    src: MacAddr,
+    ip: Ipv4Addr,
+    ip6: Ipv6Addr,
+    peer: SocketAddrV4,
+    id: [u8; 8],
+}
+
+deen! {
+    struct PacketParser for Packet {
+        src ~ Mac48,
+        ip ~ Ipv4,
+        ip6 ~ Ipv6,
+        peer ~ SocketAddrV4Be,
+        id ~ Eui64,
+    }
+}
+
+#[test]
+fn roundtrip() {
+    let src: MacAddr = "00:1b:21:0a:0b:0c".parse().unwrap();
+    let packet = Packet {
+        src,
+        ip: Ipv4Addr::new(192, 168, 1, 1),
+        ip6: Ipv6Addr::LOCALHOST,
+        peer: SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 2), 8080),
+        id: src.to_eui64(),
+    };
+    let mut buf = Vec::new();
+    PacketParser.encode(&packet, &mut buf).unwrap();
+    let mut expected = vec![0x00, 0x1b, 0x21, 0x0a, 0x0b, 0x0c, 192, 168, 1, 1];
+    expected.extend_from_slice(&[0; 15]);
+    expected.extend_from_slice(&[1, 10, 0, 0, 2, 0x1f, 0x90]);
+    expected.extend_from_slice(&[0x02, 0x1b, 0x21, 0xff, 0xfe, 0x0a, 0x0b, 0x0c]);
+    assert_eq!(buf, expected);
+
+    let decoded = PacketParser.decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(decoded, packet);
+}
+
+#[test]
+fn mac_addr() {
+    let mac: MacAddr = "FF-FF-FF-FF-FF-FF".parse().unwrap();
+    assert!(mac.is_broadcast());
+    assert_eq!(mac.to_string(), "ff:ff:ff:ff:ff:ff");
+    assert!("00:11:22:33:44".parse::<MacAddr>().is_err());
+    assert!("00:11:22:33:44:55:66".parse::<MacAddr>().is_err());
+}
@@ -0,0 +1,85 @@
+use deen::{AsciiNum, Bcd, Fixed, I16be, I32le};
+use deen_proc::deen;
+
+#[derive(Debug, PartialEq)]
+pub struct Record {
+    gain: f64,
+    position: f64,
+    date: u64,
+    imsi: u64,
+    size: u64,
+}
+
+deen! {
+    struct RecordParser for Record {
+        gain ~ Fixed::new(I16be, 15),
+        position ~ Fixed::new(I32le, 16),
+        date ~ Bcd::new(3),
+        imsi ~ Bcd::swapped(2),
+        size ~ AsciiNum::octal(12).nul_terminated(),
+    }
+}
+
+#[test]
+fn roundtrip() {
+    let record = Record {
+        gain: -0.5,
+        position: 1.25,
+        date: 201_231,
+        imsi: 1234,
+        size: 1024,
+    };
+    let mut buf = Vec::new();
+    RecordParser.encode(&record, &mut buf).unwrap();
+    assert_eq!(
+        &buf,
+        &[
+            0xc0, 0x00, 0x00, 0x40, 0x01, 0x00, 0x20, 0x12, 0x31, 0x21, 0x43, b'0', b'0', b'0',
+            b'0', b'0', b'0', b'0', b'2', b'0', b'0', b'0', 0x00
+        ]
+    );
+
+    let decoded = RecordParser.decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(decoded, record);
+}
+
+#[test]
+fn range_checks() {
+    let mut buf = Vec::new();
+    assert!(Fixed::new(I16be, 15).encode(&1.0, &mut buf).is_err());
+    assert!(Bcd::new(2).encode(&10_000, &mut buf).is_err());
+    assert!(AsciiNum::decimal(2).encode(&100, &mut buf).is_err());
+    assert!(buf.is_empty());
+
+    let err = Bcd::new(1).decode(&mut &[0x1a][..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn ascii_padding() {
+    let size = AsciiNum::octal(12).decode(&mut &b" 644 \0\0\0\0\0\0\0"[..]).unwrap();
+    assert_eq!(size, 0o644);
+
+    let blank = AsciiNum::octal(8).decode(&mut &[0; 8][..]).unwrap();
+    assert_eq!(blank, 0);
+    let blank = AsciiNum::decimal(3).decode(&mut &b"   "[..]).unwrap();
+    assert_eq!(blank, 0);
+}
+
+#[test]
+fn ascii_invalid() {
+    let err = AsciiNum::decimal(3).decode(&mut &b"+12"[..]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid numeric field \"+12\": sign is not allowed"
+    );
+    assert!(AsciiNum::decimal(3).decode(&mut &b"-12"[..]).is_err());
+
+    let mut buf = Vec::new();
+    let err = AsciiNum::decimal(0)
+        .nul_terminated()
+        .encode(&0, &mut buf)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(buf.is_empty());
+}
@@ -0,0 +1,113 @@
+use std::{cell::RefCell, io};
+
+use deen::{Deen, Optional, U16be};
+
+use super::{Encoder, Foo, Header};
+
+#[test]
+fn encode_checks_predicate() {
+    let encoder = Encoder { magic: 0xcafebabe };
+
+    let err = encoder
+        .encode(&Header::new(3, 0, Some(Foo::Hello)), &mut Vec::new())
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let err = encoder
+        .encode(&Header::new(3, 5, None), &mut Vec::new())
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let mut buf = Vec::new();
+    encoder.encode(&Header::new(1, 0, None), &mut buf).unwrap();
+    assert_eq!(buf, [0xca, 0xfe, 0xba, 0xbe, 0x01, 0x00, 0x00, 0x00]);
+    assert_eq!(
+        encoder.decode(&mut buf.as_slice()).unwrap(),
+        Header::new(1, 0, None)
+    );
+}
+
+#[test]
+fn check_on_encode() {
+    let seen = RefCell::new(Vec::new());
+    let opt = Optional::<u16>::wrap(U16be)
+        .decode_when(|| true)
+        .check(|v| {
+            seen.borrow_mut().push(*v);
+            *v < 0x8000
+        });
+
+    let mut buf = Vec::new();
+    opt.encode(&Some(0x1234), &mut buf).unwrap();
+    assert_eq!(buf, [0x12, 0x34]);
+    assert_eq!(opt.decode(&mut buf.as_slice()).unwrap(), Some(0x1234));
+    assert!(opt.encode(&None, &mut Vec::new()).is_err());
+
+    // Decoding does not run the check.
+    assert_eq!(opt.decode(&mut &[0xff, 0xff][..]).unwrap(), Some(0xffff));
+    let err = opt.encode(&Some(0xffff), &mut Vec::new()).unwrap_err();
+    assert_eq!(err.to_string(), "optional value fails its check");
+    assert_eq!(*seen.borrow(), [0x1234, 0xffff]);
+}
+
+mod trailing {
+    use deen::{Optional, U16be, U8};
+    use deen_proc::deen;
+
+    #[derive(Debug, PartialEq)]
+    pub struct Record {
+        pub kind: u8,
+        pub extension: Option<u16>,
+    }
+
+    deen! {
+        pub struct RecordParser for Record {
+            kind ~ U8,
+            extension ~ Optional::<u16>::wrap(U16be).if_remaining(),
+        }
+    }
+}
+
+#[test]
+fn present_if_remaining() {
+    use trailing::{Record, RecordParser};
+
+    let record = Record {
+        kind: 1,
+        extension: Some(0xabcd),
+    };
+    let mut buf = Vec::new();
+    RecordParser.encode(&record, &mut buf).unwrap();
+    assert_eq!(buf, [0x01, 0xab, 0xcd]);
+    assert_eq!(RecordParser.decode(&mut buf.as_slice()).unwrap(), record);
+
+    let record = Record {
+        kind: 2,
+        extension: None,
+    };
+    let mut buf = Vec::new();
+    RecordParser.encode(&record, &mut buf).unwrap();
+    assert_eq!(buf, [0x02]);
+    assert_eq!(RecordParser.decode(&mut buf.as_slice()).unwrap(), record);
+
+    let err = RecordParser.decode(&mut &[0x01, 0xab][..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn presence_byte() {
+    let opt = Optional::<u16>::wrap(U16be).with_presence_byte();
+
+    let mut buf = Vec::new();
+    opt.encode(&Some(0x0102), &mut buf).unwrap();
+    opt.encode(&None, &mut buf).unwrap();
+    assert_eq!(buf, [0x01, 0x01, 0x02, 0x00]);
+
+    let mut input = buf.as_slice();
+    assert_eq!(opt.decode(&mut input).unwrap(), Some(0x0102));
+    assert_eq!(opt.decode(&mut input).unwrap(), None);
+    assert!(input.is_empty());
+
+    let err = opt.decode(&mut &[0x02, 0x01, 0x02][..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
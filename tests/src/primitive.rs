@@ -0,0 +1,265 @@
+use std::{convert::TryFrom, marker::PhantomData};
+
+use deen::{Enum, Schema, TryFromPrimitiveError, U128be, U8};
+use deen_proc::deen;
+use try_from_primitive::{DeenEnum, TryFromPrimitive};
+
+use super::Foo;
+
+const BASE: u8 = 0x20;
+
+#[repr(u8)]
+#[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive)]
+enum Op {
+    Nop = 0,
+    Load,
+    Store,
+    Jump = 0x10,
+    Call,
+    Shift = 1 << 6,
+    Ret = BASE + 1,
+    Halt,
+}
+
+#[repr(i16)]
+#[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive)]
+enum Level {
+    Low = -2,
+    Mid,
+    High,
+}
+
+#[test]
+fn implicit_discriminants() {
+    assert_eq!(Op::try_from(1u8), Ok(Op::Load));
+    assert_eq!(Op::try_from(2u8), Ok(Op::Store));
+    assert_eq!(Op::try_from(0x11u8), Ok(Op::Call));
+    assert_eq!(Op::try_from(0x40u8), Ok(Op::Shift));
+    assert_eq!(Op::try_from(0x22u8), Ok(Op::Halt));
+    assert!(Op::try_from(3u8).is_err());
+    assert_eq!(u8::from(Op::Ret), 0x21);
+}
+
+#[test]
+fn negative_discriminants() {
+    assert_eq!(Level::try_from(-2i16), Ok(Level::Low));
+    assert_eq!(Level::try_from(0i16), Ok(Level::High));
+    assert_eq!(i16::from(Level::Mid), -1);
+    assert!(Level::try_from(1i16).is_err());
+}
+
+#[repr(u8)]
+#[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive)]
+enum Opcode {
+    Read = 1,
+    Write,
+    #[unknown]
+    Other(u16),
+}
+
+#[test]
+fn catch_all() {
+    assert_eq!(Opcode::from(2u16), Opcode::Write);
+    assert_eq!(Opcode::from(0x1234u16), Opcode::Other(0x1234));
+    assert_eq!(u16::from(Opcode::Read), 1);
+    assert_eq!(u16::from(Opcode::Other(7)), 7);
+}
+
+#[test]
+fn catch_all_decode() {
+    use deen::U16be;
+
+    let op = Enum::<_, Opcode>::new(U16be).decode(&mut &[0xbe, 0xef][..]).unwrap();
+    assert_eq!(op, Opcode::Other(0xbeef));
+
+    let mut buf = Vec::new();
+    Enum::<_, Opcode>::new(U16be).encode(&op, &mut buf).unwrap();
+    assert_eq!(buf, [0xbe, 0xef]);
+}
+
+#[test]
+fn typed_error() {
+    let err = Op::try_from(3u8).unwrap_err();
+    assert_eq!(*err.value(), 3);
+    assert_eq!(err.enum_name(), "Op");
+    assert_eq!(err.to_string(), "3 is not a valid Op value");
+
+    let err = io::Error::from(Level::try_from(5i16).unwrap_err());
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let inner = err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<TryFromPrimitiveError<Level, i16>>())
+        .unwrap();
+    assert_eq!(inner.into_value(), 5);
+
+    assert_eq!(u8::from(Op::Call), 0x11);
+    assert_eq!(i16::from(Level::High), 0);
+}
+
+mod errors {
+    pub use deen::{Enum, TryFromPrimitiveError};
+}
+
+#[repr(u8)]
+#[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive, DeenEnum)]
+#[try_from_primitive(crate = "self::errors")]
+#[deen(U8)]
+enum Color {
+    Red = 1,
+}
+
+#[test]
+fn error_crate_path() {
+    assert_eq!(Color::try_from(1u8), Ok(Color::Red));
+    let err: errors::TryFromPrimitiveError<Color, u8> = Color::try_from(2u8).unwrap_err();
+    assert_eq!(err.to_string(), "2 is not a valid Color value");
+    let deener: errors::Enum<U8, Color> = Color::deen();
+    assert_eq!(deener.decode_exact(&[1]).unwrap(), Color::Red);
+}
+
+#[repr(align(2), u8)]
+#[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive, DeenEnum)]
+#[deen(U8)]
+enum Kind {
+    File = 1,
+    Dir,
+}
+
+#[repr(usize)]
+#[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive)]
+enum Slot {
+    First,
+    Second,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive)]
+enum Mode {
+    On = 1,
+    Off = 2,
+}
+
+#[test]
+fn repr_parsing() {
+    assert_eq!(Kind::try_from(2u8), Ok(Kind::Dir));
+    assert_eq!(u8::from(Kind::File), 1);
+    assert_eq!(Slot::try_from(1usize), Ok(Slot::Second));
+    assert_eq!(usize::from(Slot::First), 0);
+    assert_eq!(Mode::try_from(2i64), Ok(Mode::Off));
+    assert_eq!(u32::try_from(Mode::On), Ok(1));
+}
+
+// Whether `E: TryFrom<P>`, answered without failing to compile when it does not hold: the
+// method of `Convertible` is found first if its bound holds, otherwise the autoref one.
+struct Probe<E, P>(PhantomData<(E, P)>);
+
+trait Convertible {
+    fn convertible(&self) -> bool {
+        true
+    }
+}
+
+impl<E: TryFrom<P>, P> Convertible for Probe<E, P> {}
+
+trait NotConvertible {
+    fn convertible(&self) -> bool {
+        false
+    }
+}
+
+impl<E, P> NotConvertible for &Probe<E, P> {}
+
+macro_rules! try_from {
+    ($e:ty: $($p:ty),*) => {
+        [$((&Probe::<$e, $p>(PhantomData)).convertible()),*]
+    };
+}
+
+#[test]
+fn conversion_widths() {
+    // Only the repr type, and the types of the original derive without one.
+    assert_eq!(try_from!(Op: u8, i8, u16, i128), [true, false, false, false]);
+    assert_eq!(try_from!(Level: i16, u16, i32), [true, false, false]);
+    assert_eq!(
+        try_from!(Mode: i32, u32, i64, u64, u128, i128, isize),
+        [false, true, true, true, true, true, false]
+    );
+    assert_eq!(try_from!(u8: Op, Level), [true, false]);
+    assert_eq!(try_from!(u32: Opcode, Op), [false, false]);
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    kind: Kind,
+    foo: Foo,
+}
+
+deen! {
+    struct EntryParser for Entry {
+        kind ~ Kind::deen(),
+        foo ~ Foo::deen(),
+    }
+}
+
+#[test]
+fn enum_deener() {
+    let entry = Entry {
+        kind: Kind::Dir,
+        foo: Foo::World,
+    };
+    let mut buf = Vec::new();
+    EntryParser.encode(&entry, &mut buf).unwrap();
+    assert_eq!(buf, [0x02, 0x00, 0x00, 0x00, 0xff]);
+    assert_eq!(EntryParser.decode(&mut buf.as_slice()).unwrap(), entry);
+
+    let err = EntryParser
+        .decode(&mut &[0x03, 0x00, 0x00, 0x00, 0xff][..])
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[repr(u128)]
+#[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive, DeenEnum)]
+#[deen(U128be)]
+enum Wide {
+    Small = 1,
+    Huge = u128::MAX,
+}
+
+#[derive(Clone)]
+struct Tagged<T>(u8, PhantomData<T>);
+
+impl<T> From<u8> for Tagged<T> {
+    fn from(v: u8) -> Tagged<T> {
+        Tagged(v, PhantomData)
+    }
+}
+
+impl<T> From<Tagged<T>> for u8 {
+    fn from(t: Tagged<T>) -> u8 {
+        t.0
+    }
+}
+
+fn enum_schema(schema: Schema) -> (String, Vec<(String, i128)>) {
+    match schema {
+        Schema::Enum { name, variants, .. } => (name, variants),
+        s => panic!("not an enum: {:?}", s),
+    }
+}
+
+#[test]
+fn enum_schema_names() {
+    let (name, variants) = enum_schema(Kind::deen().schema());
+    assert_eq!(name, "Kind");
+    assert_eq!(variants, [("File".to_string(), 1), ("Dir".to_string(), 2)]);
+
+    // Variants above `i128::MAX` do not fit the schema.
+    let (name, variants) = enum_schema(Wide::deen().schema());
+    assert_eq!(name, "Wide");
+    assert!(variants.is_empty());
+    assert_eq!(Wide::deen().decode_exact(&[0xff; 16]).unwrap(), Wide::Huge);
+
+    let (name, _) = enum_schema(Enum::<_, Tagged<Kind>>::new(U8).schema());
+    assert_eq!(name, "Tagged<Kind>");
+}
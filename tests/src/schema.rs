@@ -0,0 +1,209 @@
+use std::net::Ipv4Addr;
+
+use deen::{Endian, Ipv4, Padding, Schema, Tag, U16be, U16le, U8};
+use deen_proc::deen;
+
+use super::Encoder;
+
+#[derive(Debug, PartialEq)]
+pub struct Packet {
+    kind: u8,
+    addr: Ipv4Addr,
+    flags: u16,
+}
+
+deen! {
+    struct PacketParser(magic: u16) for Packet {
+        Tag::new(U16be, magic),
+        kind ~ U8,
+        Padding::new(3),
+        addr ~ Ipv4,
+        flags ~ if kind > 1 {
+            U16le
+        } else {
+            U16be
+        }
+    }
+}
+
+#[test]
+fn display() {
+    let schema = PacketParser { magic: 0x1234 }.schema();
+    assert_eq!(
+        schema.to_string(),
+        "\
+Packet {
+    tag u16be = 4660
+    kind: u8
+    padding 3
+    addr: Ipv4
+    flags: if kind > 1 {
+        flags: u16le
+    } else {
+        flags: u16be
+    }
+}"
+    );
+    assert_eq!(schema.size(), Some(12));
+}
+
+#[test]
+fn fields() {
+    let schema = Encoder { magic: 0xcafebabe }.schema();
+    let fields = match &schema {
+        Schema::Struct {
+            name,
+            parser,
+            params,
+            fields,
+        } if name == "Header" => {
+            assert_eq!(parser, "Encoder");
+            assert_eq!(params, &[("magic".to_string(), "u32".to_string())]);
+            fields
+        }
+        _ => panic!("not a struct: {:?}", schema),
+    };
+    let names: Vec<_> = fields.iter().map(|f| f.name.as_deref()).collect();
+    assert_eq!(
+        names,
+        [None, Some("version"), None, Some("length"), Some("foo")]
+    );
+    assert_eq!(fields[0].source, "Tag::new(U32be, magic)");
+    assert_eq!(
+        fields[0].schema,
+        Schema::Tag {
+            inner: Box::new(Schema::int(false, 32, Endian::Big)),
+            value: "3405691582".to_string(),
+        }
+    );
+    assert_eq!(fields[3].schema.size(), Some(2));
+
+    let branches = match &fields[4].schema {
+        Schema::Conditional(branches) => branches,
+        s => panic!("not conditional: {:?}", s),
+    };
+    let conditions: Vec<_> = branches.iter().map(|b| b.condition.as_deref()).collect();
+    assert_eq!(conditions, [Some("version > 2"), Some("version > 1"), None]);
+    let last = &branches[2].fields[0];
+    assert_eq!(
+        last.source,
+        "Optional::<Foo>::wrap(U32le).decode_when(|| length > 0)"
+    );
+    // The deener refers to the decoded `length`, so only its source is known.
+    assert_eq!(last.schema, Schema::Opaque(last.source.clone()));
+    assert_eq!(schema.size(), None);
+}
+
+#[test]
+fn diff_versions() {
+    let v1 = PacketParser { magic: 1 }.schema();
+    assert_eq!(v1, PacketParser { magic: 1 }.schema());
+    assert_ne!(v1, PacketParser { magic: 2 }.schema());
+}
+
+mod dependent {
+    use deen::{Enum, Optional, Take, U16be, U8};
+    use deen_proc::deen;
+    use try_from_primitive::TryFromPrimitive;
+
+    // No `Default`, since nothing is built from the fields that deeners refer to.
+    #[repr(u8)]
+    #[derive(Debug, PartialEq, Copy, Clone, TryFromPrimitive)]
+    pub enum Kind {
+        A = 1,
+        B = 2,
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub struct Message {
+        pub kind: Kind,
+        pub len: u8,
+        pub body: Option<u16>,
+    }
+
+    deen! {
+        pub struct MessageParser for Message {
+            kind ~ Enum::<_, Kind>::new(U8),
+            len ~ U8,
+            body ~ Take::new(len, Optional::<u16>::wrap(U16be).decode_when(|| kind == Kind::A)),
+        }
+    }
+}
+
+#[test]
+fn field_dependent() {
+    use dependent::*;
+
+    let message = Message {
+        kind: Kind::A,
+        len: 2,
+        body: Some(7),
+    };
+    let buf = MessageParser.to_vec(&message).unwrap();
+    assert_eq!(buf, [1, 2, 0, 7]);
+    assert_eq!(MessageParser.decode_exact(&buf).unwrap(), message);
+
+    let fields = match MessageParser.schema() {
+        Schema::Struct { fields, .. } => fields,
+        s => panic!("not a struct: {:?}", s),
+    };
+    assert_eq!(fields[1].schema, Schema::int(false, 8, Endian::Big));
+    assert_eq!(
+        fields[2].schema,
+        Schema::Opaque(
+            "Take::new(len, Optional::<u16>::wrap(U16be).decode_when(|| kind == Kind::A))"
+                .to_string()
+        )
+    );
+}
+
+struct Custom;
+
+impl Deen for Custom {
+    type Item = ();
+
+    fn encode(&self, _: &(), _: impl io::Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn decode(&self, _: impl io::Read) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn opaque() {
+    match Custom.schema() {
+        Schema::Opaque(name) => assert!(name.ends_with("schema::Custom")),
+        s => panic!("not opaque: {:?}", s),
+    }
+}
+
+#[test]
+fn size_overflow() {
+    let list = |count| Schema::List {
+        count: Some(count),
+        inner: Box::new(Schema::Padding(1 << 32)),
+    };
+    assert_eq!(list(1 << 31).size(), Some(1 << 63));
+    assert_eq!(list(1 << 32).size(), None);
+
+    let padding = Schema::Padding(u64::MAX);
+    assert_eq!(padding.size(), Some(u64::MAX));
+    let field = |schema: Schema| deen::Field {
+        name: None,
+        source: String::new(),
+        schema,
+    };
+    let fields = vec![field(padding.clone()), field(Schema::Padding(1))];
+    assert_eq!(
+        Schema::Struct {
+            name: String::new(),
+            parser: String::new(),
+            params: Vec::new(),
+            fields,
+        }
+        .size(),
+        None
+    );
+}
@@ -0,0 +1,150 @@
+use std::{collections::BTreeMap, net::IpAddr, ops::Range, time::Duration};
+
+use deen::{Canonical, DecodeLimits, Endian, Limit, LimitExceeded, Serde, U16le, VarU64, U8};
+use deen_proc::deen;
+use serde::{Deserialize, Serialize};
+
+type Record = (u16, String, Vec<i32>, Option<bool>, char);
+
+fn record() -> Record {
+    (0x1234, "hi".to_string(), vec![-1, 2], Some(true), 'é')
+}
+
+#[test]
+fn fixed_width() {
+    let deener = Serde::<Record>::new();
+    let buf = deener.to_vec(&record()).unwrap();
+    assert_eq!(
+        buf,
+        [0x12, 0x34, 2, b'h', b'i', 2, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 2, 1, 1, 0, 0, 0, 0xe9]
+    );
+    assert_eq!(deener.decode_exact(&buf).unwrap(), record());
+}
+
+#[test]
+fn varint_and_prefixes() {
+    let deener = Serde::<Record>::new()
+        .endian(Endian::Little)
+        .varint()
+        .str_len(U8)
+        .seq_len(U16le);
+    let buf = deener.to_vec(&record()).unwrap();
+    assert_eq!(buf, [0xb4, 0x24, 2, b'h', b'i', 2, 0, 1, 4, 1, 1, 0xe9, 1]);
+    assert_eq!(deener.decode_exact(&buf).unwrap(), record());
+
+    let long = (0, "x".repeat(256), Vec::new(), None, 'x');
+    let err = deener.to_vec(&long).unwrap_err();
+    assert_eq!(err.to_string(), "length 256 does not fit the prefix");
+}
+
+#[test]
+fn structs_enums_and_maps() {
+    let deener = Serde::<(Duration, Range<u8>, Vec<IpAddr>, Result<(), String>)>::new();
+    let value = (
+        Duration::new(5, 1),
+        1..3,
+        vec!["10.0.0.1".parse().unwrap()],
+        Err("no".to_string()),
+    );
+    let buf = deener.to_vec(&value).unwrap();
+    assert_eq!(
+        buf,
+        [
+            0, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0, 1, 1, 3, 1, 0, 0, 0, 0, 10, 0, 0, 1, 0, 0, 0, 1, 2,
+            b'n', b'o',
+        ]
+    );
+    assert_eq!(deener.decode_exact(&buf).unwrap(), value);
+
+    let deener = Serde::<BTreeMap<String, f64>>::new().endian(Endian::Little);
+    let map: BTreeMap<_, _> = vec![("a".to_string(), 1.5)].into_iter().collect();
+    let buf = deener.to_vec(&map).unwrap();
+    assert_eq!(buf, [1, 1, b'a', 0, 0, 0, 0, 0, 0, 0xf8, 0x3f]);
+    assert_eq!(deener.decode_exact(&buf).unwrap(), map);
+}
+
+#[test]
+fn errors_and_limits() {
+    let deener = Serde::<Option<bool>>::new();
+    let err = deener.decode_exact(&[1, 2]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "0x02 is not a valid bool");
+    let err = deener.decode_exact(&[1]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+    let err = Serde::<u16>::new()
+        .varint()
+        .decode_exact(&[0x80, 0x80, 0x04])
+        .unwrap_err();
+    assert_eq!(err.to_string(), "65536 is out of range of u16");
+    let err = Canonical::new(Serde::<u16>::new().varint())
+        .decode_exact(&[0x81, 0x00])
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "non-canonical varint: 1 encoded in 2 bytes"
+    );
+
+    let deener = Serde::<Vec<String>>::new();
+    let err = DecodeLimits::new()
+        .max_elements(2)
+        .decode(&deener, &[3, 0, 0, 0][..])
+        .unwrap_err();
+    let limit = err.get_ref().unwrap().downcast_ref::<LimitExceeded>();
+    assert_eq!(limit.map(|l| l.limit), Some(Limit::Elements));
+    let err = DecodeLimits::new()
+        .max_allocation(16)
+        .decode(&deener, &[1, 0x80, 0x01][..])
+        .unwrap_err();
+    let limit = err.get_ref().unwrap().downcast_ref::<LimitExceeded>();
+    assert_eq!(limit.map(|l| l.limit), Some(Limit::Allocation));
+
+    let err = Serde::<String>::new()
+        .decode(&[0xff, 0xff, 0xff, 0xff, 0x0f, 1, 2][..])
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Nested(Option<Box<Nested>>);
+
+#[test]
+fn nesting_depth() {
+    let deener = Serde::<Nested>::new();
+    let buf = [1, 1, 1, 0];
+    assert!(deener.decode_exact(&buf).is_ok());
+    let err = DecodeLimits::new()
+        .max_depth(4)
+        .decode(&deener, &buf[..])
+        .unwrap_err();
+    let limit = err.get_ref().unwrap().downcast_ref::<LimitExceeded>();
+    assert_eq!(limit.map(|l| l.limit), Some(Limit::Depth));
+    assert!(DecodeLimits::new()
+        .max_depth(8)
+        .decode(&deener, &buf[..])
+        .is_ok());
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    kind: u8,
+    body: BTreeMap<u16, String>,
+}
+
+deen! {
+    struct MessageParser for Message {
+        kind ~ U8,
+        body ~ Serde::<BTreeMap<u16, String>>::new().seq_len(U8).str_len(VarU64),
+    }
+}
+
+#[test]
+fn in_parser() {
+    let message = Message {
+        kind: 7,
+        body: vec![(1, "one".to_string())].into_iter().collect(),
+    };
+    let buf = MessageParser.to_vec(&message).unwrap();
+    assert_eq!(buf, [7, 1, 0, 1, 3, b'o', b'n', b'e']);
+    assert_eq!(MessageParser.decode_exact(&buf).unwrap(), message);
+}
@@ -0,0 +1,60 @@
+use deen::{from_bytes, to_vec, Any, Canonical, Padding, Tag, VarI64, VarU64, U8};
+use deen_proc::deen;
+
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    id: u64,
+    delta: i64,
+}
+
+deen! {
+    struct MessageParser for Message {
+        Tag::new(U8, 0x01),
+        id ~ VarU64,
+        Any::new(U8),
+        delta ~ VarI64,
+        Padding::new(2),
+    }
+}
+
+#[test]
+fn exact() {
+    let message = Message { id: 300, delta: -3 };
+    let buf = to_vec(&MessageParser, &message).unwrap();
+    assert_eq!(buf, [0x01, 0xac, 0x02, 0x00, 0x05, 0x00, 0x00]);
+    assert_eq!(from_bytes(&MessageParser, &buf).unwrap(), message);
+
+    let mut trailing = buf.clone();
+    trailing.push(0xff);
+    let err = MessageParser.decode_exact(&trailing).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "1 trailing bytes after decoded value");
+}
+
+#[test]
+fn canonical() {
+    let canonical = [0x01, 0xac, 0x02, 0x00, 0x05, 0x00, 0x00];
+    assert!(Canonical::new(MessageParser).decode_exact(&canonical).is_ok());
+
+    let non_canonical: [&[u8]; 3] = [
+        &[0x01, 0xac, 0x82, 0x00, 0x00, 0x05, 0x00, 0x00],
+        &[0x01, 0xac, 0x02, 0x07, 0x05, 0x00, 0x00],
+        &[0x01, 0xac, 0x02, 0x00, 0x05, 0x00, 0x01],
+    ];
+    for buf in non_canonical.iter() {
+        assert!(MessageParser.decode_exact(buf).is_ok());
+        let err = Canonical::new(MessageParser).decode_exact(buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+    assert!(!deen::is_canonical());
+}
+
+#[test]
+fn varint_overflow() {
+    let buf = [0xff; 11];
+    assert!(VarU64.decode(&mut &buf[..]).is_err());
+    let max = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+    assert_eq!(VarU64.decode(&mut &max[..]).unwrap(), u64::MAX);
+    assert_eq!(to_vec(&VarU64, &u64::MAX).unwrap(), max);
+    assert_eq!(to_vec(&VarI64, &i64::MIN).unwrap(), max);
+}
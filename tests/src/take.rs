@@ -0,0 +1,94 @@
+use deen::{Canonical, Take, U16be, U32be, U8};
+use deen_proc::deen;
+
+mod body {
+    use deen::{U16be, U8};
+    use deen_proc::deen;
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub struct Body {
+        pub id: u16,
+        pub flags: u8,
+    }
+
+    deen! {
+        pub struct BodyParser for Body {
+            id ~ U16be,
+            flags ~ U8,
+        }
+    }
+}
+
+use body::{Body, BodyParser};
+
+#[derive(Debug, PartialEq)]
+pub struct Chunk {
+    kind: u8,
+    length: u16,
+    body: Body,
+    trailer: Body,
+    value: u32,
+}
+
+deen! {
+    struct ChunkParser for Chunk {
+        kind ~ U8,
+        length ~ U16be,
+        body ~ Take::new(length, BodyParser).skip_remaining(),
+        trailer ~ Take::prefixed(U8, BodyParser),
+        value ~ Take::new(4u8, U32be),
+    }
+}
+
+fn chunk(length: u16) -> Chunk {
+    Chunk {
+        kind: 1,
+        length,
+        body: Body {
+            id: 0x0102,
+            flags: 3,
+        },
+        trailer: Body {
+            id: 0x0405,
+            flags: 6,
+        },
+        value: 7,
+    }
+}
+
+#[test]
+fn roundtrip() {
+    let buf = ChunkParser.to_vec(&chunk(5)).unwrap();
+    assert_eq!(buf, [1, 0, 5, 1, 2, 3, 0, 0, 3, 4, 5, 6, 0, 0, 0, 7]);
+    assert_eq!(ChunkParser.decode_exact(&buf).unwrap(), chunk(5));
+}
+
+#[test]
+fn under_consumed() {
+    let buf = [3, 1, 2, 3, 0];
+    assert!(Take::prefixed(U8, BodyParser)
+        .decode_exact(&buf[..4])
+        .is_ok());
+    let err = Take::new(4u8, BodyParser)
+        .decode_exact(&buf[1..])
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let skip = Take::new(4u8, BodyParser).skip_remaining();
+    assert!(skip.decode_exact(&buf[1..]).is_ok());
+    assert!(Canonical::new(skip).decode_exact(&[1, 2, 3, 9]).is_err());
+}
+
+#[test]
+fn over_consumed() {
+    let err = Take::new(2u8, BodyParser)
+        .decode_exact(&[1, 2, 3])
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn length_mismatch_on_encode() {
+    let err = ChunkParser.encode(&chunk(2), Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
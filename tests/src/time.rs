@@ -0,0 +1,99 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use deen::{
+    DosDateTime, FileTime, GpsTime, I32le, NtpDate, NtpTimestamp, U32be, U64be, UnixTime,
+};
+use deen_proc::deen;
+
+#[derive(Debug, PartialEq)]
+pub struct Times {
+    unix: SystemTime,
+    millis: SystemTime,
+    ntp: SystemTime,
+    filetime: SystemTime,
+    dos: SystemTime,
+    gps: SystemTime,
+}
+
+deen! {
+    struct TimesParser for Times {
+        unix ~ UnixTime::seconds(I32le),
+        millis ~ UnixTime::millis(U64be),
+        ntp ~ NtpTimestamp,
+        filetime ~ FileTime,
+        dos ~ DosDateTime,
+        gps ~ GpsTime::seconds(U32be),
+    }
+}
+
+fn time(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+#[test]
+fn roundtrip() {
+    let t = time(1_600_000_000);
+    let times = Times {
+        unix: t,
+        millis: t + Duration::from_millis(5),
+        ntp: t + Duration::from_millis(500),
+        filetime: UNIX_EPOCH,
+        dos: t,
+        gps: t,
+    };
+    let mut buf = Vec::new();
+    TimesParser.encode(&times, &mut buf).unwrap();
+    assert_eq!(
+        &buf,
+        &[
+            0x00, 0x10, 0x5e, 0x5f, // unix
+            0x00, 0x00, 0x01, 0x74, 0x87, 0x6e, 0x80, 0x05, // millis
+            0xe3, 0x08, 0x8e, 0x80, 0x80, 0x00, 0x00, 0x00, // ntp
+            0x00, 0x80, 0x3e, 0xd5, 0xde, 0xb1, 0x9d, 0x01, // filetime
+            0x54, 0x63, 0x2d, 0x51, // dos
+            0x4c, 0x88, 0xd2, 0x92, // gps
+        ][..]
+    );
+
+    let decoded = TimesParser.decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(decoded, times);
+}
+
+#[test]
+fn before_epoch() {
+    let t = UNIX_EPOCH - Duration::from_secs(86_400);
+    let mut buf = Vec::new();
+    UnixTime::seconds(I32le).encode(&t, &mut buf).unwrap();
+    assert_eq!(UnixTime::seconds(I32le).decode(&mut buf.as_slice()).unwrap(), t);
+
+    let err = UnixTime::seconds(U32be).encode(&t, &mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let err = DosDateTime.encode(&t, &mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn dos_invalid_day() {
+    // 2020-02-29 exists, 2021-02-29 and 2021-04-31 do not.
+    let leap = DosDateTime.decode(&mut &[0, 0, 0x5d, 0x50][..]).unwrap();
+    assert_eq!(leap, time(1_582_934_400));
+    for date in &[[0x5d, 0x52], [0x9f, 0x52]] {
+        let err = DosDateTime
+            .decode(&mut &[0, 0, date[0], date[1]][..])
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
+
+#[test]
+fn ntp_eras() {
+    let t = time(2_200_000_000);
+    let mut buf = Vec::new();
+    NtpTimestamp.encode(&t, &mut buf).unwrap();
+    assert_eq!(NtpTimestamp.decode(&mut buf.as_slice()).unwrap(), t);
+
+    buf.clear();
+    NtpDate.encode(&t, &mut buf).unwrap();
+    assert_eq!(&buf[..4], &[0x00, 0x00, 0x00, 0x01]);
+    assert_eq!(NtpDate.decode(&mut buf.as_slice()).unwrap(), t);
+}
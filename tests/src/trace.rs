@@ -0,0 +1,121 @@
+use std::io;
+
+use deen::{Trace, TraceEntry};
+
+use super::{Encoder, Foo, Header};
+
+fn entry(label: &str, depth: usize, start: u64, end: u64, value: Option<&str>) -> TraceEntry {
+    TraceEntry {
+        label: label.to_string(),
+        depth,
+        start,
+        end,
+        value: value.map(str::to_string),
+        failed: false,
+    }
+}
+
+#[test]
+fn trace_entries() {
+    let buf = [
+        0xca, 0xfe, 0xba, 0xbe, 0x03, 0x00, 0x05, 0x42, 0xff, 0x00, 0x00, 0xff, 0x00,
+    ];
+    let (result, trace) = Trace::decode(&Encoder { magic: 0xcafebabe }, &buf);
+    assert_eq!(result.unwrap(), Header::new(3, 0x542, Some(Foo::Hello)));
+    assert_eq!(trace.decoded, 13);
+    assert_eq!(trace.error, None);
+    assert_eq!(
+        trace.entries,
+        [
+            entry("Tag::new(U32be, magic)", 0, 0, 4, None),
+            entry("version", 0, 4, 5, Some("3")),
+            entry("Any::new(U8)", 0, 5, 6, None),
+            entry("length", 0, 6, 8, Some("1346")),
+            entry("Tag::new(U8, 0xff)", 0, 8, 9, None),
+            entry("foo", 0, 9, 13, Some("Some(Hello)")),
+        ]
+    );
+}
+
+#[test]
+fn hexdump_of_failure() {
+    let buf = [
+        0xca, 0xfe, 0xba, 0xbe, 0x03, 0x00, 0x05, 0x42, 0x00, 0x00, 0x09, 0x45,
+    ];
+    let (result, trace) = Trace::decode(&Encoder { magic: 0xcafebabe }, &buf);
+    assert!(result.is_err());
+    assert!(trace.entries[4].failed);
+    assert_eq!(
+        trace.to_string(),
+        format!(
+            "\
+00000000  ca fe ba be{0:37}  ....{0:12}  Tag::new(U32be, magic)
+00000004  03{0:46}  .{0:15}  version = 3
+00000005  00{0:46}  .{0:15}  Any::new(U8)
+00000006  05 42{0:43}  .B{0:14}  length = 1346
+00000008  00{0:46}  .{0:15}  Tag::new(U8, 0xff)  <-- failed here
+error at 0x9: unexpected tag - expected: 255, found: 0
+00000009  00 09 45{0:40}  ..E{0:13}  (not decoded)
+",
+            ""
+        )
+    );
+}
+
+mod inner {
+    use deen::U16be;
+    use deen_proc::deen;
+
+    #[derive(Debug, PartialEq, Clone)]
+    pub struct Inner {
+        pub id: u16,
+    }
+
+    deen! {
+        pub struct InnerParser for Inner {
+            id ~ U16be,
+        }
+    }
+}
+
+mod outer {
+    use deen::U8;
+    use deen_proc::deen;
+
+    use super::inner::{Inner, InnerParser};
+
+    #[derive(Debug, PartialEq)]
+    pub struct Outer {
+        pub kind: u8,
+        pub inner: Inner,
+    }
+
+    deen! {
+        pub struct OuterParser for Outer {
+            kind ~ U8,
+            inner ~ InnerParser,
+        }
+    }
+}
+
+#[test]
+fn nested_entries() {
+    let (result, trace) = Trace::decode(&outer::OuterParser, &[0x01, 0x00]);
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(
+        trace.entries,
+        [
+            entry("kind", 0, 0, 1, Some("1")),
+            TraceEntry {
+                failed: true,
+                ..entry("inner", 0, 1, 2, None)
+            },
+            TraceEntry {
+                failed: true,
+                ..entry("id", 1, 1, 2, None)
+            },
+        ]
+    );
+    assert!(trace.to_string().contains("  id  <-- failed here\n"));
+    assert!(!trace.to_string().contains("inner  <-- failed here"));
+}
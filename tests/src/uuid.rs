@@ -0,0 +1,46 @@
+use deen::{Guid, Uuid, UuidBe};
+use deen_proc::deen;
+
+#[derive(Debug, PartialEq)]
+pub struct PartitionEntry {
+    type_guid: Uuid,
+    unique: Uuid,
+}
+
+deen! {
+    struct PartitionEntryParser for PartitionEntry {
+        type_guid ~ Guid,
+        unique ~ UuidBe,
+    }
+}
+
+#[test]
+fn gpt_entry() {
+    // EFI System Partition type GUID as stored on disk.
+    let esp = [
+        0x28, 0x73, 0x2a, 0xc1, 0x1f, 0xf8, 0xd2, 0x11, 0xba, 0x4b, 0x00, 0xa0, 0xc9, 0x3e, 0xc9,
+        0x3b,
+    ];
+    let mut buf = esp.to_vec();
+    buf.extend_from_slice(&esp);
+
+    let entry = PartitionEntryParser.decode(&mut buf.as_slice()).unwrap();
+    assert_eq!(
+        entry.type_guid.to_string(),
+        "c12a7328-f81f-11d2-ba4b-00a0c93ec93b"
+    );
+    assert_eq!(entry.unique.to_string(), "28732ac1-1ff8-d211-ba4b-00a0c93ec93b");
+
+    let mut encoded = Vec::new();
+    PartitionEntryParser.encode(&entry, &mut encoded).unwrap();
+    assert_eq!(encoded, buf);
+}
+
+#[test]
+fn parse() {
+    let uuid: Uuid = "{C12A7328-F81F-11D2-BA4B-00A0C93EC93B}".parse().unwrap();
+    assert_eq!(uuid.version(), 1);
+    assert_eq!(uuid.to_string(), "c12a7328-f81f-11d2-ba4b-00a0c93ec93b");
+    assert!("c12a7328-f81f11d2-ba4b-00a0c93ec93b".parse::<Uuid>().is_err());
+    assert!("c12a7328-f81f-11d2-ba4b-00a0c93ec93g".parse::<Uuid>().is_err());
+}
@@ -5,147 +5,517 @@ extern crate proc_macro;
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Ident, Meta, NestedMeta};
+use syn::{
+    parenthesized,
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+    spanned::Spanned,
+    Attribute, Data, DataEnum, DeriveInput, Fields, Ident, LitStr, Meta, NestedMeta, Path, Token,
+    Type, Variant,
+};
 
-#[proc_macro_derive(TryFromPrimitive)]
+/// Implements conversions between a fieldless enum and primitive integers. Conversions into the
+/// enum fail with `deen::TryFromPrimitiveError`; crates that get the error type from elsewhere
+/// name the path containing it with `#[try_from_primitive(crate = "path")]`. Conversions into
+/// integers that can hold every discriminant are infallible `From` impls.
+///
+/// Conversions are generated for the repr type only, or for the field type of the `#[unknown]`
+/// variant. Enums without a repr keep the conversions from `u32`, `u64`, `u128`, `i64` and
+/// `i128`.
+#[proc_macro_derive(TryFromPrimitive, attributes(unknown, try_from_primitive))]
 pub fn my_macro(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let ty = input
-        .attrs
-        .iter()
-        .filter_map(|a| match a.parse_meta() {
-            Ok(m) => Some(m),
-            Err(_) => None,
-        })
-        .find(|m| m.name() == "repr")
-        .map(|m| match m {
-            Meta::List(l) => {
-                let ty = match l.nested.first() {
-                    Some(t) => match t.value() {
-                        NestedMeta::Meta(m) => match m {
-                            Meta::Word(i) => i.to_string(),
-                            _ => panic!("wrong content of \"repr\" attribute"),
-                        },
-                        _ => panic!("wrong content of \"repr\" attribute"),
-                    },
-                    _ => panic!("wrong content of \"repr\" attribute"),
-                };
-                let (s, bits) = ty.split_at(1);
-                (
-                    s.to_string(),
-                    bits.parse().expect("wrong content of \"repr\" attribute"),
-                )
-            }
-            _ => panic!("wrong content of \"repr\" attribute"),
-        });
-    let ty_bits = ty
-        .as_ref()
-        .map(|(v, i)| {
-            let dec = if v == "i" { 1 } else { 0 };
-            i - dec
-        })
-        .unwrap_or(32);
+    match try_from_primitive(input) {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn try_from_primitive(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let krate = crate_path(&input.attrs)?;
+    let repr = repr_type(&input.attrs)?;
+    let repr_name = repr.as_ref().map(Ident::to_string);
+    let repr = repr.unwrap_or_else(|| Ident::new("isize", Span::call_site()));
 
     let ident = input.ident;
     let data = match input.data {
         Data::Enum(data) => data,
         Data::Struct(data) => {
-            return syn::Error::new(data.struct_token.span, "Can only derive primitive enums")
-                .to_compile_error()
-                .into();
+            return Err(syn::Error::new(
+                data.struct_token.span,
+                "Can only derive primitive enums",
+            ));
         }
         Data::Union(data) => {
-            return syn::Error::new(data.union_token.span, "Can only derive primitive enums")
-                .to_compile_error()
-                .into();
+            return Err(syn::Error::new(
+                data.union_token.span,
+                "Can only derive primitive enums",
+            ));
         }
     };
 
-    let has_discriminant = if data.variants.iter().all(|v| v.discriminant.is_some()) {
-        true
-    } else if data.variants.iter().all(|v| v.discriminant.is_none()) {
-        false
-    } else {
-        unimplemented!();
+    // With a catch-all variant, values are those of its field rather than of the repr.
+    let catch_all = catch_all_variant(&data)?;
+    let value_ty = match &catch_all {
+        Some((_, ty)) => ty.clone(),
+        None => repr.clone(),
+    };
+    let conversion_types = match (&catch_all, &repr_name) {
+        (Some((_, ty)), _) => vec![ty.to_string()],
+        (None, Some(repr)) => vec![repr.clone()],
+        (None, None) => ["u32", "u64", "u128", "i64", "i128"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect(),
     };
 
-    let variants_from = data
+    // Discriminants follow Rust's own rule: an implicit one is the previous plus one, starting
+    // from zero. They are emitted as typed constants so that negative values and constant
+    // expressions are evaluated by the compiler and can be used as match patterns.
+    let consts = data
         .variants
         .iter()
         .enumerate()
         .map(|(i, variant)| {
-            let name = &variant.ident;
-            let value = if has_discriminant {
-                let v = &variant.discriminant.as_ref().unwrap().1;
-                quote! { #v }
+            let name = discriminant_ident(i);
+            let value = match &variant.discriminant {
+                Some((_, v)) => quote! { #v },
+                None if i == 0 => quote! { 0 },
+                None => {
+                    let prev = discriminant_ident(i - 1);
+                    quote! { #prev + 1 }
+                }
+            };
+            let allow = if is_catch_all(variant) {
+                quote! { #[allow(dead_code)] }
             } else {
-                quote! { #i }
+                quote! {}
             };
             quote! {
-                #value => Ok(#ident::#name),
+                #allow
+                const #name: #value_ty = #value;
             }
         })
         .collect::<Vec<_>>();
+
+    let variants_from = data
+        .variants
+        .iter()
+        .enumerate()
+        .filter(|(_, variant)| !is_catch_all(variant))
+        .map(|(i, variant)| {
+            let name = &variant.ident;
+            let value = discriminant_ident(i);
+            quote! {
+                #value => #ident::#name,
+            }
+        })
+        .chain(catch_all.iter().map(|(name, _)| {
+            quote! {
+                v => #ident::#name(v),
+            }
+        }))
+        .collect::<Vec<_>>();
+    let unknown_value = if catch_all.is_some() {
+        quote! {}
+    } else {
+        quote! {
+            _ => return Err(invalid()),
+        }
+    };
     let variants_to = data
         .variants
         .iter()
         .enumerate()
         .map(|(i, variant)| {
             let name = &variant.ident;
-            let value = if has_discriminant {
-                let v = &variant.discriminant.as_ref().unwrap().1;
-                quote! { #v }
+            if is_catch_all(variant) {
+                quote! {
+                    #ident::#name(v) => v,
+                }
             } else {
-                quote! { #i }
-            };
-            quote! {
-                #ident::#name => Ok(#value),
+                let value = discriminant_ident(i);
+                quote! {
+                    #ident::#name => #value,
+                }
             }
         })
         .collect::<Vec<_>>();
 
-    let impls = [8, 16, 32, 64, 128]
-        .iter()
-        .map(|n| {
-            ['u', 'i']
-                .iter()
-                .filter(|&&s| {
-                    let dec = if s == 'i' { 1 } else { 0 };
-                    n - dec >= ty_bits
-                })
-                .map(|s| {
-                    let variants_from = variants_from.iter();
-                    let variants_to = variants_to.iter();
-                    let ty = Ident::new(&format!("{}{}", s, n), Span::call_site());
-                    quote! {
-                        impl core::convert::TryFrom<#ty> for #ident {
-                            type Error = String;
-
-                            fn try_from(value: #ty) -> Result<Self, Self::Error> {
-                                match value {
-                                    #(#variants_from)*
-                                    _ => Err(format!("{} is not a valid enum value", value))
-                                }
+    let impls = conversion_types.iter().map(|name| {
+            let consts = consts.iter();
+            let consts_to = consts.clone();
+            let variants_from = variants_from.iter();
+            let variants_to = variants_to.iter();
+            let ty = Ident::new(name, Span::call_site());
+
+            let from = if catch_all.is_some() && fits(name, &value_ty.to_string()) {
+                quote! {
+                    impl core::convert::From<#ty> for #ident {
+                        #[allow(clippy::useless_conversion)]
+                        fn from(value: #ty) -> Self {
+                            #(#consts)*
+                            match <#value_ty as core::convert::From<#ty>>::from(value) {
+                                #(#variants_from)*
                             }
                         }
-                        impl core::convert::TryFrom<#ident> for #ty {
-                            type Error = String;
+                    }
+                }
+            } else {
+                quote! {
+                    impl core::convert::TryFrom<#ty> for #ident {
+                        type Error = #krate::TryFromPrimitiveError<Self, #ty>;
 
-                            fn try_from(value: #ident) -> Result<Self, Self::Error> {
-                                match value {
-                                    #(#variants_to)*
-                                }
-                            }
+                        #[allow(clippy::useless_conversion)]
+                        fn try_from(value: #ty) -> Result<Self, Self::Error> {
+                            #(#consts)*
+                            let invalid = || #krate::TryFromPrimitiveError::new(value, stringify!(#ident));
+                            let v = <#value_ty as core::convert::TryFrom<#ty>>::try_from(value)
+                                .map_err(|_| invalid())?;
+                            Ok(match v {
+                                #(#variants_from)*
+                                #unknown_value
+                            })
                         }
                     }
-                })
-                .collect::<Vec<_>>()
-        })
-        .flatten();
+                }
+            };
+            let to = if fits(&value_ty.to_string(), name) {
+                quote! {
+                    impl core::convert::From<#ident> for #ty {
+                        #[allow(clippy::useless_conversion)]
+                        fn from(value: #ident) -> Self {
+                            #(#consts_to)*
+                            let v = match value {
+                                #(#variants_to)*
+                            };
+                            <#ty as core::convert::From<#value_ty>>::from(v)
+                        }
+                    }
+                }
+            } else {
+                quote! {
+                    impl core::convert::TryFrom<#ident> for #ty {
+                        type Error = core::num::TryFromIntError;
+
+                        #[allow(clippy::useless_conversion)]
+                        fn try_from(value: #ident) -> Result<Self, Self::Error> {
+                            #(#consts_to)*
+                            let v = match value {
+                                #(#variants_to)*
+                            };
+                            <#ty as core::convert::TryFrom<#value_ty>>::try_from(v)
+                        }
+                    }
+                }
+            };
+            quote! {
+                #from
+                #to
+            }
+        });
 
-    let expanded = quote! {
+    Ok(quote! {
         #(#impls)*
+    })
+}
+
+fn discriminant_ident(i: usize) -> Ident {
+    Ident::new(&format!("DISCRIMINANT_{}", i), Span::call_site())
+}
+
+fn is_catch_all(variant: &Variant) -> bool {
+    variant.attrs.iter().any(|a| a.path.is_ident("unknown"))
+}
+
+/// Finds the `#[unknown]` variant that captures values without a variant of their own and
+/// returns its name and the integer type of its single field.
+fn catch_all_variant(data: &DataEnum) -> syn::Result<Option<(Ident, Ident)>> {
+    let mut catch_all = None;
+    for variant in &data.variants {
+        if !is_catch_all(variant) {
+            if let Fields::Unit = variant.fields {
+                continue;
+            }
+            return Err(syn::Error::new(
+                variant.span(),
+                "only unit variants and a single #[unknown] variant are supported",
+            ));
+        }
+        if catch_all.is_some() {
+            return Err(syn::Error::new(
+                variant.span(),
+                "only one variant can be marked #[unknown]",
+            ));
+        }
+
+        let ty = match &variant.fields {
+            Fields::Unnamed(f) if f.unnamed.len() == 1 => match &f.unnamed[0].ty {
+                Type::Path(p) if p.qself.is_none() && p.path.segments.len() == 1 => {
+                    Some(p.path.segments[0].ident.clone())
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        match ty {
+            Some(ty) if int_type(&ty.to_string()).is_some() => {
+                catch_all = Some((variant.ident.clone(), ty));
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    variant.fields.span(),
+                    "#[unknown] variant must have a single field of a primitive integer type",
+                ));
+            }
+        }
+    }
+    Ok(catch_all)
+}
+
+/// Path of the `deen` crate, or of the module re-exporting the items the derives use, given in
+/// `#[try_from_primitive(crate = "...")]`.
+fn crate_path(attrs: &[Attribute]) -> syn::Result<Path> {
+    struct CrateAttr(Path);
+
+    impl Parse for CrateAttr {
+        fn parse(input: ParseStream) -> syn::Result<Self> {
+            let content;
+            parenthesized!(content in input);
+            content.parse::<Token![crate]>()?;
+            content.parse::<Token![=]>()?;
+            let path: LitStr = content.parse()?;
+            Ok(CrateAttr(path.parse()?))
+        }
+    }
+
+    match attrs.iter().find(|a| a.path.is_ident("try_from_primitive")) {
+        Some(attr) => {
+            let CrateAttr(path) = syn::parse2(attr.tts.clone())?;
+            Ok(path)
+        }
+        None => Ok(syn::parse_quote!(deen)),
+    }
+}
+
+/// Integer type from `#[repr(...)]`, ignoring other hints such as `C` or `align(N)`.
+fn repr_type(attrs: &[Attribute]) -> syn::Result<Option<Ident>> {
+    let mut repr = None;
+    for attr in attrs.iter().filter(|a| a.path.is_ident("repr")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(l) => l,
+            m => {
+                return Err(syn::Error::new(
+                    m.span(),
+                    "wrong content of \"repr\" attribute",
+                ))
+            }
+        };
+        for nested in &list.nested {
+            if let NestedMeta::Meta(Meta::Word(i)) = nested {
+                if int_type(&i.to_string()).is_some() {
+                    if repr.is_some() {
+                        return Err(syn::Error::new(
+                            i.span(),
+                            "conflicting integer representation",
+                        ));
+                    }
+                    repr = Some(i.clone());
+                }
+            }
+        }
+    }
+    Ok(repr)
+}
+
+/// Signedness and width of a primitive integer type name. `isize` and `usize` have no width
+/// known to the macro and are reported as `None` bits.
+fn int_type(name: &str) -> Option<(bool, Option<u32>)> {
+    match name {
+        "usize" => return Some((false, None)),
+        "isize" => return Some((true, None)),
+        _ => {}
+    }
+    let (s, bits) = name.split_at(1);
+    let signed = match s {
+        "u" => false,
+        "i" => true,
+        _ => return None,
     };
+    match bits.parse() {
+        Ok(bits @ 8) | Ok(bits @ 16) | Ok(bits @ 32) | Ok(bits @ 64) | Ok(bits @ 128) => {
+            Some((signed, Some(bits)))
+        }
+        _ => None,
+    }
+}
 
-    TokenStream::from(expanded)
+/// Whether the standard library has `From<from> for to`, i.e. every value of `from` can be
+/// represented by `to` on every target.
+fn fits(from: &str, to: &str) -> bool {
+    match (int_type(from), int_type(to)) {
+        (Some((fs, Some(fb))), Some((ts, Some(tb)))) => {
+            if fs == ts {
+                fb <= tb
+            } else {
+                !fs && fb < tb
+            }
+        }
+        (Some((fs, Some(fb))), Some((ts, None))) => {
+            if fs == ts {
+                fb <= 16
+            } else {
+                !fs && fb < 16
+            }
+        }
+        (Some((_, None)), Some((_, None))) => from == to,
+        _ => false,
+    }
+}
+
+/// Adds `deen()` to a `TryFromPrimitive` enum, returning a `deen::Enum` deener that stores the
+/// enum with the integer deener given in `#[deen(...)]`, e.g. `#[deen(U16be)]`. The path of
+/// `deen` can be changed as for `TryFromPrimitive`. Its schema lists the variants of fieldless
+/// enums, except for `repr(u128)` ones.
+#[proc_macro_derive(DeenEnum, attributes(deen, try_from_primitive))]
+pub fn deen_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match deen_enum_impl(input) {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn deen_enum_impl(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let krate = crate_path(&input.attrs)?;
+    let deener = deener_path(&input)?;
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // Only fieldless enums can be cast to get the values of their variants. Values of `u128`
+    // enums may not fit the `i128` of the schema, so they are left out.
+    let unsigned_128 = repr_type(&input.attrs)?.is_some_and(|r| r == "u128");
+    let variants = match &input.data {
+        Data::Enum(data)
+            if input.generics.params.is_empty()
+                && !unsigned_128
+                && data
+                    .variants
+                    .iter()
+                    .all(|v| matches!(v.fields, Fields::Unit)) =>
+        {
+            data.variants
+                .iter()
+                .map(|v| {
+                    let name = &v.ident;
+                    let text = name.to_string();
+                    quote! { (#text, #ident::#name as i128) }
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+    let name = ident.to_string();
+
+    Ok(quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            pub fn deen() -> #krate::Enum<#deener, Self> {
+                #krate::Enum::new(#deener).with_variants(#name, &[#(#variants),*])
+            }
+        }
+    })
+}
+
+/// Path of the unit deener struct given in `#[deen(...)]`.
+fn deener_path(input: &DeriveInput) -> syn::Result<Path> {
+    struct DeenerAttr(Path);
+
+    impl Parse for DeenerAttr {
+        fn parse(input: ParseStream) -> syn::Result<Self> {
+            let content;
+            parenthesized!(content in input);
+            Ok(DeenerAttr(content.parse()?))
+        }
+    }
+
+    let attr = input
+        .attrs
+        .iter()
+        .find(|a| a.path.is_ident("deen"))
+        .ok_or_else(|| {
+            syn::Error::new(
+                input.ident.span(),
+                "missing #[deen(...)] attribute with the integer deener",
+            )
+        })?;
+    let DeenerAttr(path) = syn::parse2(attr.tts.clone())?;
+    Ok(path)
+}
+
+/// Implements `deen::Flag` for a fieldless enum whose variants are bit flags, so that sets of
+/// them can be held in `deen::Flags`. The enum needs an unsigned `#[repr]` and `Copy`. The path
+/// of `deen` can be changed as for `TryFromPrimitive`.
+#[proc_macro_derive(Bitflags, attributes(try_from_primitive))]
+pub fn bitflags(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match bitflags_impl(input) {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn bitflags_impl(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let krate = crate_path(&input.attrs)?;
+    let ident = &input.ident;
+    let repr = match repr_type(&input.attrs)? {
+        Some(repr) if !int_type(&repr.to_string()).unwrap().0 => repr,
+        _ => {
+            return Err(syn::Error::new(
+                ident.span(),
+                "Bitflags requires an unsigned integer #[repr]",
+            ));
+        }
+    };
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => return Err(syn::Error::new(ident.span(), "Can only derive flag enums")),
+    };
+
+    let flags = data
+        .variants
+        .iter()
+        .map(|variant| {
+            if let Fields::Unit = variant.fields {
+                let name = &variant.ident;
+                let label = name.to_string();
+                Ok(quote! {
+                    (#ident::#name, #label),
+                })
+            } else {
+                Err(syn::Error::new(variant.span(), "flags can not have fields"))
+            }
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        impl #krate::Flag for #ident {
+            type Bits = #repr;
+
+            const FLAGS: &'static [(Self, &'static str)] = &[
+                #(#flags)*
+            ];
+
+            fn bits(self) -> #repr {
+                self as #repr
+            }
+        }
+
+        impl<R: Into<#krate::Flags<#ident>>> core::ops::BitOr<R> for #ident {
+            type Output = #krate::Flags<#ident>;
+
+            fn bitor(self, rhs: R) -> #krate::Flags<#ident> {
+                #krate::Flags::from(self) | rhs
+            }
+        }
+    })
 }